@@ -0,0 +1,111 @@
+use std::io::{self, BufRead, Write};
+
+use hnefatafl::game::tafl::engine_protocol::*;
+use hnefatafl::game::tafl::figure::Side;
+use hnefatafl::game::tafl::notation::*;
+use hnefatafl::game::tafl::rules::*;
+
+const USAGE: &str = "\
+Usage: hnefatafl-dummy-engine [REPLY]...
+
+A minimal engine for testing the engine protocol. It answers `play` with the first legal move of
+the position, or with the provided replies in order while there are any left, e.g.
+`hnefatafl-dummy-engine \"move a1-a2\" \"error thinking\"`. A reply may consist of several
+lines.
+
+Options:
+    -h, --help  print this message";
+
+fn main() {
+    let mut replies: Vec<String> = std::env::args().skip(1).collect();
+
+    if replies.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    replies.reverse();
+
+    if let Err(err) = run(replies) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Speaks the engine protocol on stdin/stdout until `goodbye` is received or stdin is closed.
+fn run(mut replies: Vec<String>) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    let mut rules: Option<Rules> = None;
+    let mut position: Option<ParsedPosition> = None;
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let rows = rules.as_ref().map(|rules| rules.rows).unwrap_or(0);
+
+        let reply = match GuiMessage::parse(&line) {
+            Ok(GuiMessage::Hello) => Some("hello dummy".to_string()),
+            Ok(GuiMessage::Rules {
+                rows,
+                cols,
+                throne_position,
+                end_positions,
+            }) => {
+                rules = Some(Rules {
+                    rows,
+                    cols,
+                    throne_position,
+                    end_positions,
+                    no_legal_moves: NoLegalMovesOutcome::default(),
+                });
+                None
+            }
+            Ok(GuiMessage::Position { position: data }) => {
+                position = parse_position(&data).ok();
+                None
+            }
+            Ok(GuiMessage::Play { side }) => Some(
+                replies
+                    .pop()
+                    .unwrap_or_else(|| first_legal_move(&rules, &position, side).to_line(rows)),
+            ),
+            Ok(GuiMessage::Goodbye) => break,
+            // `clock` and unknown messages
+            _ => None,
+        };
+
+        if let Some(reply) = reply {
+            writeln!(stdout, "{}", reply)?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+fn first_legal_move(
+    rules: &Option<Rules>,
+    position: &Option<ParsedPosition>,
+    side: Side,
+) -> EngineMessage {
+    let (Some(rules), Some(position)) = (rules, position) else {
+        return EngineMessage::Error {
+            message: "rules and position should be sent before play".to_string(),
+        };
+    };
+
+    let legal_move = TaflGame::new(rules.clone(), &position.figures, side)
+        .ok()
+        .and_then(|game| game.legal_moves().first().copied());
+
+    match legal_move {
+        Some(legal_move) => EngineMessage::Move {
+            from: legal_move.from,
+            to: legal_move.to,
+        },
+        None => EngineMessage::Error {
+            message: "there are no legal moves".to_string(),
+        },
+    }
+}
@@ -0,0 +1,91 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use hnefatafl::game::tafl::engine_protocol::EngineCommand;
+use hnefatafl::tournament::TournamentOptions;
+
+const USAGE: &str = "\
+Usage: hnefatafl-match [OPTIONS] --engine <COMMAND> --engine <COMMAND>...
+
+Plays games between two engines, or a round-robin among more, switching sides after each game.
+
+Options:
+    --engine <COMMAND>     an engine speaking the engine protocol, e.g. \"hnefatafl --engine\"
+    --games <N>            the number of games each pair of engines plays (default: 10)
+    --time <SECONDS>       the time on the clock of each side (default: 60)
+    --variant <NAME>       the variant to play (default: hnefatafl)
+    --max-moves <N>        adjudicate games as draws after N moves (default: 500)
+    --records <DIR>        the directory the record files are written to (default: records)
+    -h, --help             print this message";
+
+fn main() {
+    let mut options = TournamentOptions {
+        engines: vec![],
+        games: 10,
+        time: Duration::from_secs(60),
+        variant: "hnefatafl".to_string(),
+        max_moves: 500,
+        records: PathBuf::from("records"),
+    };
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| {
+                eprintln!("`{}` should be followed by a value\n\n{}", arg, USAGE);
+                std::process::exit(2);
+            })
+        };
+
+        match arg.as_str() {
+            "--engine" => {
+                let command = value();
+                match EngineCommand::parse(&command) {
+                    Ok(command) => options.engines.push(command),
+                    Err(err) => {
+                        eprintln!("`{}` is not a valid command: {}\n\n{}", command, err, USAGE);
+                        std::process::exit(2);
+                    }
+                }
+            }
+            "--games" => options.games = number(&arg, value()) as usize,
+            "--time" => options.time = Duration::from_secs(number(&arg, value())),
+            "--variant" => options.variant = value(),
+            "--max-moves" => options.max_moves = number(&arg, value()) as usize,
+            "--records" => options.records = PathBuf::from(value()),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                eprintln!("unknown argument `{}`\n\n{}", arg, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if options.engines.len() < 2 {
+        eprintln!("at least two engines should play\n\n{}", USAGE);
+        std::process::exit(2);
+    }
+
+    if let Err(err) = hnefatafl::tournament::run(&options) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Parses the positive number given for the option `name`, exiting if it isn't one.
+fn number(name: &str, value: String) -> u64 {
+    match value.parse::<u64>() {
+        Ok(number) if 0 < number => number,
+        _ => {
+            eprintln!(
+                "`{}` should be followed by a positive number\n\n{}",
+                name, USAGE
+            );
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use hnefatafl::game::tafl::ai::SearchOptions;
+use hnefatafl::self_play::*;
+
+const USAGE: &str = "\
+Usage: hnefatafl-selfplay [OPTIONS]
+
+Lets the built-in AI play against itself and writes the searched positions with their scores
+and the results of the games as training samples.
+
+Options:
+    --games <N>              the number of games to play (default: 100)
+    --depth <N>              the maximum search depth (default: 3)
+    --move-time <MS>         the time limit of a search in milliseconds (default: 500)
+    --random-moves <N>       the number of random moves starting each game (default: 4)
+    --max-moves <N>          adjudicate games as draws after N moves (default: 300)
+    --variant <NAME>         the variant to play (default: hnefatafl)
+    --format <jsonl|binary>  the format of the samples (default: jsonl)
+    --output <FILE>          the file the samples are written to (default: samples.jsonl or
+                             samples.bin)
+    --seed <N>               the seed of the random moves (default: random)
+    -h, --help               print this message";
+
+fn main() {
+    let mut options = SelfPlayOptions {
+        games: 100,
+        search: SearchOptions {
+            max_depth: 3,
+            time_limit: Duration::from_millis(500),
+        },
+        random_moves: 4,
+        max_moves: 300,
+        variant: "hnefatafl".to_string(),
+        format: SampleFormat::Jsonl,
+        output: PathBuf::new(),
+        seed: None,
+    };
+
+    let mut output: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next().unwrap_or_else(|| {
+                eprintln!("`{}` should be followed by a value\n\n{}", arg, USAGE);
+                std::process::exit(2);
+            })
+        };
+
+        match arg.as_str() {
+            "--games" => options.games = number(&arg, value()) as usize,
+            "--depth" => options.search.max_depth = number(&arg, value()) as u32,
+            "--move-time" => {
+                options.search.time_limit = Duration::from_millis(number(&arg, value()))
+            }
+            "--random-moves" => options.random_moves = number(&arg, value()) as usize,
+            "--max-moves" => options.max_moves = number(&arg, value()) as usize,
+            "--variant" => options.variant = value(),
+            "--format" => {
+                options.format = match value().as_str() {
+                    "jsonl" => SampleFormat::Jsonl,
+                    "binary" => SampleFormat::Binary,
+                    _ => {
+                        eprintln!("`--format` should be jsonl or binary\n\n{}", USAGE);
+                        std::process::exit(2);
+                    }
+                }
+            }
+            "--output" => output = Some(PathBuf::from(value())),
+            "--seed" => options.seed = Some(number(&arg, value())),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                eprintln!("unknown argument `{}`\n\n{}", arg, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    options.output = output.unwrap_or_else(|| match options.format {
+        SampleFormat::Jsonl => PathBuf::from("samples.jsonl"),
+        SampleFormat::Binary => PathBuf::from("samples.bin"),
+    });
+
+    if let Err(err) = hnefatafl::self_play::run(&options) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Parses the number given for the option `name`, exiting if it isn't one.
+fn number(name: &str, value: String) -> u64 {
+    match value.parse::<u64>() {
+        Ok(number) => number,
+        _ => {
+            eprintln!("`{}` should be followed by a number\n\n{}", name, USAGE);
+            std::process::exit(2);
+        }
+    }
+}
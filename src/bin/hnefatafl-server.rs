@@ -0,0 +1,36 @@
+const USAGE: &str = "\
+Usage: hnefatafl-server [OPTIONS]
+
+Options:
+    --port <PORT>    the port to accept WebSocket connections on (default: 7878)
+    -h, --help       print this message";
+
+fn main() {
+    let mut port: u16 = 7878;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--port" => {
+                let Some(value) = args.next().and_then(|value| value.parse().ok()) else {
+                    eprintln!("`--port` should be followed by a port\n\n{}", USAGE);
+                    std::process::exit(2);
+                };
+                port = value;
+            }
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ => {
+                eprintln!("unknown argument `{}`\n\n{}", arg, USAGE);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if let Err(err) = hnefatafl::server::run(port) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,302 @@
+use std::time::Duration;
+
+use hnefatafl::game::tafl::engine_protocol::EngineCommand;
+use hnefatafl::game::tafl::figure::Side;
+use hnefatafl::game::tafl::game_setup::GameSetup;
+use hnefatafl::game::tafl::network::*;
+use hnefatafl::game::tafl::notation::parse_side;
+use hnefatafl::game::tafl::online::OnlineServer;
+use hnefatafl::game::tafl::players::*;
+use hnefatafl::game::tafl::record::RecordFile;
+use hnefatafl::game::tafl::rules::VARIANTS;
+use hnefatafl::game::tafl::time_control::*;
+
+pub const USAGE: &str = "\
+Usage: hnefatafl [OPTIONS]
+
+Options:
+    --variant <NAME>               the variant to play (default: hnefatafl)
+    --time <SECONDS>               the main time on the clock of each side (default: 600)
+    --increment <SECONDS>          add SECONDS to the clock after every move
+    --delay <SECONDS>              start the clock only SECONDS after every move
+    --byo-yomi <PERIODS>x<SECONDS> give PERIODS periods of SECONDS once the main time is over
+    --move-limit <SECONDS>         lose when a single move takes longer than SECONDS
+    --attacker <human|ai>          who plays the attackers (default: human)
+    --defender <human|ai>          who plays the defenders (default: human)
+    --position <POSITION>          start from a position string, e.g. /3t3/7/.../
+    --load <FILE>                  continue the game stored in a record file
+    --attacker-engine <COMMAND>    let an external engine play the attackers
+    --defender-engine <COMMAND>    let an external engine play the defenders
+    --engine                       run the built-in AI as an engine on stdin/stdout
+    --host <PORT>                  wait for another player to join on PORT
+    --join <ADDRESS>               join the game hosted on ADDRESS, e.g. 192.168.0.2:7878
+    --spectate <ADDRESS>           watch the games hosted on ADDRESS
+    --side <attackers|defenders>   the side played when hosting (default: attackers)
+    --server <ADDRESS>             the server of the multiplayer lobby (default: localhost:7878)
+    --tui                          play in the terminal instead of a window
+    -h, --help                     print this message
+
+The game starts right away, skipping the main menu, when --variant, --attacker, --defender,
+--position, --load or one of the time options is used.";
+
+#[derive(Default)]
+pub struct CliOptions {
+    pub players: Players,
+    pub network_mode: Option<NetworkMode>,
+    pub online_server: Option<OnlineServer>,
+    pub time_control: Option<TimeControl>,
+    pub game_setup: Option<GameSetup>,
+    pub engine: bool,
+    pub tui: bool,
+    pub help: bool,
+}
+
+/// Parses the command line arguments (excluding the program name).
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<CliOptions, String> {
+    let mut options = CliOptions::default();
+    let mut host_side: Option<Side> = None;
+    let mut set_up = false;
+    let mut variant: Option<String> = None;
+    let mut position: Option<String> = None;
+    let mut record: Option<RecordFile> = None;
+    let mut time: Option<Duration> = None;
+    let mut bonus: Option<TimeBonus> = None;
+    let mut move_limit: Option<Duration> = None;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--variant" => {
+                let name = value(&mut args, &arg)?;
+                if !VARIANTS.contains(&name.as_str()) {
+                    return Err(format!(
+                        "unknown variant `{}`, known are: {}",
+                        name,
+                        VARIANTS.join(", ")
+                    ));
+                }
+
+                variant = Some(name);
+                set_up = true;
+            }
+            "--time" => {
+                time = Some(seconds(&value(&mut args, &arg)?)?);
+                set_up = true;
+            }
+            "--increment" | "--delay" | "--byo-yomi" => {
+                if bonus.is_some() {
+                    return Err(
+                        "only one of `--increment`, `--delay` and `--byo-yomi` can be used"
+                            .to_string(),
+                    );
+                }
+
+                let data = value(&mut args, &arg)?;
+                bonus = Some(match arg.as_str() {
+                    "--increment" => TimeBonus::Increment(seconds(&data)?),
+                    "--delay" => TimeBonus::Delay(seconds(&data)?),
+                    _ => {
+                        let Some((periods, period)) = data.split_once('x') else {
+                            return Err(format!("`{}` should look like 5x30", data));
+                        };
+
+                        let Some(periods) = periods.parse::<u32>().ok().filter(|n| 0 < *n) else {
+                            return Err(format!("`{}` is not a number of periods", periods));
+                        };
+
+                        TimeBonus::ByoYomi {
+                            periods,
+                            period: seconds(period)?,
+                        }
+                    }
+                });
+                set_up = true;
+            }
+            "--move-limit" => {
+                move_limit = Some(seconds(&value(&mut args, &arg)?)?);
+                set_up = true;
+            }
+            "--attacker" | "--defender" => {
+                let side = match arg.as_str() {
+                    "--attacker" => Side::Attacker,
+                    _ => Side::Defender,
+                };
+
+                let player_kind = match value(&mut args, &arg)?.as_str() {
+                    "human" => PlayerKind::Human,
+                    "ai" => PlayerKind::built_in_ai()?,
+                    other => return Err(format!("`{}` should be human or ai", other)),
+                };
+
+                options.players.set(side, player_kind);
+                set_up = true;
+            }
+            "--position" => {
+                position = Some(value(&mut args, &arg)?);
+                set_up = true;
+            }
+            "--load" => {
+                let path = value(&mut args, &arg)?;
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("failed to read `{}`: {}", path, err))?;
+
+                record = Some(
+                    RecordFile::parse(&data)
+                        .map_err(|err| format!("failed to load `{}`: {}", path, err))?,
+                );
+                set_up = true;
+            }
+            "--attacker-engine" | "--defender-engine" => {
+                let side = match arg.as_str() {
+                    "--attacker-engine" => Side::Attacker,
+                    _ => Side::Defender,
+                };
+
+                let command = value(&mut args, &arg)?;
+                let command = EngineCommand::parse(&command)
+                    .map_err(|err| format!("`{}` is not a valid command: {}", command, err))?;
+
+                options
+                    .players
+                    .set(side, PlayerKind::ExternalEngine { command });
+            }
+            "--engine" => options.engine = true,
+            "--host" => {
+                let port = value(&mut args, &arg)?;
+                let port = port
+                    .parse::<u16>()
+                    .map_err(|_| format!("`{}` is not a valid port", port))?;
+
+                options.network_mode = Some(NetworkMode::Host {
+                    port,
+                    side: Side::Attacker,
+                });
+            }
+            "--join" => {
+                options.network_mode = Some(NetworkMode::Join {
+                    address: value(&mut args, &arg)?,
+                });
+            }
+            "--spectate" => {
+                options.network_mode = Some(NetworkMode::Spectate {
+                    address: value(&mut args, &arg)?,
+                });
+            }
+            "--side" => {
+                let side = value(&mut args, &arg)?;
+                host_side = Some(parse_side(&side).map_err(|err| err.to_string())?);
+            }
+            "--server" => {
+                options.online_server = Some(OnlineServer {
+                    address: value(&mut args, &arg)?,
+                });
+            }
+            "--tui" => options.tui = true,
+            "-h" | "--help" => options.help = true,
+            _ => return Err(format!("unknown argument `{}`", arg)),
+        }
+    }
+
+    match &mut options.network_mode {
+        Some(NetworkMode::Host { side, .. }) => {
+            *side = host_side.unwrap_or(Side::Attacker);
+        }
+        Some(NetworkMode::Join { .. } | NetworkMode::Spectate { .. }) if host_side.is_some() => {
+            return Err("`--side` is decided by the host".to_string());
+        }
+        _ if host_side.is_some() => {
+            return Err("`--side` can only be used with `--host`".to_string());
+        }
+        _ => (),
+    }
+
+    if options.network_mode.is_some()
+        && (!options.players.is_human(Side::Attacker) || !options.players.is_human(Side::Defender))
+    {
+        return Err("engines can't play in network games".to_string());
+    }
+
+    if options.tui {
+        if options.network_mode.is_some() {
+            return Err("network games can't be played in the terminal".to_string());
+        }
+
+        if !options.players.is_human(Side::Attacker) || !options.players.is_human(Side::Defender) {
+            return Err("only two players on this computer can play in the terminal".to_string());
+        }
+    }
+
+    if set_up {
+        if options.network_mode.is_some() {
+            return Err("only games played on this computer can be set up".to_string());
+        }
+
+        // the time control of the record is kept unless it is changed explicitly
+        let mut time_control = record
+            .as_ref()
+            .map(|record| record.time_control.clone())
+            .unwrap_or_default();
+
+        if let Some(time) = time {
+            time_control.time = time;
+            time_control.unlimited = false;
+        }
+        if let Some(bonus) = bonus {
+            time_control.bonus = bonus;
+        }
+        if move_limit.is_some() {
+            time_control.move_limit = move_limit;
+        }
+
+        options.time_control = Some(time_control);
+
+        options.game_setup = Some(game_setup(variant, position, record)?);
+    }
+
+    Ok(options)
+}
+
+/// Sets up the game from the options, the ones given explicitly win over the ones of `record`.
+fn game_setup(
+    variant: Option<String>,
+    position: Option<String>,
+    record: Option<RecordFile>,
+) -> Result<GameSetup, String> {
+    let Some(record) = record else {
+        let variant = variant.unwrap_or_else(|| "hnefatafl".to_string());
+
+        return GameSetup::new(&variant, position.as_deref(), Side::Attacker, vec![], None)
+            .map_err(|err| err.to_string());
+    };
+
+    if position.is_some() {
+        return Err("`--position` can't be used with `--load`".to_string());
+    }
+
+    if variant.is_some_and(|variant| variant != record.variant) {
+        return Err(format!("the record is a game of {}", record.variant));
+    }
+
+    GameSetup::new(
+        &record.variant,
+        Some(&record.initial_position),
+        record.first_side,
+        record.entries,
+        record.outcome,
+    )
+    .map_err(|err| format!("failed to replay the record: {}", err))
+}
+
+/// Parses a positive number of seconds.
+fn seconds(data: &str) -> Result<Duration, String> {
+    data.parse::<u64>()
+        .ok()
+        .filter(|secs| 0 < *secs)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("`{}` is not a valid number of seconds", data))
+}
+
+/// Gets the value following the option `name`.
+fn value(args: &mut impl Iterator<Item = String>, name: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("`{}` should be followed by a value", name))
+}
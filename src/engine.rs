@@ -0,0 +1,244 @@
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use hnefatafl::game::tafl::ai::*;
+use hnefatafl::game::tafl::engine_protocol::*;
+use hnefatafl::game::tafl::figure::Side;
+use hnefatafl::game::tafl::notation::*;
+use hnefatafl::game::tafl::rules::*;
+use hnefatafl::game::tafl::time_control::TimeBonus;
+
+/// Runs the built-in AI as an engine speaking the engine protocol on stdin/stdout.
+///
+/// Returns when `goodbye` is received or stdin is closed.
+pub fn run() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    let mut rules: Option<Rules> = None;
+    let mut position: Option<ParsedPosition> = None;
+    let mut clock: Option<EngineClock> = None;
+
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let rows = rules.as_ref().map(|rules| rules.rows).unwrap_or(0);
+
+        let reply = match GuiMessage::parse(&line) {
+            Ok(GuiMessage::Hello) => Some(EngineMessage::Hello {
+                name: format!("hnefatafl {}", env!("CARGO_PKG_VERSION")),
+            }),
+            Ok(GuiMessage::Rules {
+                rows,
+                cols,
+                throne_position,
+                end_positions,
+            }) => {
+                rules = Some(Rules {
+                    rows,
+                    cols,
+                    throne_position,
+                    end_positions,
+                    no_legal_moves: NoLegalMovesOutcome::default(),
+                });
+                None
+            }
+            Ok(GuiMessage::Position { position: data }) => match parse_position(&data) {
+                Ok(parsed) => {
+                    position = Some(parsed);
+                    None
+                }
+                Err(err) => Some(EngineMessage::Error {
+                    message: err.to_string(),
+                }),
+            },
+            Ok(GuiMessage::Clock { clock: data }) => {
+                clock = Some(data);
+                None
+            }
+            Ok(GuiMessage::Play { side }) => Some(play(&rules, &position, &clock, side)),
+            Ok(GuiMessage::Goodbye) => break,
+            // unknown messages are ignored, they may be extensions of the protocol
+            Err(err) => {
+                eprintln!("ignoring `{}`: {}", line, err);
+                None
+            }
+        };
+
+        if let Some(reply) = reply {
+            writeln!(stdout, "{}", reply.to_line(rows))?;
+            stdout.flush()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Searches for a move of `side` in the last received position.
+fn play(
+    rules: &Option<Rules>,
+    position: &Option<ParsedPosition>,
+    clock: &Option<EngineClock>,
+    side: Side,
+) -> EngineMessage {
+    let (Some(rules), Some(position)) = (rules, position) else {
+        return EngineMessage::Error {
+            message: "rules and position should be sent before play".to_string(),
+        };
+    };
+
+    if rules.rows != position.rows || rules.cols != position.cols {
+        return EngineMessage::Error {
+            message: "position should have the size of the board".to_string(),
+        };
+    }
+
+    let game = match TaflGame::new(rules.clone(), &position.figures, side) {
+        Ok(game) => game,
+        Err(err) => {
+            return EngineMessage::Error {
+                message: err.to_string(),
+            }
+        }
+    };
+
+    let mut options = SearchOptions::default();
+
+    if let Some(clock) = clock {
+        options.time_limit = time_for_move(clock, side, options.time_limit);
+    }
+
+    match search(&game, &options).best_move {
+        Some(best_move) => EngineMessage::Move {
+            from: best_move.from,
+            to: best_move.to,
+        },
+        None => EngineMessage::Error {
+            message: "there are no legal moves".to_string(),
+        },
+    }
+}
+
+/// Decides how long to search for a move of `side`, at most `max`.
+fn time_for_move(clock: &EngineClock, side: Side, max: Duration) -> Duration {
+    let time_control = &clock.time_control;
+    if time_control.unlimited {
+        return max;
+    }
+
+    let own = clock.side(side);
+
+    // use a small part of the main time, assuming there are still many moves to go
+    let mut time = own.remaining / 30;
+
+    match time_control.bonus {
+        TimeBonus::None => (),
+        // the increment is added again after the move, as long as enough main time is left
+        TimeBonus::Increment(increment) => time += (increment * 3 / 4).min(own.remaining / 2),
+        // the delay doesn't count against the main time
+        TimeBonus::Delay(delay) => time += delay * 3 / 4,
+        // a move made within the period keeps it
+        TimeBonus::ByoYomi { period, .. } if 0 < own.periods => time = time.max(period * 3 / 4),
+        TimeBonus::ByoYomi { .. } => (),
+    }
+
+    if let Some(move_limit) = time_control.move_limit {
+        time = time.min(move_limit * 3 / 4);
+    }
+
+    time.clamp(Duration::from_millis(100), max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hnefatafl::game::tafl::time_control::{ClockState, TimeControl};
+
+    const MAX: Duration = Duration::from_secs(10);
+
+    fn clock(remaining: u64, periods: u32, bonus: TimeBonus) -> EngineClock {
+        let side_clock = ClockState {
+            remaining: Duration::from_secs(remaining),
+            periods,
+        };
+
+        EngineClock {
+            attacker: side_clock,
+            defender: ClockState::default(),
+            time_control: TimeControl {
+                bonus,
+                ..TimeControl::default()
+            },
+        }
+    }
+
+    #[test]
+    fn a_part_of_the_main_time_is_used() {
+        let clock = clock(60, 0, TimeBonus::None);
+
+        assert_eq!(
+            time_for_move(&clock, Side::Attacker, MAX),
+            Duration::from_secs(2)
+        );
+        // the defenders are out of time, the search is still as long as the shortest allowed
+        assert_eq!(
+            time_for_move(&clock, Side::Defender, MAX),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn games_without_a_clock_use_the_longest_search() {
+        let clock = EngineClock {
+            time_control: TimeControl {
+                unlimited: true,
+                ..TimeControl::default()
+            },
+            ..EngineClock::default()
+        };
+
+        assert_eq!(time_for_move(&clock, Side::Attacker, MAX), MAX);
+    }
+
+    #[test]
+    fn bonuses_add_to_the_time() {
+        let increment = clock(60, 0, TimeBonus::Increment(Duration::from_secs(4)));
+        assert_eq!(
+            time_for_move(&increment, Side::Attacker, MAX),
+            Duration::from_secs(5)
+        );
+
+        let delay = clock(0, 0, TimeBonus::Delay(Duration::from_secs(4)));
+        assert_eq!(
+            time_for_move(&delay, Side::Attacker, MAX),
+            Duration::from_secs(3)
+        );
+    }
+
+    #[test]
+    fn byo_yomi_periods_are_used_after_the_main_time() {
+        let bonus = TimeBonus::ByoYomi {
+            periods: 3,
+            period: Duration::from_secs(8),
+        };
+
+        assert_eq!(
+            time_for_move(&clock(0, 2, bonus), Side::Attacker, MAX),
+            Duration::from_secs(6)
+        );
+        assert_eq!(
+            time_for_move(&clock(0, 0, bonus), Side::Attacker, MAX),
+            Duration::from_millis(100)
+        );
+    }
+
+    #[test]
+    fn moves_stay_within_the_move_limit() {
+        let mut clock = clock(600, 0, TimeBonus::None);
+        clock.time_control.move_limit = Some(Duration::from_secs(4));
+
+        assert_eq!(
+            time_for_move(&clock, Side::Attacker, MAX),
+            Duration::from_secs(3)
+        );
+    }
+}
@@ -3,24 +3,41 @@ use bevy::window::close_on_esc;
 
 use self::camera::*;
 use self::main_menu::*;
+use self::settings::SettingsPlugin;
+use self::tafl::game_setup::GameSetup;
 use self::tafl::*;
+use self::theme::ThemePlugin;
 
 mod camera;
 mod main_menu;
-mod tafl;
+pub mod settings;
+pub mod tafl;
+pub mod theme;
+pub mod widgets;
 
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(CameraPlugin)
+        // the settings come first, the other plugins start from them
+        app.add_plugins(SettingsPlugin)
+            .add_plugins(ThemePlugin)
+            .add_plugins(CameraPlugin)
             .add_plugins(MainMenuPlugin)
             .add_plugins(TaflPlugin)
+            .add_systems(
+                Startup,
+                start_set_up_game.run_if(resource_exists::<GameSetup>),
+            )
             .add_systems(
                 Update,
                 (
                     close_on_esc.run_if(in_state(GameState::MainMenu)),
-                    main_menu_on_esc.run_if(not(in_state(GameState::MainMenu))),
+                    // games have a pause menu instead
+                    main_menu_on_esc.run_if(
+                        not(in_state(GameState::MainMenu))
+                            .and_then(not(in_state(GameState::InGame))),
+                    ),
                 ),
             )
             .init_state::<GameState>();
@@ -31,6 +48,14 @@ impl Plugin for GamePlugin {
 enum GameState {
     #[default]
     MainMenu,
+    // the choices before a game, see `main_menu::new_game`
+    NewGame,
+    // the save slots, see `main_menu::load_game`
+    LoadGame,
+    // the lobby of the server, see `main_menu::multiplayer`
+    Multiplayer,
+    // see `main_menu::settings_menu`
+    Settings,
     InGame,
 }
 
@@ -42,3 +67,8 @@ fn main_menu_on_esc(
         next_game_state.set(GameState::MainMenu);
     }
 }
+
+// a set up game skips the main menu
+fn start_set_up_game(mut next_game_state: ResMut<NextState<GameState>>) {
+    next_game_state.set(GameState::InGame);
+}
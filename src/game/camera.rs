@@ -1,11 +1,26 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    ui::RelativeCursorPosition,
+    window::PrimaryWindow,
+};
+
+// how far the view can be zoomed in and out, relative to the fitted view
+const MIN_ZOOM: f32 = 0.5;
+const MAX_ZOOM: f32 = 6.;
+// how much a single line of the mouse wheel zooms
+const ZOOM_PER_LINE: f32 = 1.1;
+// how many pixels scrolled with a touchpad make up a line
+const PIXELS_PER_LINE: f32 = 20.;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_camera)
-            .add_systems(PreUpdate, track_mouse_position);
+            .add_systems(PreUpdate, track_mouse_position)
+            .add_systems(Update, (zoom_camera, pan_camera, fit_camera).chain())
+            .init_resource::<CameraFit>();
     }
 }
 
@@ -17,11 +32,50 @@ pub struct MousePositionTracker {
     pub mouse_world_position: Option<Vec2>,
 }
 
+/// The area of the world kept in view, set while a game is shown.
+///
+/// The camera is scaled so that the area fills `window_part` of the window whenever the window is
+/// resized, the players can zoom and move the view from there.
+#[derive(Resource, Debug, Clone)]
+pub struct CameraFit {
+    /// The size of the area around the origin, None to show the world unscaled.
+    pub area: Option<Vec2>,
+    /// The part of the width and the height of the window the area fills, the rest is left to the
+    /// ui.
+    pub window_part: Vec2,
+}
+
+impl Default for CameraFit {
+    fn default() -> Self {
+        Self {
+            area: None,
+            window_part: Vec2::ONE,
+        }
+    }
+}
+
+/// How far the view is zoomed in, and moved by dragging with the right or middle mouse button.
+#[derive(Component, Debug)]
+pub struct CameraView {
+    pub zoom: f32,
+    pub pan: Vec2,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.,
+            pan: Vec2::ZERO,
+        }
+    }
+}
+
 fn spawn_camera(mut commands: Commands) {
     commands.spawn((
         Camera2dBundle::default(),
         MainCamera,
         MousePositionTracker::default(),
+        CameraView::default(),
     ));
 }
 
@@ -36,3 +90,115 @@ fn track_mouse_position(
             .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor));
     }
 }
+
+/// Zooms towards the cursor with the mouse wheel, the point under the cursor stays in place.
+fn zoom_camera(
+    mut event: EventReader<MouseWheel>,
+    camera_fit: Res<CameraFit>,
+    q_scrollable: Query<&RelativeCursorPosition>,
+    mut q_camera: Query<(&mut CameraView, &MousePositionTracker), With<MainCamera>>,
+) {
+    let lines: f32 = event
+        .read()
+        .map(|ev| match ev.unit {
+            MouseScrollUnit::Line => ev.y,
+            MouseScrollUnit::Pixel => ev.y / PIXELS_PER_LINE,
+        })
+        .sum();
+
+    // the wheel scrolls the ui under the cursor instead, e.g. the chat
+    if lines == 0.
+        || camera_fit.area.is_none()
+        || q_scrollable.iter().any(|cursor| cursor.mouse_over())
+    {
+        return;
+    }
+
+    let Ok((mut view, mouse_position)) = q_camera.get_single_mut() else {
+        return;
+    };
+
+    let zoom = (view.zoom * ZOOM_PER_LINE.powf(lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+
+    if let Some(cursor) = mouse_position.mouse_world_position {
+        // the distance of the cursor to the center of the view shrinks with the zoom
+        let factor = view.zoom / zoom;
+        view.pan = cursor + (view.pan - cursor) * factor;
+    }
+
+    view.zoom = zoom;
+}
+
+/// Moves the view while the right or the middle mouse button is held.
+fn pan_camera(
+    mut event: EventReader<MouseMotion>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    camera_fit: Res<CameraFit>,
+    mut q_camera: Query<(&mut CameraView, &OrthographicProjection), With<MainCamera>>,
+) {
+    let delta: Vec2 = event.read().map(|ev| ev.delta).sum();
+
+    if delta == Vec2::ZERO
+        || camera_fit.area.is_none()
+        || !buttons.any_pressed([MouseButton::Right, MouseButton::Middle])
+    {
+        return;
+    }
+
+    let Ok((mut view, projection)) = q_camera.get_single_mut() else {
+        return;
+    };
+
+    // the world follows the cursor, the y-axis of the screen points down
+    view.pan += Vec2::new(-delta.x, delta.y) * projection.scale;
+}
+
+/// Scales the camera so that the area of `CameraFit` fits the window, and applies the view.
+fn fit_camera(
+    camera_fit: Res<CameraFit>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    mut q_camera: Query<
+        (&mut CameraView, &mut OrthographicProjection, &mut Transform),
+        With<MainCamera>,
+    >,
+) {
+    let (Ok(window), Ok((mut view, mut projection, mut transform))) =
+        (q_window.get_single(), q_camera.get_single_mut())
+    else {
+        return;
+    };
+
+    let (scale, pan) = match camera_fit.area {
+        Some(area) => {
+            let available = Vec2::new(window.width(), window.height()) * camera_fit.window_part;
+            if available.x <= 0. || available.y <= 0. {
+                return;
+            }
+
+            let fitted_scale = (area / available).max_element();
+
+            // the area can't be moved out of the view
+            let pan = view.pan.clamp(-area / 2., area / 2.);
+            if view.pan != pan {
+                view.pan = pan;
+            }
+
+            (fitted_scale / view.zoom, pan)
+        }
+        // e.g. in the menus, the next game starts with the fitted view again
+        None => {
+            if view.zoom != 1. || view.pan != Vec2::ZERO {
+                *view = CameraView::default();
+            }
+
+            (1., Vec2::ZERO)
+        }
+    };
+
+    if projection.scale != scale {
+        projection.scale = scale;
+    }
+    if transform.translation.xy() != pan {
+        transform.translation = pan.extend(transform.translation.z);
+    }
+}
@@ -1,19 +1,50 @@
+use crate::game::tafl::network::*;
+use crate::game::tafl::notation::format_side;
+use crate::game::tafl::players::Players;
+use crate::game::tafl::save::*;
+use crate::game::tafl::time_control::TimeControl;
+use crate::game::widgets::*;
 use crate::game::*;
 
+use self::load_game::*;
+use self::multiplayer::MultiplayerPlugin;
+use self::new_game::NewGamePlugin;
+use self::settings_menu::SettingsMenuPlugin;
+
+mod load_game;
+mod multiplayer;
+mod new_game;
+mod settings_menu;
+
 pub struct MainMenuPlugin;
 
 impl Plugin for MainMenuPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+        app.add_plugins(MultiplayerPlugin)
+            .add_plugins(NewGamePlugin)
+            .add_plugins(LoadGamePlugin)
+            .add_plugins(SettingsMenuPlugin)
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
             .add_systems(OnExit(GameState::MainMenu), despawn_main_menu)
-            .add_systems(Update, play_button.run_if(in_state(GameState::MainMenu)));
+            .add_systems(
+                Update,
+                (
+                    main_menu_buttons,
+                    network_status_text.run_if(resource_exists::<NetworkSession>),
+                )
+                    .run_if(in_state(GameState::MainMenu)),
+            );
     }
 }
 
 #[derive(Component)]
 struct MainMenuUi;
 
-fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_main_menu(
+    network_mode: Option<Res<NetworkMode>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
     commands
         .spawn((
             MainMenuUi,
@@ -52,31 +83,103 @@ fn spawn_main_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
                     ));
                 });
 
-            // Button
-            parent
-                .spawn((
-                    PlayButton,
-                    ButtonBundle {
-                        style: Style {
-                            width: Val::Px(200.),
-                            height: Val::Px(50.),
-                            align_items: AlignItems::Center,
-                            justify_content: JustifyContent::Center,
-                            ..default()
-                        },
-                        ..default()
-                    },
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
+            // network games start once the other player is connected
+            if network_mode.is_some() {
+                parent.spawn((
+                    NetworkStatusText,
+                    TextBundle::from_section(
+                        "",
                         TextStyle {
                             font: asset_server.load("fonts/FiraSans-Bold.ttf"),
-                            font_size: 40.0,
+                            font_size: 30.0,
                             color: Color::rgb(0.9, 0.9, 0.9),
                         },
-                    ));
-                });
+                    ),
+                ));
+            } else {
+                let spawn_button =
+                    |parent: &mut ChildBuilder, button: MainMenuButton, label: &str| {
+                        parent
+                            .spawn((
+                                button,
+                                ButtonBundle {
+                                    style: Style {
+                                        width: Val::Px(250.),
+                                        height: Val::Px(50.),
+                                        margin: UiRect::all(Val::Px(5.)),
+                                        align_items: AlignItems::Center,
+                                        justify_content: JustifyContent::Center,
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    label,
+                                    TextStyle {
+                                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                        font_size: 40.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                ));
+                            });
+                    };
+
+                // a game that was left by closing the window or a crash
+                match read_autosave() {
+                    Ok(Some((saved_game, _))) => {
+                        parent
+                            .spawn((
+                                RecoveryPrompt,
+                                NodeBundle {
+                                    style: Style {
+                                        align_items: AlignItems::Center,
+                                        flex_direction: FlexDirection::Column,
+                                        margin: UiRect::bottom(Val::Px(20.)),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                            ))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(
+                                    format!(
+                                        "An unfinished game was found: {} moves, {} to move",
+                                        saved_game.moves(),
+                                        format_side(saved_game.side_to_move())
+                                    ),
+                                    TextStyle {
+                                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                        font_size: 30.0,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                ));
+
+                                parent.spawn(NodeBundle::default()).with_children(|parent| {
+                                    spawn_button(parent, MainMenuButton::Restore, "Restore");
+                                    spawn_button(parent, MainMenuButton::Discard, "Discard");
+                                });
+                            });
+                    }
+                    Ok(None) => (),
+                    Err(err) => warn!("{}", err),
+                }
+
+                // Buttons
+                if let Some(slot) = latest_slot() {
+                    spawn_button(parent, MainMenuButton::Continue(slot), "Continue");
+                }
+
+                for (button, label) in [
+                    (MainMenuButton::Play, "Play"),
+                    (MainMenuButton::LoadGame, "Load game"),
+                    (MainMenuButton::Multiplayer, "Multiplayer"),
+                    (MainMenuButton::Settings, "Settings"),
+                ] {
+                    spawn_button(parent, button, label);
+                }
+            }
         });
 }
 
@@ -85,28 +188,99 @@ fn despawn_main_menu(q_main_menu: Query<Entity, With<MainMenuUi>>, mut commands:
     commands.entity(main_menu_entity).despawn_recursive();
 }
 
+/// Offers to restore the game that was autosaved.
 #[derive(Component)]
-struct PlayButton;
+struct RecoveryPrompt;
+
+#[derive(Component, Clone, Copy)]
+enum MainMenuButton {
+    /// Continues the autosaved game.
+    Restore,
+    /// Throws the autosaved game away.
+    Discard,
+    /// Continues the game saved last, in the slot.
+    Continue(usize),
+    Play,
+    LoadGame,
+    Multiplayer,
+    Settings,
+}
 
-fn play_button(
+fn main_menu_buttons(
     mut q_button: Query<
-        (&Interaction, &mut BackgroundColor),
-        (Changed<Interaction>, With<PlayButton>),
+        (&Interaction, &MainMenuButton, &mut BackgroundColor),
+        Changed<Interaction>,
     >,
+    q_recovery_prompt: Query<Entity, With<RecoveryPrompt>>,
+    mut players: ResMut<Players>,
+    mut time_control: ResMut<TimeControl>,
     mut next_game_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
 ) {
-    for (interaction, mut background_color) in &mut q_button {
-        match *interaction {
-            Interaction::Pressed => {
-                *background_color = Color::rgb_u8(157, 79, 79).into();
-                next_game_state.set(GameState::InGame);
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        next_game_state.set(match button {
+            MainMenuButton::Restore => {
+                let restored = match read_autosave() {
+                    Ok(Some((saved_game, _))) => continue_game(
+                        saved_game,
+                        None,
+                        &mut players,
+                        &mut time_control,
+                        &mut commands,
+                    )
+                    .map_err(|err| err.to_string()),
+                    Ok(None) => Err("the autosave is gone".to_string()),
+                    Err(err) => Err(err),
+                };
+
+                match restored {
+                    Ok(()) => GameState::InGame,
+                    Err(err) => {
+                        warn!("failed to restore the game: {}", err);
+                        continue;
+                    }
+                }
             }
-            Interaction::Hovered => {
-                *background_color = Color::rgb_u8(157, 79, 79).into();
+            MainMenuButton::Discard => {
+                if let Err(err) = clear_autosave() {
+                    warn!("failed to discard the autosave: {}", err);
+                }
+
+                for recovery_prompt_entity in &q_recovery_prompt {
+                    commands.entity(recovery_prompt_entity).despawn_recursive();
+                }
+                continue;
             }
-            Interaction::None => {
-                *background_color = Color::rgb_u8(78, 112, 165).into();
+            MainMenuButton::Continue(slot) => {
+                match load_game(*slot, &mut players, &mut time_control, &mut commands) {
+                    Ok(()) => GameState::InGame,
+                    // the load game screen tells what is wrong with the slot
+                    Err(_) => GameState::LoadGame,
+                }
             }
+            MainMenuButton::Play => GameState::NewGame,
+            MainMenuButton::LoadGame => GameState::LoadGame,
+            MainMenuButton::Multiplayer => GameState::Multiplayer,
+            MainMenuButton::Settings => GameState::Settings,
+        });
+    }
+}
+
+#[derive(Component)]
+struct NetworkStatusText;
+
+fn network_status_text(
+    session: Res<NetworkSession>,
+    mut q_text: Query<&mut Text, With<NetworkStatusText>>,
+) {
+    for mut text in &mut q_text {
+        if text.sections[0].value != session.status() {
+            text.sections[0].value = session.status().to_string();
         }
     }
 }
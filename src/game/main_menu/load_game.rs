@@ -0,0 +1,250 @@
+use bevy::ecs::system::SystemParam;
+use bevy::utils::Duration;
+
+use crate::game::tafl::notation::format_side;
+use crate::game::tafl::players::Players;
+use crate::game::tafl::save::*;
+use crate::game::tafl::time_control::TimeControl;
+use crate::game::widgets::*;
+use crate::game::*;
+
+/// The screen listing the save slots, from which saved games are continued.
+pub struct LoadGamePlugin;
+
+impl Plugin for LoadGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LoadGame), spawn_load_game_menu)
+            .add_systems(OnExit(GameState::LoadGame), despawn_load_game_menu)
+            .add_systems(
+                Update,
+                load_game_buttons.run_if(in_state(GameState::LoadGame)),
+            );
+    }
+}
+
+/// Continues the game saved in `slot`, the game starts once the state is set to `InGame`.
+pub fn load_game(
+    slot: usize,
+    players: &mut Players,
+    time_control: &mut TimeControl,
+    commands: &mut Commands,
+) -> Result<(), String> {
+    let Some((saved_game, _)) = read_slot(slot)? else {
+        return Err(format!("slot {} is empty", slot + 1));
+    };
+
+    continue_game(saved_game, Some(slot), players, time_control, commands)
+        .map_err(|err| format!("failed to replay slot {}: {}", slot + 1, err))
+}
+
+/// Continues `saved_game`, `slot` is the slot it was loaded from.
+pub fn continue_game(
+    saved_game: SavedGame,
+    slot: Option<usize>,
+    players: &mut Players,
+    time_control: &mut TimeControl,
+    commands: &mut Commands,
+) -> Result<(), &'static str> {
+    let game_setup = saved_game.game_setup(slot)?;
+
+    *players = saved_game.players;
+    *time_control = saved_game.record.time_control;
+    commands.insert_resource(game_setup);
+
+    Ok(())
+}
+
+#[derive(Component)]
+struct LoadGameUi;
+
+#[derive(Component)]
+struct StatusText;
+
+/// Holds a row for each save slot.
+#[derive(Component)]
+struct SlotList;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum LoadGameButton {
+    Load(usize),
+    Delete(usize),
+    Back,
+}
+
+/// Describes how long ago a game was saved, e.g. `5 min ago`.
+fn format_age(age: Duration) -> String {
+    let minutes = age.as_secs() / 60;
+
+    match minutes {
+        0 => "just now".to_string(),
+        1..=59 => format!("{} min ago", minutes),
+        60..=1439 => format!("{} h ago", minutes / 60),
+        _ => format!("{} days ago", minutes / 60 / 24),
+    }
+}
+
+fn spawn_slot_rows(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    for slot in 0..SAVE_SLOTS {
+        let slot_info = read_slot(slot);
+
+        let label = match &slot_info {
+            Ok(Some((saved_game, saved_at))) => format!(
+                "Slot {}: {}, {} moves, {} to move, saved {}",
+                slot + 1,
+                saved_game.record.variant,
+                saved_game.moves(),
+                format_side(saved_game.side_to_move()),
+                format_age(saved_at.elapsed().unwrap_or_default())
+            ),
+            Ok(None) => format!("Slot {}: empty", slot + 1),
+            Err(_) => format!("Slot {}: unreadable", slot + 1),
+        };
+
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|parent| {
+                spawn_button(
+                    parent,
+                    LoadGameButton::Load(slot),
+                    label,
+                    ButtonSize::with_width(600.),
+                    asset_server,
+                );
+
+                if !matches!(slot_info, Ok(None)) {
+                    spawn_button(
+                        parent,
+                        LoadGameButton::Delete(slot),
+                        "Delete".to_string(),
+                        ButtonSize::with_width(120.),
+                        asset_server,
+                    );
+                }
+            });
+    }
+}
+
+fn spawn_load_game_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn((
+            LoadGameUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Load game",
+                text_style(&asset_server, 60.),
+            ));
+
+            parent
+                .spawn((
+                    SlotList,
+                    NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            flex_direction: FlexDirection::Column,
+                            margin: UiRect::vertical(Val::Px(10.)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| spawn_slot_rows(parent, &asset_server));
+
+            parent.spawn((
+                StatusText,
+                TextBundle::from_section("", text_style(&asset_server, 24.)).with_style(Style {
+                    margin: UiRect::vertical(Val::Px(10.)),
+                    ..default()
+                }),
+            ));
+
+            spawn_button(
+                parent,
+                LoadGameButton::Back,
+                "Back".to_string(),
+                ButtonSize::with_width(220.),
+                &asset_server,
+            );
+        });
+}
+
+fn despawn_load_game_menu(q_load_game_ui: Query<Entity, With<LoadGameUi>>, mut commands: Commands) {
+    let load_game_ui_entity = q_load_game_ui.single();
+    commands.entity(load_game_ui_entity).despawn_recursive();
+}
+
+/// The resources set up for playing a loaded game.
+#[derive(SystemParam)]
+struct LoadedGame<'w> {
+    players: ResMut<'w, Players>,
+    time_control: ResMut<'w, TimeControl>,
+}
+
+fn load_game_buttons(
+    mut q_button: Query<
+        (&Interaction, &LoadGameButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    q_slot_list: Query<Entity, With<SlotList>>,
+    mut q_status_text: Query<&mut Text, With<StatusText>>,
+    mut loaded_game: LoadedGame,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        let result = match button {
+            LoadGameButton::Load(slot) => load_game(
+                *slot,
+                &mut loaded_game.players,
+                &mut loaded_game.time_control,
+                &mut commands,
+            )
+            .map(|_| next_game_state.set(GameState::InGame)),
+            LoadGameButton::Delete(slot) => {
+                let result = clear_slot(*slot)
+                    .map_err(|err| format!("failed to delete slot {}: {}", slot + 1, err));
+
+                for slot_list_entity in &q_slot_list {
+                    commands
+                        .entity(slot_list_entity)
+                        .despawn_descendants()
+                        .with_children(|parent| spawn_slot_rows(parent, &asset_server));
+                }
+
+                result
+            }
+            LoadGameButton::Back => {
+                next_game_state.set(GameState::MainMenu);
+                Ok(())
+            }
+        };
+
+        for mut text in &mut q_status_text {
+            text.sections[0].value = result.clone().err().unwrap_or_default();
+        }
+    }
+}
@@ -0,0 +1,544 @@
+use bevy::utils::Duration;
+use bevy::window::ReceivedCharacter;
+
+use crate::game::tafl::figure::Side;
+use crate::game::tafl::notation::format_side;
+use crate::game::tafl::online::*;
+use crate::game::tafl::rules::VARIANTS;
+use crate::game::tafl::server_protocol::*;
+use crate::game::tafl::time_control::*;
+use crate::game::widgets::*;
+use crate::game::*;
+
+// how often the open games are listed again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The lobby of the server, in which games against other players are found.
+pub struct MultiplayerPlugin;
+
+impl Plugin for MultiplayerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Multiplayer), spawn_lobby)
+            .add_systems(OnExit(GameState::Multiplayer), despawn_lobby)
+            .add_systems(OnEnter(GameState::MainMenu), disconnect)
+            .add_systems(
+                Update,
+                (
+                    type_name,
+                    lobby_buttons,
+                    refresh_game_list,
+                    update_status_text,
+                    update_name_text,
+                    update_button_labels,
+                    update_game_list,
+                )
+                    .run_if(in_state(GameState::Multiplayer)),
+            )
+            .init_resource::<LobbySettings>();
+    }
+}
+
+/// The choices made in the lobby, kept while a game is played.
+#[derive(Resource)]
+struct LobbySettings {
+    name: String,
+    // index into VARIANTS
+    variant: usize,
+    // index into TIME_CONTROL_PRESETS, games on the server always have a clock
+    time: usize,
+    // the side played in created challenges
+    side: Side,
+    // whether only the games with the chosen time control are listed
+    only_chosen_time: bool,
+}
+
+impl Default for LobbySettings {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            variant: 0,
+            time: TIME_CONTROL_PRESETS
+                .iter()
+                .position(|(name, _)| *name == "10 min")
+                .unwrap(),
+            side: Side::Attacker,
+            only_chosen_time: false,
+        }
+    }
+}
+
+impl LobbySettings {
+    fn variant(&self) -> String {
+        VARIANTS[self.variant].to_string()
+    }
+
+    fn time_name(&self) -> &'static str {
+        TIME_CONTROL_PRESETS[self.time].0
+    }
+
+    fn time_control(&self) -> &'static TimeControl {
+        &TIME_CONTROL_PRESETS[self.time].1
+    }
+
+    /// Chooses the next time control with a clock.
+    fn next_time(&mut self) {
+        loop {
+            self.time = (self.time + 1) % TIME_CONTROL_PRESETS.len();
+
+            if !self.time_control().unlimited {
+                break;
+            }
+        }
+    }
+}
+
+fn move_limit_ms(time_control: &TimeControl) -> Option<u64> {
+    time_control
+        .move_limit
+        .map(|move_limit| move_limit.as_millis() as u64)
+}
+
+/// Names the time control of an open game, using the name of its preset if there is one.
+fn time_control_name(game: &OpenGame) -> String {
+    let time_control = time_control(game.time_ms, game.bonus, game.move_limit_ms);
+
+    match TIME_CONTROL_PRESETS
+        .iter()
+        .find(|(_, preset)| *preset == time_control)
+    {
+        Some((name, _)) => name.to_string(),
+        None => format!("{} min", game.time_ms / 60 / 1000),
+    }
+}
+
+#[derive(Component)]
+struct LobbyUi;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component)]
+struct NameText;
+
+/// Holds a button for each open game.
+#[derive(Component)]
+struct GameList;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum LobbyButton {
+    Connect,
+    Variant,
+    Time,
+    Side,
+    Filter,
+    QuickMatch,
+    Challenge,
+    Join(u64),
+    Back,
+}
+
+impl LobbyButton {
+    /// Gets the text of the button, None if it never changes.
+    fn label(&self, settings: &LobbySettings, session: Option<&OnlineSession>) -> Option<String> {
+        let label = match self {
+            LobbyButton::Connect => match session {
+                Some(session) if session.is_connected() && session.name().is_some() => "Rename",
+                _ => "Connect",
+            }
+            .to_string(),
+            LobbyButton::Variant => format!("Variant: {}", settings.variant()),
+            LobbyButton::Time => format!("Time: {}", settings.time_name()),
+            LobbyButton::Side => format!("Side: {}", format_side(settings.side)),
+            LobbyButton::Filter => match settings.only_chosen_time {
+                true => format!("Listing: {}", settings.time_name()),
+                false => "Listing: all".to_string(),
+            },
+            LobbyButton::QuickMatch => match session.is_some_and(|session| session.is_queued()) {
+                true => "Leave queue",
+                false => "Quick match",
+            }
+            .to_string(),
+            LobbyButton::Challenge => match session.and_then(|session| session.challenge()) {
+                Some(_) => "Challenged",
+                None => "Challenge",
+            }
+            .to_string(),
+            LobbyButton::Join(_) | LobbyButton::Back => return None,
+        };
+
+        Some(label)
+    }
+}
+
+fn spawn_lobby(
+    settings: Res<LobbySettings>,
+    session: Option<Res<OnlineSession>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let session = session.as_deref();
+
+    let row = || NodeBundle {
+        style: Style {
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        ..default()
+    };
+
+    let spawn_row = |parent: &mut ChildBuilder, buttons: &[LobbyButton]| {
+        parent.spawn(row()).with_children(|parent| {
+            for button in buttons {
+                let label = button.label(&settings, session).unwrap_or_default();
+                // the names of the time controls are longer than the other labels
+                let width = match button {
+                    LobbyButton::Time | LobbyButton::Filter => 300.,
+                    _ => 220.,
+                };
+                spawn_button(
+                    parent,
+                    *button,
+                    label,
+                    ButtonSize::with_width(width),
+                    &asset_server,
+                );
+            }
+        });
+    };
+
+    commands
+        .spawn((
+            LobbyUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Multiplayer",
+                text_style(&asset_server, 60.),
+            ));
+
+            parent.spawn((
+                StatusText,
+                TextBundle::from_section("", text_style(&asset_server, 24.)).with_style(Style {
+                    margin: UiRect::vertical(Val::Px(10.)),
+                    ..default()
+                }),
+            ));
+
+            // Name
+            parent.spawn(row()).with_children(|parent| {
+                parent.spawn((
+                    NameText,
+                    TextBundle::from_section("", text_style(&asset_server, 30.)).with_style(
+                        Style {
+                            min_width: Val::Px(300.),
+                            ..default()
+                        },
+                    ),
+                ));
+
+                let label = LobbyButton::Connect
+                    .label(&settings, session)
+                    .unwrap_or_default();
+                spawn_button(
+                    parent,
+                    LobbyButton::Connect,
+                    label,
+                    ButtonSize::with_width(220.),
+                    &asset_server,
+                );
+            });
+
+            spawn_row(
+                parent,
+                &[
+                    LobbyButton::Variant,
+                    LobbyButton::Time,
+                    LobbyButton::Side,
+                    LobbyButton::Filter,
+                ],
+            );
+            spawn_row(parent, &[LobbyButton::QuickMatch, LobbyButton::Challenge]);
+
+            parent.spawn(
+                TextBundle::from_section("Open games", text_style(&asset_server, 30.)).with_style(
+                    Style {
+                        margin: UiRect::top(Val::Px(20.)),
+                        ..default()
+                    },
+                ),
+            );
+
+            parent.spawn((
+                GameList,
+                NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        flex_direction: FlexDirection::Column,
+                        min_height: Val::Px(150.),
+                        ..default()
+                    },
+                    ..default()
+                },
+            ));
+
+            spawn_button(
+                parent,
+                LobbyButton::Back,
+                "Back".to_string(),
+                ButtonSize::with_width(220.),
+                &asset_server,
+            );
+        });
+}
+
+fn despawn_lobby(q_lobby_ui: Query<Entity, With<LobbyUi>>, mut commands: Commands) {
+    let lobby_ui_entity = q_lobby_ui.single();
+    commands.entity(lobby_ui_entity).despawn_recursive();
+}
+
+/// Leaves the server once the lobby is left, giving up the created challenge and the place in the
+/// queue.
+fn disconnect(mut commands: Commands) {
+    commands.remove_resource::<OnlineSession>();
+}
+
+fn type_name(
+    mut event: EventReader<ReceivedCharacter>,
+    input: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<LobbySettings>,
+) {
+    for ev in event.read() {
+        for c in ev.char.chars() {
+            let is_allowed = c.is_alphanumeric() || c == '-' || c == '_';
+
+            if is_allowed && settings.name.chars().count() < MAX_NAME_LENGTH {
+                settings.name.push(c);
+            }
+        }
+    }
+
+    if input.just_pressed(KeyCode::Backspace) {
+        settings.name.pop();
+    }
+}
+
+fn lobby_buttons(
+    mut q_button: Query<(&Interaction, &LobbyButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut settings: ResMut<LobbySettings>,
+    session: Option<Res<OnlineSession>>,
+    server: Res<OnlineServer>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            LobbyButton::Connect => {
+                if settings.name.is_empty() {
+                    continue;
+                }
+
+                let register = ClientMessage::Register {
+                    name: settings.name.clone(),
+                };
+
+                match &session {
+                    Some(session) if session.is_connected() => session.send(register),
+                    _ => {
+                        let session = OnlineSession::connect(&server.address);
+                        session.send(register);
+                        commands.insert_resource(session);
+                    }
+                }
+            }
+            LobbyButton::Variant => settings.variant = (settings.variant + 1) % VARIANTS.len(),
+            LobbyButton::Time => settings.next_time(),
+            LobbyButton::Side => settings.side = settings.side.opponent(),
+            LobbyButton::Filter => settings.only_chosen_time = !settings.only_chosen_time,
+            LobbyButton::QuickMatch => {
+                let Some(session) = &session else {
+                    continue;
+                };
+
+                if session.is_queued() {
+                    session.send(ClientMessage::LeaveQueue);
+                } else {
+                    let time_control = settings.time_control();
+
+                    session.send(ClientMessage::JoinQueue {
+                        variant: settings.variant(),
+                        time_ms: time_control.time.as_millis() as u64,
+                        bonus: Bonus::from_time_bonus(time_control.bonus),
+                        move_limit_ms: move_limit_ms(time_control),
+                    });
+                }
+            }
+            LobbyButton::Challenge => {
+                if let Some(session) = &session {
+                    let time_control = settings.time_control();
+
+                    session.send(ClientMessage::CreateGame {
+                        variant: settings.variant(),
+                        side: settings.side,
+                        time_ms: time_control.time.as_millis() as u64,
+                        bonus: Bonus::from_time_bonus(time_control.bonus),
+                        move_limit_ms: move_limit_ms(time_control),
+                    });
+                }
+            }
+            LobbyButton::Join(game_id) => {
+                if let Some(session) = &session {
+                    session.send(ClientMessage::JoinGame { game_id: *game_id });
+                }
+            }
+            LobbyButton::Back => next_game_state.set(GameState::MainMenu),
+        }
+    }
+}
+
+/// Lists the open games every now and then and whenever the filters change.
+fn refresh_game_list(
+    session: Option<Res<OnlineSession>>,
+    settings: Res<LobbySettings>,
+    time: Res<Time>,
+    mut since_refresh: Local<Option<Duration>>,
+) {
+    let Some(session) = session.filter(|session| session.name().is_some()) else {
+        *since_refresh = None;
+        return;
+    };
+
+    let since_refresh = since_refresh.get_or_insert(REFRESH_INTERVAL);
+    *since_refresh += time.delta();
+
+    if *since_refresh < REFRESH_INTERVAL && !settings.is_changed() {
+        return;
+    }
+
+    *since_refresh = Duration::ZERO;
+
+    let time_control = settings
+        .only_chosen_time
+        .then(|| settings.time_control());
+
+    session.send(ClientMessage::ListGames {
+        variant: Some(settings.variant()),
+        time_ms: time_control.map(|time_control| time_control.time.as_millis() as u64),
+        bonus: time_control.and_then(|time_control| Bonus::from_time_bonus(time_control.bonus)),
+        move_limit_ms: time_control.and_then(move_limit_ms),
+    });
+}
+
+fn set_text(text: &mut Text, value: &str) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value.to_string();
+    }
+}
+
+fn update_status_text(
+    session: Option<Res<OnlineSession>>,
+    server: Res<OnlineServer>,
+    mut q_text: Query<&mut Text, With<StatusText>>,
+) {
+    let status = match &session {
+        Some(session) => session.status().to_string(),
+        None => format!("Enter a name to play on {}", server.address),
+    };
+
+    for mut text in &mut q_text {
+        set_text(&mut text, &status);
+    }
+}
+
+fn update_name_text(settings: Res<LobbySettings>, mut q_text: Query<&mut Text, With<NameText>>) {
+    for mut text in &mut q_text {
+        set_text(&mut text, &format!("Name: {}_", settings.name));
+    }
+}
+
+fn update_button_labels(
+    settings: Res<LobbySettings>,
+    session: Option<Res<OnlineSession>>,
+    q_button: Query<(&LobbyButton, &Children)>,
+    mut q_text: Query<&mut Text>,
+) {
+    for (button, children) in &q_button {
+        let Some(label) = button.label(&settings, session.as_deref()) else {
+            continue;
+        };
+
+        for child in children {
+            if let Ok(mut text) = q_text.get_mut(*child) {
+                set_text(&mut text, &label);
+            }
+        }
+    }
+}
+
+/// Shows a button for joining each open game.
+fn update_game_list(
+    session: Option<Res<OnlineSession>>,
+    q_game_list: Query<(Entity, Ref<GameList>)>,
+    mut shown_games: Local<Vec<OpenGame>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let games = session
+        .as_ref()
+        .map(|session| session.open_games())
+        .unwrap_or_default();
+
+    for (game_list_entity, game_list) in &q_game_list {
+        if !game_list.is_added() && *shown_games == games {
+            continue;
+        }
+
+        *shown_games = games.to_vec();
+
+        commands
+            .entity(game_list_entity)
+            .despawn_descendants()
+            .with_children(|parent| {
+                if games.is_empty() {
+                    parent.spawn(TextBundle::from_section(
+                        "No open games",
+                        text_style(&asset_server, 24.),
+                    ));
+                }
+
+                for game in games {
+                    let label = format!(
+                        "{} plays {}, {}, {}",
+                        game.creator,
+                        format_side(game.side),
+                        game.variant,
+                        time_control_name(game)
+                    );
+
+                    spawn_button(
+                        parent,
+                        LobbyButton::Join(game.game_id),
+                        label,
+                        ButtonSize::with_width(500.),
+                        &asset_server,
+                    );
+                }
+            });
+    }
+}
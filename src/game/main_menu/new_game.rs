@@ -0,0 +1,304 @@
+use crate::game::tafl::figure::Side;
+use crate::game::tafl::game_setup::GameSetup;
+use crate::game::tafl::notation::format_side;
+use crate::game::tafl::players::*;
+use crate::game::tafl::rules::VARIANTS;
+use crate::game::tafl::time_control::*;
+use crate::game::widgets::*;
+use crate::game::*;
+
+/// The screen before a game played on this computer, in which the players choose how it is
+/// played.
+pub struct NewGamePlugin;
+
+impl Plugin for NewGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::NewGame),
+            (load_new_game_settings, spawn_new_game_menu).chain(),
+        )
+        .add_systems(OnExit(GameState::NewGame), despawn_new_game_menu)
+        .add_systems(
+            Update,
+            (new_game_buttons, update_button_labels, update_status_text)
+                .chain()
+                .run_if(in_state(GameState::NewGame)),
+        )
+        .init_resource::<NewGameSettings>();
+    }
+}
+
+/// The choices made on the new game screen, kept for the next game.
+#[derive(Resource)]
+struct NewGameSettings {
+    // index into VARIANTS
+    variant: usize,
+    // index into TIME_CONTROL_PRESETS
+    time: usize,
+    // the side making the first move
+    first_side: Side,
+    attacker: PlayerKind,
+    defender: PlayerKind,
+    // why the game couldn't be started
+    error: Option<String>,
+}
+
+impl Default for NewGameSettings {
+    fn default() -> Self {
+        Self {
+            variant: 0,
+            time: 3,
+            first_side: Side::Attacker,
+            attacker: PlayerKind::Human,
+            defender: PlayerKind::Human,
+            error: None,
+        }
+    }
+}
+
+impl NewGameSettings {
+    fn player(&mut self, side: Side) -> &mut PlayerKind {
+        match side {
+            Side::Attacker => &mut self.attacker,
+            Side::Defender => &mut self.defender,
+        }
+    }
+}
+
+#[derive(Component)]
+struct NewGameUi;
+
+#[derive(Component)]
+struct StatusText;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum NewGameButton {
+    Variant,
+    Time,
+    FirstSide,
+    Player(Side),
+    Start,
+    Back,
+}
+
+impl NewGameButton {
+    /// Gets the text of the button, None if it never changes.
+    fn label(&self, settings: &NewGameSettings) -> Option<String> {
+        let label = match self {
+            NewGameButton::Variant => format!("Variant: {}", VARIANTS[settings.variant]),
+            NewGameButton::Time => format!("Time: {}", TIME_CONTROL_PRESETS[settings.time].0),
+            NewGameButton::FirstSide => format!("First move: {}", format_side(settings.first_side)),
+            NewGameButton::Player(side) => {
+                let player = match side {
+                    Side::Attacker => &settings.attacker,
+                    Side::Defender => &settings.defender,
+                };
+
+                let kind = match player {
+                    PlayerKind::Human | PlayerKind::Remote => "Human",
+                    PlayerKind::ExternalEngine { .. } => "AI",
+                };
+
+                let side = match side {
+                    Side::Attacker => "Attackers",
+                    Side::Defender => "Defenders",
+                };
+
+                format!("{}: {}", side, kind)
+            }
+            NewGameButton::Start | NewGameButton::Back => return None,
+        };
+
+        Some(label)
+    }
+}
+
+/// Starts from the players and the time control of the previous game, e.g. the ones given on the
+/// command line.
+fn load_new_game_settings(
+    players: Res<Players>,
+    time_control: Res<TimeControl>,
+    mut settings: ResMut<NewGameSettings>,
+) {
+    for side in [Side::Attacker, Side::Defender] {
+        *settings.player(side) = match players.get(side) {
+            // the opponent of an online game isn't around anymore
+            PlayerKind::Remote => PlayerKind::Human,
+            player => player.clone(),
+        };
+    }
+
+    if let Some(time) = TIME_CONTROL_PRESETS
+        .iter()
+        .position(|(_, preset)| *preset == *time_control)
+    {
+        settings.time = time;
+    }
+
+    settings.error = None;
+}
+
+fn spawn_new_game_menu(
+    settings: Res<NewGameSettings>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn((
+            NewGameUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "New game",
+                text_style(&asset_server, 60.),
+            ));
+
+            for button in [
+                NewGameButton::Variant,
+                NewGameButton::Time,
+                NewGameButton::FirstSide,
+                NewGameButton::Player(Side::Attacker),
+                NewGameButton::Player(Side::Defender),
+            ] {
+                let label = button.label(&settings).unwrap_or_default();
+                spawn_button(
+                    parent,
+                    button,
+                    label,
+                    ButtonSize::with_width(350.),
+                    &asset_server,
+                );
+            }
+
+            parent.spawn((
+                StatusText,
+                TextBundle::from_section("", text_style(&asset_server, 24.)).with_style(Style {
+                    margin: UiRect::vertical(Val::Px(10.)),
+                    ..default()
+                }),
+            ));
+
+            spawn_button(
+                parent,
+                NewGameButton::Start,
+                "Start".to_string(),
+                ButtonSize::with_width(350.),
+                &asset_server,
+            );
+            spawn_button(
+                parent,
+                NewGameButton::Back,
+                "Back".to_string(),
+                ButtonSize::with_width(350.),
+                &asset_server,
+            );
+        });
+}
+
+fn despawn_new_game_menu(q_new_game_ui: Query<Entity, With<NewGameUi>>, mut commands: Commands) {
+    let new_game_ui_entity = q_new_game_ui.single();
+    commands.entity(new_game_ui_entity).despawn_recursive();
+}
+
+fn new_game_buttons(
+    mut q_button: Query<(&Interaction, &NewGameButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut settings: ResMut<NewGameSettings>,
+    mut players: ResMut<Players>,
+    mut time_control: ResMut<TimeControl>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut commands: Commands,
+) {
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        settings.error = None;
+
+        match button {
+            NewGameButton::Variant => settings.variant = (settings.variant + 1) % VARIANTS.len(),
+            NewGameButton::Time => settings.time = (settings.time + 1) % TIME_CONTROL_PRESETS.len(),
+            NewGameButton::FirstSide => settings.first_side = settings.first_side.opponent(),
+            NewGameButton::Player(side) => {
+                let player = match settings.player(*side) {
+                    PlayerKind::Human | PlayerKind::Remote => PlayerKind::built_in_ai(),
+                    PlayerKind::ExternalEngine { .. } => Ok(PlayerKind::Human),
+                };
+
+                match player {
+                    Ok(player) => *settings.player(*side) = player,
+                    Err(err) => settings.error = Some(err),
+                }
+            }
+            NewGameButton::Start => {
+                let game_setup = GameSetup::new(
+                    VARIANTS[settings.variant],
+                    None,
+                    settings.first_side,
+                    vec![],
+                    None,
+                );
+
+                match game_setup {
+                    Ok(game_setup) => {
+                        players.attacker = settings.attacker.clone();
+                        players.defender = settings.defender.clone();
+                        *time_control = TIME_CONTROL_PRESETS[settings.time].1.clone();
+
+                        commands.insert_resource(game_setup);
+                        next_game_state.set(GameState::InGame);
+                    }
+                    Err(err) => settings.error = Some(err.to_string()),
+                }
+            }
+            NewGameButton::Back => next_game_state.set(GameState::MainMenu),
+        }
+    }
+}
+
+fn set_text(text: &mut Text, value: &str) {
+    if text.sections[0].value != value {
+        text.sections[0].value = value.to_string();
+    }
+}
+
+fn update_button_labels(
+    settings: Res<NewGameSettings>,
+    q_button: Query<(&NewGameButton, &Children)>,
+    mut q_text: Query<&mut Text>,
+) {
+    for (button, children) in &q_button {
+        let Some(label) = button.label(&settings) else {
+            continue;
+        };
+
+        for child in children {
+            if let Ok(mut text) = q_text.get_mut(*child) {
+                set_text(&mut text, &label);
+            }
+        }
+    }
+}
+
+fn update_status_text(
+    settings: Res<NewGameSettings>,
+    mut q_text: Query<&mut Text, With<StatusText>>,
+) {
+    let status = settings.error.as_deref().unwrap_or_default();
+
+    for mut text in &mut q_text {
+        set_text(&mut text, status);
+    }
+}
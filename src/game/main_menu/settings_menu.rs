@@ -0,0 +1,102 @@
+use crate::game::settings::*;
+use crate::game::widgets::*;
+use crate::game::*;
+
+/// The settings screen of the main menu, the settings are saved as soon as they are changed.
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Settings), spawn_settings_menu)
+            .add_systems(OnExit(GameState::Settings), despawn_settings_menu)
+            .add_systems(Update, back_button.run_if(in_state(GameState::Settings)));
+    }
+}
+
+#[derive(Component)]
+struct SettingsMenuUi;
+
+#[derive(Component)]
+struct BackButton;
+
+fn spawn_settings_menu(
+    settings: Res<Settings>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn((
+            SettingsMenuUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section("Settings", text_style(&asset_server, 60.)).with_style(
+                    Style {
+                        margin: UiRect::bottom(Val::Px(10.)),
+                        ..default()
+                    },
+                ),
+            );
+
+            spawn_setting_buttons(parent, &settings, &asset_server);
+
+            parent
+                .spawn((
+                    BackButton,
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(350.),
+                            height: Val::Px(40.),
+                            margin: UiRect::top(Val::Px(15.)),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON_COLOR.into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Back",
+                        text_style(&asset_server, 24.),
+                    ));
+                });
+        });
+}
+
+fn despawn_settings_menu(
+    q_settings_menu_ui: Query<Entity, With<SettingsMenuUi>>,
+    mut commands: Commands,
+) {
+    let settings_menu_ui_entity = q_settings_menu_ui.single();
+    commands.entity(settings_menu_ui_entity).despawn_recursive();
+}
+
+type BackButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<BackButton>),
+>;
+
+fn back_button(mut q_button: BackButtonQuery, mut next_game_state: ResMut<NextState<GameState>>) {
+    for (interaction, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+
+        if *interaction == Interaction::Pressed {
+            next_game_state.set(GameState::MainMenu);
+        }
+    }
+}
@@ -0,0 +1,579 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use bevy::ui::UiScale;
+use bevy::window::{PrimaryWindow, WindowMode};
+
+use crate::game::tafl::time_control::*;
+use crate::game::theme::Themes;
+use crate::game::widgets::*;
+use crate::game::*;
+
+// the values the settings cycle through
+const VOLUMES: &[f32] = &[0., 0.25, 0.5, 0.75, 1.];
+const SLIDE_DURATIONS: &[f32] = &[0., 0.1, 0.2, 0.4];
+const WINDOW_MODES: &[WindowMode] = &[
+    WindowMode::Windowed,
+    WindowMode::BorderlessFullscreen,
+    WindowMode::Fullscreen,
+];
+const RESOLUTIONS: &[(u32, u32)] = &[(1024, 768), (1280, 720), (1600, 900), (1920, 1080)];
+const TEXT_SIZES: &[f32] = &[1., 1.25, 1.5];
+
+/// Loads the settings at startup and saves them whenever they change, the plugins apply the
+/// settings they are concerned with.
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let settings = match read_settings() {
+            Ok(settings) => settings,
+            Err(err) => {
+                warn!("{}", err);
+                Settings::default()
+            }
+        };
+
+        // inserted before the other plugins initialize it, the command line overrides it
+        app.insert_resource(settings.time_control())
+            .insert_resource(settings)
+            .add_systems(
+                Update,
+                (
+                    setting_buttons,
+                    (
+                        update_setting_labels,
+                        apply_window_settings,
+                        apply_text_size,
+                        save_settings,
+                    )
+                        .run_if(resource_changed::<Settings>),
+                )
+                    .chain(),
+            );
+    }
+}
+
+/// The preferences of the player, kept in `settings.txt` of the config directory.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Settings {
+    /// The volume of all sounds, from 0 to 1.
+    pub master_volume: f32,
+    /// The volume of the sound effects, relative to the master volume.
+    pub effect_volume: f32,
+    /// How long a moved figure slides to its field, in seconds.
+    pub slide_duration: f32,
+    /// Whether the fields the selected figure can move to are highlighted.
+    pub move_highlights: bool,
+    /// Whether the field of the selected figure is highlighted.
+    pub selection_highlight: bool,
+    /// Whether the files and ranks are labeled around the board.
+    pub coordinates: bool,
+    /// Whether the board is turned to the side of the player, or of the side to move in hot-seat
+    /// games.
+    pub auto_rotate: bool,
+    pub window_mode: WindowMode,
+    pub resolution: (u32, u32),
+    /// The time control the new game screen starts with, an index into `TIME_CONTROL_PRESETS`.
+    pub time_control: usize,
+    /// The name of the theme, see `Themes`.
+    pub theme: String,
+    /// Whether the starting areas of the sides have colors that are told apart with color
+    /// blindness, and the turn indicators are labeled with the sides.
+    pub colorblind: bool,
+    /// Whether the figures are marked with the shape of their side.
+    pub figure_markers: bool,
+    /// Whether the highlights are bright and opaque.
+    pub high_contrast: bool,
+    /// How much the menus and the text are enlarged.
+    pub text_size: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.,
+            effect_volume: 1.,
+            slide_duration: 0.2,
+            move_highlights: true,
+            selection_highlight: true,
+            coordinates: true,
+            auto_rotate: true,
+            window_mode: WindowMode::Windowed,
+            resolution: (1280, 720),
+            time_control: 3,
+            theme: "classic".to_string(),
+            colorblind: false,
+            figure_markers: false,
+            high_contrast: false,
+            text_size: 1.,
+        }
+    }
+}
+
+impl Settings {
+    pub fn time_control(&self) -> TimeControl {
+        TIME_CONTROL_PRESETS[self.time_control].1.clone()
+    }
+
+    /// Converts the settings into the text of the settings file, a setting per line.
+    pub fn to_text(&self) -> String {
+        format!(
+            "master-volume {}\n\
+             effect-volume {}\n\
+             slide-duration {}\n\
+             move-highlights {}\n\
+             selection-highlight {}\n\
+             coordinates {}\n\
+             auto-rotate {}\n\
+             window-mode {}\n\
+             resolution {}x{}\n\
+             time-control {}\n\
+             theme {}\n\
+             colorblind {}\n\
+             figure-markers {}\n\
+             high-contrast {}\n\
+             text-size {}\n",
+            self.master_volume,
+            self.effect_volume,
+            self.slide_duration,
+            format_switch(self.move_highlights),
+            format_switch(self.selection_highlight),
+            format_switch(self.coordinates),
+            format_switch(self.auto_rotate),
+            format_window_mode(self.window_mode),
+            self.resolution.0,
+            self.resolution.1,
+            TIME_CONTROL_PRESETS[self.time_control].0,
+            self.theme,
+            format_switch(self.colorblind),
+            format_switch(self.figure_markers),
+            format_switch(self.high_contrast),
+            self.text_size
+        )
+    }
+
+    /// Parses the text of a settings file, the settings that are missing or invalid keep their
+    /// defaults so that a single broken line doesn't reset all of them.
+    pub fn parse(data: &str) -> Self {
+        let mut settings = Settings::default();
+
+        for line in data.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let (name, value) = line.split_once(' ').unwrap_or((line, ""));
+
+            if let Err(err) = settings.set(name, value.trim()) {
+                warn!("ignored `{}` in the settings: {}", line, err);
+            }
+        }
+
+        settings
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), &'static str> {
+        match name {
+            "master-volume" => self.master_volume = parse_volume(value)?,
+            "effect-volume" => self.effect_volume = parse_volume(value)?,
+            "slide-duration" => {
+                self.slide_duration = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|duration| (0. ..=2.).contains(duration))
+                    .ok_or("the slide duration should be between 0 and 2 seconds")?;
+            }
+            "move-highlights" => self.move_highlights = parse_switch(value)?,
+            "selection-highlight" => self.selection_highlight = parse_switch(value)?,
+            "coordinates" => self.coordinates = parse_switch(value)?,
+            "auto-rotate" => self.auto_rotate = parse_switch(value)?,
+            "window-mode" => {
+                self.window_mode = WINDOW_MODES
+                    .iter()
+                    .copied()
+                    .find(|window_mode| format_window_mode(*window_mode) == value)
+                    .ok_or("the window mode should be windowed, borderless or fullscreen")?;
+            }
+            "resolution" => {
+                self.resolution = value
+                    .split_once('x')
+                    .and_then(|(width, height)| Some((width.parse().ok()?, height.parse().ok()?)))
+                    .filter(|(width, height)| *width > 0 && *height > 0)
+                    .ok_or("the resolution should look like 1280x720")?;
+            }
+            "time-control" => {
+                self.time_control = TIME_CONTROL_PRESETS
+                    .iter()
+                    .position(|(preset_name, _)| *preset_name == value)
+                    .ok_or("unknown time control")?;
+            }
+            // themes can be removed, unknown ones fall back to the default theme
+            "theme" => self.theme = value.to_string(),
+            "colorblind" => self.colorblind = parse_switch(value)?,
+            "figure-markers" => self.figure_markers = parse_switch(value)?,
+            "high-contrast" => self.high_contrast = parse_switch(value)?,
+            "text-size" => {
+                self.text_size = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|text_size| (0.5..=3.).contains(text_size))
+                    .ok_or("the text size should be between 0.5 and 3")?;
+            }
+            _ => return Err("unknown setting"),
+        }
+
+        Ok(())
+    }
+}
+
+fn format_switch(on: bool) -> &'static str {
+    match on {
+        true => "on",
+        false => "off",
+    }
+}
+
+fn parse_switch(value: &str) -> Result<bool, &'static str> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err("the setting should be on or off"),
+    }
+}
+
+fn parse_volume(value: &str) -> Result<f32, &'static str> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|volume| (0. ..=1.).contains(volume))
+        .ok_or("the volume should be between 0 and 1")
+}
+
+fn format_window_mode(window_mode: WindowMode) -> &'static str {
+    match window_mode {
+        WindowMode::Windowed => "windowed",
+        WindowMode::BorderlessFullscreen => "borderless",
+        WindowMode::SizedFullscreen | WindowMode::Fullscreen => "fullscreen",
+    }
+}
+
+/// Gets the directory of the settings, in the config directory of the user.
+pub fn config_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        PathBuf::from(dir).join("hnefatafl")
+    } else if let Some(dir) = std::env::var_os("APPDATA") {
+        PathBuf::from(dir).join("hnefatafl")
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home).join(".config/hnefatafl")
+    } else {
+        PathBuf::from("hnefatafl")
+    }
+}
+
+fn settings_path() -> PathBuf {
+    config_dir().join("settings.txt")
+}
+
+/// Reads the settings file, the defaults are used if there is none yet.
+pub fn read_settings() -> Result<Settings, String> {
+    let path = settings_path();
+
+    match fs::read_to_string(&path) {
+        Ok(data) => Ok(Settings::parse(&data)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+        Err(err) => Err(format!("failed to read {}: {}", path.display(), err)),
+    }
+}
+
+pub fn write_settings(settings: &Settings) -> io::Result<()> {
+    let path = settings_path();
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, settings.to_text())
+}
+
+fn save_settings(settings: Res<Settings>) {
+    // they were just read from the file
+    if settings.is_added() {
+        return;
+    }
+
+    if let Err(err) = write_settings(&settings) {
+        warn!("failed to save the settings: {}", err);
+    }
+}
+
+fn apply_window_settings(
+    settings: Res<Settings>,
+    mut q_window: Query<&mut Window, With<PrimaryWindow>>,
+    mut applied: Local<Option<(WindowMode, (u32, u32))>>,
+) {
+    // otherwise a window resized by the player would be reset whenever any setting changes
+    if *applied == Some((settings.window_mode, settings.resolution)) {
+        return;
+    }
+
+    let Ok(mut window) = q_window.get_single_mut() else {
+        return;
+    };
+
+    let (width, height) = settings.resolution;
+    window.mode = settings.window_mode;
+    window.resolution.set(width as f32, height as f32);
+
+    *applied = Some((settings.window_mode, settings.resolution));
+}
+
+// scales all of the ui, so that larger text still fits the buttons
+fn apply_text_size(settings: Res<Settings>, mut ui_scale: ResMut<UiScale>) {
+    if ui_scale.0 != settings.text_size {
+        ui_scale.0 = settings.text_size;
+    }
+}
+
+/// Changes a setting when pressed, spawned by `spawn_setting_buttons`.
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum SettingButton {
+    MasterVolume,
+    EffectVolume,
+    SlideDuration,
+    MoveHighlights,
+    SelectionHighlight,
+    Coordinates,
+    AutoRotate,
+    WindowMode,
+    Resolution,
+    TimeControl,
+    Theme,
+    Colorblind,
+    FigureMarkers,
+    HighContrast,
+    TextSize,
+}
+
+impl SettingButton {
+    const ALL: [SettingButton; 15] = [
+        SettingButton::MasterVolume,
+        SettingButton::EffectVolume,
+        SettingButton::SlideDuration,
+        SettingButton::MoveHighlights,
+        SettingButton::SelectionHighlight,
+        SettingButton::Coordinates,
+        SettingButton::AutoRotate,
+        SettingButton::WindowMode,
+        SettingButton::Resolution,
+        SettingButton::TimeControl,
+        SettingButton::Theme,
+        SettingButton::Colorblind,
+        SettingButton::FigureMarkers,
+        SettingButton::HighContrast,
+        SettingButton::TextSize,
+    ];
+
+    fn label(&self, settings: &Settings) -> String {
+        let percent = |value: f32| format!("{}%", (value * 100.).round());
+
+        match self {
+            SettingButton::MasterVolume => format!("Volume: {}", percent(settings.master_volume)),
+            SettingButton::EffectVolume => {
+                format!("Effects: {}", percent(settings.effect_volume))
+            }
+            SettingButton::SlideDuration => match settings.slide_duration {
+                duration if duration <= 0. => "Slide animation: off".to_string(),
+                duration => format!("Slide animation: {} s", duration),
+            },
+            SettingButton::MoveHighlights => {
+                format!(
+                    "Move highlights: {}",
+                    format_switch(settings.move_highlights)
+                )
+            }
+            SettingButton::SelectionHighlight => format!(
+                "Selection highlight: {}",
+                format_switch(settings.selection_highlight)
+            ),
+            SettingButton::Coordinates => {
+                format!("Coordinates: {}", format_switch(settings.coordinates))
+            }
+            SettingButton::AutoRotate => {
+                format!("Rotate board: {}", format_switch(settings.auto_rotate))
+            }
+            SettingButton::WindowMode => {
+                format!("Window: {}", format_window_mode(settings.window_mode))
+            }
+            SettingButton::Resolution => {
+                format!(
+                    "Resolution: {}x{}",
+                    settings.resolution.0, settings.resolution.1
+                )
+            }
+            SettingButton::TimeControl => format!(
+                "Default time: {}",
+                TIME_CONTROL_PRESETS[settings.time_control].0
+            ),
+            SettingButton::Theme => format!("Theme: {}", settings.theme),
+            SettingButton::Colorblind => {
+                format!("Colorblind palette: {}", format_switch(settings.colorblind))
+            }
+            SettingButton::FigureMarkers => {
+                format!("Figure markers: {}", format_switch(settings.figure_markers))
+            }
+            SettingButton::HighContrast => {
+                format!("High contrast: {}", format_switch(settings.high_contrast))
+            }
+            SettingButton::TextSize => format!("Text size: {}", percent(settings.text_size)),
+        }
+    }
+}
+
+// gets the value after `current`, starting over after the last one
+fn next_value(values: &[f32], current: f32) -> f32 {
+    values
+        .iter()
+        .copied()
+        .find(|value| current < *value)
+        .unwrap_or(values[0])
+}
+
+/// Spawns a button for each setting, used by the settings screen and the pause menu.
+pub fn spawn_setting_buttons(
+    parent: &mut ChildBuilder,
+    settings: &Settings,
+    asset_server: &AssetServer,
+) {
+    // two columns, so that all of them fit on the screen
+    parent.spawn(NodeBundle::default()).with_children(|parent| {
+        for column in SettingButton::ALL.chunks(8) {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for button in column {
+                        spawn_setting_button(parent, *button, settings, asset_server);
+                    }
+                });
+        }
+    });
+}
+
+fn spawn_setting_button(
+    parent: &mut ChildBuilder,
+    button: SettingButton,
+    settings: &Settings,
+    asset_server: &AssetServer,
+) {
+    parent
+        .spawn((
+            button,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(330.),
+                    height: Val::Px(40.),
+                    margin: UiRect::all(Val::Px(4.)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                button.label(settings),
+                text_style(asset_server, 24.),
+            ));
+        });
+}
+
+fn setting_buttons(
+    mut q_button: Query<(&Interaction, &SettingButton, &mut BackgroundColor), Changed<Interaction>>,
+    mut settings: ResMut<Settings>,
+    mut time_control: ResMut<TimeControl>,
+    game_state: Res<State<GameState>>,
+    themes: Res<Themes>,
+) {
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            SettingButton::MasterVolume => {
+                settings.master_volume = next_value(VOLUMES, settings.master_volume);
+            }
+            SettingButton::EffectVolume => {
+                settings.effect_volume = next_value(VOLUMES, settings.effect_volume);
+            }
+            SettingButton::SlideDuration => {
+                settings.slide_duration = next_value(SLIDE_DURATIONS, settings.slide_duration);
+            }
+            SettingButton::MoveHighlights => settings.move_highlights = !settings.move_highlights,
+            SettingButton::SelectionHighlight => {
+                settings.selection_highlight = !settings.selection_highlight;
+            }
+            SettingButton::Coordinates => settings.coordinates = !settings.coordinates,
+            SettingButton::AutoRotate => settings.auto_rotate = !settings.auto_rotate,
+            SettingButton::WindowMode => {
+                let current = WINDOW_MODES
+                    .iter()
+                    .position(|window_mode| *window_mode == settings.window_mode)
+                    .unwrap_or(0);
+
+                settings.window_mode = WINDOW_MODES[(current + 1) % WINDOW_MODES.len()];
+            }
+            SettingButton::Resolution => {
+                let next = RESOLUTIONS
+                    .iter()
+                    .position(|resolution| *resolution == settings.resolution)
+                    .map_or(0, |current| (current + 1) % RESOLUTIONS.len());
+
+                settings.resolution = RESOLUTIONS[next];
+            }
+            SettingButton::TimeControl => {
+                settings.time_control = (settings.time_control + 1) % TIME_CONTROL_PRESETS.len();
+
+                // the time control of the game in progress stays
+                if *game_state.get() != GameState::InGame {
+                    *time_control = settings.time_control();
+                }
+            }
+            SettingButton::Theme => settings.theme = themes.next_name(&settings.theme).to_string(),
+            SettingButton::Colorblind => settings.colorblind = !settings.colorblind,
+            SettingButton::FigureMarkers => settings.figure_markers = !settings.figure_markers,
+            SettingButton::HighContrast => settings.high_contrast = !settings.high_contrast,
+            SettingButton::TextSize => {
+                settings.text_size = next_value(TEXT_SIZES, settings.text_size);
+            }
+        }
+    }
+}
+
+fn update_setting_labels(
+    settings: Res<Settings>,
+    q_button: Query<(&SettingButton, &Children)>,
+    mut q_text: Query<&mut Text>,
+) {
+    for (button, children) in &q_button {
+        let label = button.label(&settings);
+
+        for child in children {
+            if let Ok(mut text) = q_text.get_mut(*child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.clone();
+                }
+            }
+        }
+    }
+}
@@ -2,29 +2,61 @@ use bevy::prelude::*;
 
 use self::board::*;
 use self::board_highlights::*;
+use self::board_view::BoardViewPlugin;
 use self::capturing::*;
+use self::chat::ChatPlugin;
+use self::coordinate_labels::CoordinateLabelsPlugin;
+use self::external_engine::ExternalEnginePlugin;
 use self::figure::*;
+use self::game_setup::GameSetupPlugin;
+use self::keyboard::KeyboardPlugin;
 use self::moving::*;
+use self::network::NetworkPlugin;
+use self::online::OnlinePlugin;
+use self::pause_menu::PauseMenuPlugin;
 use self::player_interaction::*;
-use self::shieldwall_capturing::*;
+use self::players::*;
+use self::record::RecordPlugin;
+use self::rules::{GameOutcome, NoLegalMovesOutcome, Rules, TaflGame};
+use self::save::SavePlugin;
 use self::sounds::SoundsPlugin;
 use self::spawn_data::*;
 use self::spawning::*;
+use self::time_control::*;
 use self::ui::*;
 use self::victory_ui::VictoryUiPlugin;
 use self::win_conditions::*;
+use crate::game::settings::Settings;
 use crate::game::GameState;
 
-mod board;
+pub mod ai;
+pub mod board;
 mod board_highlights;
+mod board_view;
 mod capturing;
-mod figure;
+pub mod chat;
+mod coordinate_labels;
+pub mod engine_protocol;
+mod external_engine;
+pub mod figure;
+pub mod game_setup;
+mod keyboard;
 mod moving;
+pub mod network;
+pub mod network_protocol;
+pub mod notation;
+pub mod online;
+mod pause_menu;
 mod player_interaction;
-mod shieldwall_capturing;
+pub mod players;
+pub mod record;
+pub mod rules;
+pub mod save;
+pub mod server_protocol;
 mod sounds;
 pub mod spawn_data;
 mod spawning;
+pub mod time_control;
 mod ui;
 mod victory_ui;
 mod win_conditions;
@@ -36,24 +68,40 @@ impl Plugin for TaflPlugin {
         app.add_plugins(UiPlugin)
             .add_plugins(VictoryUiPlugin)
             .add_plugins(SoundsPlugin)
+            .add_plugins(ExternalEnginePlugin)
+            .add_plugins(NetworkPlugin)
+            .add_plugins(OnlinePlugin)
+            .add_plugins(ChatPlugin)
+            .add_plugins(RecordPlugin)
+            .add_plugins(GameSetupPlugin)
+            .add_plugins(SavePlugin)
+            .add_plugins(PauseMenuPlugin)
+            .add_plugins(KeyboardPlugin)
+            .add_plugins(CoordinateLabelsPlugin)
+            .add_plugins(BoardViewPlugin)
             .add_event::<SpawnBoardEvent>()
             .add_event::<SpawnFiguresEvent>()
+            .add_event::<ResetFiguresEvent>()
             .add_event::<SpawnHighlightsEvent>()
             .add_event::<DespawnHighlightsEvent>()
+            .add_event::<SelectFigureEvent>()
             .add_event::<TryMoveFigureEvent>()
             .add_event::<MoveFigureEvent>()
+            .add_event::<FigureMovedEvent>()
             .add_event::<ReleaseSelectedFigureEvent>()
             .add_event::<CaptureCheckEvent>()
             .add_event::<EndMoveEvent>()
             .add_event::<KingOnCornerCheckEvent>()
             .add_event::<KingSurroundedCheckEvent>()
+            .add_event::<NoLegalMovesCheckEvent>()
             .add_event::<EndGameEvent>()
-            .add_systems(Update, (spawn_board, spawn_figures).chain())
+            .add_systems(Update, (spawn_board, reset_figures, spawn_figures).chain())
             .add_systems(
                 Update,
                 (
                     (
                         on_mouse_pressed,
+                        select_figure,
                         drag_grabbed,
                         on_mouse_released,
                         try_move_figure,
@@ -64,17 +112,25 @@ impl Plugin for TaflPlugin {
                         end_move,
                         king_on_corner_check,
                         king_surrounded_check,
+                        no_legal_moves_check,
                         game_timer_check,
                         on_game_end,
                     )
                         .chain()
                         .run_if(in_state(TaflState::Playing)),
-                    spawn_highlights.after(on_mouse_pressed),
+                    spawn_highlights.after(select_figure),
                     despawn_highlights.after(on_mouse_released),
+                    hide_selection_indicator.after(select_figure),
                 )
                     .run_if(in_state(GameState::InGame)),
             )
+            .add_systems(
+                Update,
+                (apply_settings, show_figure_markers).run_if(resource_changed::<Settings>),
+            )
             .add_systems(OnEnter(GameState::InGame), spawn_hnefatafl)
+            .add_systems(OnEnter(TaflState::Paused), drop_selection)
+            .add_systems(OnEnter(TaflState::Menu), drop_selection)
             .add_systems(
                 OnExit(GameState::InGame),
                 (
@@ -88,6 +144,9 @@ impl Plugin for TaflPlugin {
             .insert_resource(SelectionOptions::default())
             .insert_resource(SelectedFigure::default())
             .insert_resource(MoveFigureOptions::default())
+            .init_resource::<HighlightOptions>()
+            .init_resource::<Players>()
+            .init_resource::<TimeControl>()
             .init_state::<TaflState>();
     }
 }
@@ -104,9 +163,23 @@ fn clear_resources(
     next_tafl_state.set(TaflState::Playing);
 }
 
+fn apply_settings(
+    settings: Res<Settings>,
+    mut move_figure_options: ResMut<MoveFigureOptions>,
+    mut highlight_options: ResMut<HighlightOptions>,
+) {
+    move_figure_options.slide_duration = settings.slide_duration;
+    highlight_options.possible_moves = settings.move_highlights;
+    highlight_options.selection = settings.selection_highlight;
+}
+
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 enum TaflState {
     #[default]
     Playing,
+    // waiting for something outside of the game, e.g. a player to reconnect
+    Paused,
+    // the players are in the pause menu, see `pause_menu`
+    Menu,
     Ended,
 }
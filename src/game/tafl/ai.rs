@@ -0,0 +1,245 @@
+use std::time::{Duration, Instant};
+
+use crate::game::tafl::rules::*;
+use crate::game::tafl::*;
+
+/// The score of a won position, reduced by the number of moves it takes to win.
+pub const WIN_SCORE: i32 = 1_000_000;
+
+// evaluation weights, in terms of an attacker soldier being worth 100
+const ATTACKER_VALUE: i32 = 100;
+const DEFENDER_VALUE: i32 = 150;
+const KING_CORNER_DISTANCE_VALUE: i32 = 15;
+const KING_OPEN_ROUTE_VALUE: i32 = 400;
+const KING_ATTACKED_SIDE_VALUE: i32 = 50;
+
+pub struct SearchOptions {
+    pub max_depth: u32,
+    pub time_limit: Duration,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            time_limit: Duration::from_secs(2),
+        }
+    }
+}
+
+pub struct SearchResult {
+    /// None if the side to move has no legal moves.
+    pub best_move: Option<Move>,
+    /// The score of the position from the perspective of the side to move.
+    pub score: i32,
+    /// The depth of the last completed iteration.
+    pub depth: u32,
+    pub nodes: u64,
+}
+
+/// Searches for the best move of the side to move using iterative deepening alpha-beta search.
+pub fn search(game: &TaflGame, options: &SearchOptions) -> SearchResult {
+    let mut searcher = Searcher {
+        deadline: Instant::now() + options.time_limit,
+        nodes: 0,
+        timed_out: false,
+    };
+
+    let mut result = SearchResult {
+        best_move: game.legal_moves().first().copied(),
+        score: evaluate(game),
+        depth: 0,
+        nodes: 0,
+    };
+
+    for depth in 1..=options.max_depth {
+        let Some((best_move, score)) = searcher.search_root(game, depth, result.best_move) else {
+            break;
+        };
+
+        result.best_move = Some(best_move);
+        result.score = score;
+        result.depth = depth;
+
+        // searching deeper won't change a forced result
+        if WIN_SCORE - (depth as i32) <= score.abs() {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result
+}
+
+struct Searcher {
+    deadline: Instant,
+    nodes: u64,
+    timed_out: bool,
+}
+
+impl Searcher {
+    /// Returns the best move and its score, or None if there are no moves or the time ran out.
+    fn search_root(
+        &mut self,
+        game: &TaflGame,
+        depth: u32,
+        previous_best_move: Option<Move>,
+    ) -> Option<(Move, i32)> {
+        let mut moves = order_moves(game, game.legal_moves());
+
+        // the best move of the previous iteration is likely to be good again
+        if let Some(previous_best_move) = previous_best_move {
+            if let Some(index) = moves.iter().position(|m| *m == previous_best_move) {
+                moves.swap(0, index);
+            }
+        }
+
+        let mut best: Option<(Move, i32)> = None;
+        let mut alpha = -WIN_SCORE - 1;
+        let beta = WIN_SCORE + 1;
+
+        for m in moves {
+            let mut child = game.clone();
+            child.make_move(m).unwrap();
+
+            let score = -self.negamax(&child, depth - 1, -beta, -alpha, 1);
+
+            if self.timed_out {
+                return None;
+            }
+
+            if best.is_none() || alpha < score {
+                alpha = score;
+                best = Some((m, score));
+            }
+        }
+
+        best
+    }
+
+    fn negamax(&mut self, game: &TaflGame, depth: u32, mut alpha: i32, beta: i32, ply: i32) -> i32 {
+        self.nodes += 1;
+
+        // checking the time is relatively slow, so it is only done every 1024 nodes
+        if self.nodes & 1023 == 0 && self.deadline <= Instant::now() {
+            self.timed_out = true;
+        }
+
+        if self.timed_out {
+            return 0;
+        }
+
+        if let Some(outcome) = game.outcome() {
+            return match outcome {
+                GameOutcome::Win(side) if side == game.side_to_move() => WIN_SCORE - ply,
+                GameOutcome::Win(_) => -(WIN_SCORE - ply),
+                GameOutcome::Draw => 0,
+            };
+        }
+
+        if depth == 0 {
+            return evaluate(game);
+        }
+
+        for m in order_moves(game, game.legal_moves()) {
+            let mut child = game.clone();
+            child.make_move(m).unwrap();
+
+            let score = -self.negamax(&child, depth - 1, -beta, -alpha, ply + 1);
+
+            if beta <= score {
+                return beta;
+            }
+
+            if alpha < score {
+                alpha = score;
+            }
+        }
+
+        alpha
+    }
+}
+
+/// Puts the moves that are likely to be good first, which makes alpha-beta pruning cut more.
+fn order_moves(game: &TaflGame, moves: Vec<Move>) -> Vec<Move> {
+    let mut king_moves = vec![];
+    let mut other_moves = vec![];
+
+    for m in moves {
+        match game.get(m.from) {
+            Some(FigureType {
+                kind: FigureKind::King,
+                ..
+            }) => king_moves.push(m),
+            _ => other_moves.push(m),
+        }
+    }
+
+    king_moves.extend(other_moves);
+    king_moves
+}
+
+/// Evaluates a position statically from the perspective of the side to move.
+pub fn evaluate(game: &TaflGame) -> i32 {
+    let rules = game.rules();
+
+    // score from the attackers' perspective
+    let mut score = 0;
+
+    for (_, figure_type) in game.iter_figures() {
+        match (figure_type.side, figure_type.kind) {
+            (Side::Attacker, _) => score += ATTACKER_VALUE,
+            (Side::Defender, FigureKind::Soldier) => score -= DEFENDER_VALUE,
+            (Side::Defender, FigureKind::King) => {}
+        }
+    }
+
+    for king_position in game.king_positions() {
+        let corner_distance = rules
+            .end_positions
+            .iter()
+            .map(|end_position| {
+                end_position.x.abs_diff(king_position.x) + end_position.y.abs_diff(king_position.y)
+            })
+            .min()
+            .unwrap_or(0);
+
+        score += corner_distance as i32 * KING_CORNER_DISTANCE_VALUE;
+
+        let open_routes = game
+            .possible_moves(king_position)
+            .iter()
+            .filter(|position| rules.end_positions.contains(position))
+            .count();
+
+        score -= open_routes as i32 * KING_OPEN_ROUTE_VALUE;
+
+        let directions: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+        for (dx, dy) in directions {
+            let x = king_position.x as isize + dx;
+            let y = king_position.y as isize + dy;
+
+            if x < 0 || y < 0 {
+                continue;
+            }
+
+            let neighbor = game.get(Position {
+                x: x as usize,
+                y: y as usize,
+            });
+
+            if let Some(FigureType {
+                side: Side::Attacker,
+                ..
+            }) = neighbor
+            {
+                score += KING_ATTACKED_SIDE_VALUE;
+            }
+        }
+    }
+
+    match game.side_to_move() {
+        Side::Attacker => score,
+        Side::Defender => -score,
+    }
+}
@@ -1,5 +1,7 @@
 use bevy::{prelude::*, utils::HashMap};
 
+use crate::game::tafl::*;
+
 pub enum Axis2 {
     X,
     Y,
@@ -19,6 +21,7 @@ pub struct BoardOptions {
     pub cols: usize,
     pub throne_position: Position,
     pub end_positions: Vec<Position>,
+    pub no_legal_moves: NoLegalMovesOutcome,
     pub figures: HashMap<Position, Entity>,
     pub field_size: f32,
     pub border_width: f32,
@@ -34,6 +37,9 @@ pub struct Board {
     pub throne_position: Position,
     pub end_positions: Vec<Position>,
 
+    // what happens when the side to move can't move any of its figures
+    pub no_legal_moves: NoLegalMovesOutcome,
+
     // position - figure_entity map
     pub figures: HashMap<Position, Entity>,
 
@@ -56,6 +62,9 @@ pub struct Board {
 
     // the z-axis coordinate figures displayed on the board should have
     pub figure_z: f32,
+
+    // whether the board is seen from the other side, turned by 180 degrees, see `board_view`
+    pub rotated: bool,
 }
 
 impl Board {
@@ -66,6 +75,7 @@ impl Board {
 
         let throne_position = options.throne_position;
         let end_positions = options.end_positions;
+        let no_legal_moves = options.no_legal_moves;
 
         let figures = options.figures;
 
@@ -94,6 +104,7 @@ impl Board {
 
             throne_position,
             end_positions,
+            no_legal_moves,
 
             figures,
 
@@ -108,6 +119,8 @@ impl Board {
             upper_left_field_position,
 
             figure_z: figures_z,
+
+            rotated: false,
         }
     }
 
@@ -130,11 +143,12 @@ impl Board {
         let x = x_adjusted as usize / self.field_offset as usize;
         let y = y_adjusted as usize / self.field_offset as usize;
 
-        Some(Position { x, y })
+        Some(self.view_position(Position { x, y }))
     }
 
     /// Converts a position on the board to a world position.
     pub fn board_to_world(&self, position: Position) -> Vec2 {
+        let position = self.view_position(position);
         let upper_left = self.upper_left_field_position;
         let field_offset = self.field_offset;
 
@@ -144,144 +158,21 @@ impl Board {
         Vec2 { x, y }
     }
 
-    /// Determines whether the provided `position` is on the board or not.
-    pub fn is_on_board(&self, position: Position) -> bool {
-        return position.x < self.cols || position.y < self.rows;
-    }
-
-    /// Gets all neighboring figure entities of a certain BoardPosition.
-    /// Neighboring means that either x +/- 1 or y +/- 1 relative to the position.
-    pub fn get_neighbors(&self, position: Position) -> Vec<Entity> {
-        let mut result: Vec<Entity> = vec![];
-
-        // left
-        if 0 <= position.x as isize - 1 {
-            if let Some(figure_entity) = self.figures.get(&Position {
-                x: position.x - 1,
-                y: position.y,
-            }) {
-                result.push(*figure_entity);
-            }
-        }
-
-        // right
-        if position.x + 1 < self.cols {
-            if let Some(figure_entity) = self.figures.get(&Position {
-                x: position.x + 1,
-                y: position.y,
-            }) {
-                result.push(*figure_entity);
-            }
+    /// Converts between a position on the board and the position it is displayed at, they only
+    /// differ if the board is rotated.
+    pub fn view_position(&self, position: Position) -> Position {
+        if !self.rotated {
+            return position;
         }
 
-        // bottom
-        if 0 <= position.y as isize - 1 {
-            if let Some(figure_entity) = self.figures.get(&Position {
-                x: position.x,
-                y: position.y - 1,
-            }) {
-                result.push(*figure_entity);
-            }
-        }
-
-        // top
-        if position.y + 1 < self.rows {
-            if let Some(figure_entity) = self.figures.get(&Position {
-                x: position.x,
-                y: position.y + 1,
-            }) {
-                result.push(*figure_entity);
-            }
-        }
-
-        result
-    }
-
-    pub fn get_neighbors2(&self, position: Position) -> Neighbors {
-        let left = if 1 <= position.x {
-            let position = Position {
-                x: position.x - 1,
-                y: position.y,
-            };
-
-            Neighbor::from_pos_and_option(position, self.figures.get(&position).copied())
-        } else {
-            Neighbor::Wall
-        };
-
-        let right = if position.x < self.cols - 1 {
-            let position = Position {
-                x: position.x + 1,
-                y: position.y,
-            };
-
-            Neighbor::from_pos_and_option(position, self.figures.get(&position).copied())
-        } else {
-            Neighbor::Wall
-        };
-
-        let bottom = if 1 <= position.y {
-            let position = Position {
-                x: position.x,
-                y: position.y - 1,
-            };
-
-            Neighbor::from_pos_and_option(position, self.figures.get(&position).copied())
-        } else {
-            Neighbor::Wall
-        };
-
-        let top = if position.y < self.rows - 1 {
-            let position = Position {
-                x: position.x,
-                y: position.y + 1,
-            };
-
-            Neighbor::from_pos_and_option(position, self.figures.get(&position).copied())
-        } else {
-            Neighbor::Wall
-        };
-
-        Neighbors {
-            left,
-            right,
-            bottom,
-            top,
+        Position {
+            x: self.cols - 1 - position.x,
+            y: self.rows - 1 - position.y,
         }
     }
-}
-
-pub struct Neighbors {
-    pub left: Neighbor,
-    pub right: Neighbor,
-    pub bottom: Neighbor,
-    pub top: Neighbor,
-}
 
-impl Neighbors {
-    pub fn to_vec(self) -> Vec<Neighbor> {
-        let mut vec = vec![];
-        vec.push(self.left);
-        vec.push(self.right);
-        vec.push(self.bottom);
-        vec.push(self.top);
-
-        vec
-    }
-}
-
-#[derive(PartialEq, Eq, Copy, Clone)]
-pub enum Neighbor {
-    Some { position: Position, entity: Entity },
-    Empty { position: Position },
-    Wall,
-}
-
-impl Neighbor {
-    fn from_pos_and_option(position: Position, option: Option<Entity>) -> Self {
-        match option {
-            Some(entity) => Neighbor::Some { position, entity },
-            None => Neighbor::Empty { position },
-        }
+    /// Determines whether the provided `position` is on the board or not.
+    pub fn is_on_board(&self, position: Position) -> bool {
+        return position.x < self.cols || position.y < self.rows;
     }
 }
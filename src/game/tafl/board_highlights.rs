@@ -1,6 +1,24 @@
 use crate::game::tafl::*;
 use bevy::sprite::MaterialMesh2dBundle;
 
+/// Which of the highlights are shown, set from the settings.
+#[derive(Resource)]
+pub struct HighlightOptions {
+    /// The fields the selected figure can move to.
+    pub possible_moves: bool,
+    /// The field of the selected figure.
+    pub selection: bool,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            possible_moves: true,
+            selection: true,
+        }
+    }
+}
+
 #[derive(Component, Clone)]
 pub struct BoardHighlights {
     pub mesh: Handle<Mesh>,
@@ -26,8 +44,14 @@ pub struct DespawnHighlightsEvent {
 pub fn spawn_highlights(
     mut q_board_highlights: Query<(&Board, &mut BoardHighlights)>,
     mut event: EventReader<SpawnHighlightsEvent>,
+    highlight_options: Res<HighlightOptions>,
     mut commands: Commands,
 ) {
+    if !highlight_options.possible_moves {
+        event.clear();
+        return;
+    }
+
     for ev in event.read() {
         let Ok((board, mut highlights)) = q_board_highlights.get_mut(ev.board_entity) else {
             return;
@@ -0,0 +1,177 @@
+use bevy::ecs::system::SystemParam;
+
+use crate::game::camera::CameraFit;
+use crate::game::settings::Settings;
+use crate::game::tafl::chat::chat_focused;
+use crate::game::tafl::*;
+
+// the part of the window the board fills, the turn indicators are on its left and right
+const BOARD_WINDOW_PART: Vec2 = Vec2::new(0.6, 0.9);
+
+/// Fits the board into the window and turns it around so that the players can see it from their
+/// side, F flips it by hand.
+///
+/// The board is seen from the side of the attackers unless it is rotated, `Board::board_to_world`
+/// and `Board::world_to_board` take the rotation into account.
+pub struct BoardViewPlugin;
+
+impl Plugin for BoardViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                fit_board_to_window,
+                auto_rotate_board,
+                flip_board.run_if(not(chat_focused)),
+                rotate_board,
+            )
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        )
+        .add_systems(OnExit(GameState::InGame), reset_board_view)
+        .init_resource::<BoardView>();
+    }
+}
+
+/// How the board should be seen, the board is turned as soon as no figure is moving.
+#[derive(Resource, Default)]
+pub struct BoardView {
+    pub rotated: bool,
+    // the side the board was last automatically turned to
+    auto_side: Option<Side>,
+}
+
+/// Gets the side the board is seen from when it is rotated automatically: the side of the player
+/// in games against an engine or over the network, the side to move in hot-seat games.
+fn perspective(players: &Players, side_to_move: Side) -> Side {
+    match (
+        players.is_human(Side::Attacker),
+        players.is_human(Side::Defender),
+    ) {
+        (true, true) => side_to_move,
+        (false, true) => Side::Defender,
+        _ => Side::Attacker,
+    }
+}
+
+fn auto_rotate_board(
+    settings: Res<Settings>,
+    players: Res<Players>,
+    q_turn_tracker: Query<&TurnTracker, With<MainBoard>>,
+    mut board_view: ResMut<BoardView>,
+) {
+    if !settings.auto_rotate {
+        return;
+    }
+
+    let Ok(turn_tracker) = q_turn_tracker.get_single() else {
+        return;
+    };
+
+    // only when the perspective changes, so that a board flipped by hand stays flipped
+    let side = perspective(&players, turn_tracker.side);
+    if board_view.auto_side == Some(side) {
+        return;
+    }
+
+    board_view.rotated = side == Side::Defender;
+    board_view.auto_side = Some(side);
+}
+
+fn flip_board(input: Res<ButtonInput<KeyCode>>, mut board_view: ResMut<BoardView>) {
+    if input.just_pressed(KeyCode::KeyF) {
+        board_view.rotated = !board_view.rotated;
+    }
+}
+
+// the transforms of the fields and the figures are changed by the same system
+type SelectionIndicatorFilter = (
+    With<SelectionIndicator>,
+    Without<BoardField>,
+    Without<Figure>,
+);
+
+/// The selected figure with the indicator around it, which moves with the board.
+#[derive(SystemParam)]
+struct Selection<'w, 's> {
+    options: Res<'w, SelectionOptions>,
+    selected_figure: Res<'w, SelectedFigure>,
+    q_indicator: Query<'w, 's, &'static mut Transform, SelectionIndicatorFilter>,
+}
+
+/// Rotates the board to the view and moves everything on it to the new place of its field.
+fn rotate_board(
+    board_view: Res<BoardView>,
+    mut selection: Selection,
+    mut q_board: Query<(Entity, &mut Board), With<MainBoard>>,
+    mut q_field: Query<(&BoardField, &mut Transform), Without<Figure>>,
+    mut q_figure: Query<(&Figure, &mut Transform), Without<BoardField>>,
+    mut spawn_highlights_event: EventWriter<SpawnHighlightsEvent>,
+) {
+    // the figure sliding to its field would end up on the wrong field
+    if selection.options.selection_locked {
+        return;
+    }
+
+    let Ok((board_entity, mut board)) = q_board.get_single_mut() else {
+        return;
+    };
+
+    if board.rotated == board_view.rotated {
+        return;
+    }
+
+    board.rotated = board_view.rotated;
+
+    for (field, mut transform) in &mut q_field {
+        let z = transform.translation.z;
+        transform.translation = board.board_to_world(field.position).extend(z);
+    }
+
+    // a grabbed figure is raised above the others, the z-axis coordinates stay
+    for (figure, mut transform) in &mut q_figure {
+        let z = transform.translation.z;
+        transform.translation = board.board_to_world(figure.position).extend(z);
+    }
+
+    if let SelectedFigure::Some { figure_entity, .. } = *selection.selected_figure {
+        let Ok((figure, _)) = q_figure.get(figure_entity) else {
+            return;
+        };
+
+        for mut transform in &mut selection.q_indicator {
+            let z = transform.translation.z;
+            transform.translation = board.board_to_world(figure.position).extend(z);
+        }
+
+        // replaces the highlights of the old fields
+        spawn_highlights_event.send(SpawnHighlightsEvent {
+            board_entity,
+            positions: possible_moves(&board, *figure),
+        });
+    }
+}
+
+/// Keeps the board with its coordinate labels in view, whatever its size and the size of the
+/// window.
+fn fit_board_to_window(
+    q_board: Query<&Board, Added<MainBoard>>,
+    mut camera_fit: ResMut<CameraFit>,
+) {
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+
+    // the outer border and the coordinate labels
+    let margin = board.outer_border_width + 0.6 * board.field_size;
+
+    *camera_fit = CameraFit {
+        area: Some(Vec2::new(board.width, board.height) + 2. * margin),
+        window_part: BOARD_WINDOW_PART,
+    };
+}
+
+fn reset_board_view(mut board_view: ResMut<BoardView>, mut camera_fit: ResMut<CameraFit>) {
+    *board_view = BoardView::default();
+    *camera_fit = CameraFit::default();
+}
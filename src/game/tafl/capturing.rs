@@ -2,59 +2,11 @@ use crate::game::tafl::*;
 
 // Note: Surrounding the King is a win_condition.
 
-/// Captures a figure.
-pub fn capture(
-    q_board: &mut Query<&mut Board>,
-    q_figure: &Query<&Figure>,
-    board_entity: Entity,
-    figure_entity: Entity,
-    commands: &mut Commands,
-) {
-    let mut board = q_board.get_mut(board_entity).unwrap();
-    let figure = q_figure.get(figure_entity).unwrap();
-
-    board.figures.remove(&figure.position);
-    commands.entity(figure_entity).despawn();
-}
-
-/// Determines whether a `position` on the board is blocked for a `figure`.
-///
-/// A blocked position means it either:
-/// - contains an enemy
-/// - is an end position (one of the 4 corners of the board)
-/// - is the empty throne (the center position of the board)
-fn is_blocked(
-    q_board: &Query<&Board>,
-    q_figure: &Query<&Figure>,
-    board_entity: Entity,
-    figure_entity: Entity,
-    position: Position,
-) -> bool {
-    let board = q_board.get(board_entity).unwrap();
-    let figure = q_figure.get(figure_entity).unwrap();
-
-    let other = board.figures.get(&position);
-
-    // contains enemy
-    if let Some(other_entity) = other {
-        let other_figure = q_figure.get(*other_entity).unwrap();
-
-        if other_figure.side != figure.side {
-            return true;
-        }
-    }
-
-    // is end position
-    if board.end_positions.contains(&position) {
-        return true;
+/// Captures the figure at `position`.
+pub fn capture(board: &mut Board, position: Position, commands: &mut Commands) {
+    if let Some(figure_entity) = board.figures.remove(&position) {
+        commands.entity(figure_entity).despawn();
     }
-
-    // is empty throne
-    if board.throne_position == position && other == None {
-        return true;
-    }
-
-    false
 }
 
 #[derive(Event)]
@@ -63,6 +15,8 @@ pub struct CaptureCheckEvent {
     pub moved_figure_entity: Entity,
 }
 
+/// Captures the figures the moved figure captures, including shieldwall captures, as decided by
+/// `TaflGame::capture_around`.
 pub fn capture_check(
     mut event: EventReader<CaptureCheckEvent>,
     mut q_board: Query<&mut Board>,
@@ -73,96 +27,26 @@ pub fn capture_check(
 ) {
     for ev in event.read() {
         let board_entity = ev.board_entity;
-        let moved_figure_entity = ev.moved_figure_entity;
-
-        let mut capture_happened = false;
 
-        let board = q_board.get(board_entity).unwrap();
-        let moved_figure = q_figure.get(moved_figure_entity).unwrap();
+        let mut board = q_board.get_mut(board_entity).unwrap();
+        let moved_figure = q_figure.get(ev.moved_figure_entity).unwrap();
 
-        let neighbors = board.get_neighbors(moved_figure.position);
+        let mut game = TaflGame::from_board(&board, &q_figure, moved_figure.side);
+        let (captured, king_was_attacked) = game.capture_around(moved_figure.position);
 
-        for figure_entity in neighbors {
-            let figure = q_figure.get(figure_entity).unwrap();
-
-            // only enemies can be captured
-            if figure.side == moved_figure.side {
-                continue;
-            }
-
-            // the king can't be captured, but may be part of a shieldwall capture
-            //
-            // every time the king is "part" of a capture it has to be checked
-            // whether it is surrounded
-            if figure.kind == FigureKind::King {
-                capture_happened |= shieldwall_capture_check(
-                    &mut q_board,
-                    &q_figure,
-                    ev.board_entity,
-                    figure_entity,
-                    &mut commands,
-                );
-
-                king_surrounded_check_event.send(KingSurroundedCheckEvent { board_entity });
-                continue;
-            }
-
-            // if compared to the moved_figrure the opposite side of the figure is blocked than
-            // it is captured
-
-            // TODO implement addition/substraction trait for Position
-
-            let mfx = moved_figure.position.x;
-            let mfy = moved_figure.position.y;
-
-            let fx = figure.position.x;
-            let fy = figure.position.y;
-
-            let diff_x = fx as i32 - mfx as i32;
-            let diff_y = fy as i32 - mfy as i32;
-
-            let x = fx as i32 + diff_x;
-            let y = fy as i32 + diff_y;
-
-            if x < 0 || y < 0 {
-                continue;
-            }
-
-            let other_position = Position {
-                x: x as usize,
-                y: y as usize,
-            };
-
-            if is_blocked(
-                &mut q_board.transmute_lens::<&Board>().query(),
-                &q_figure,
-                board_entity,
-                figure_entity,
-                other_position,
-            ) {
-                capture(
-                    &mut q_board,
-                    &q_figure,
-                    board_entity,
-                    figure_entity,
-                    &mut commands,
-                );
+        for position in &captured {
+            capture(&mut board, *position, &mut commands);
+        }
 
-                capture_happened |= true;
-            } else {
-                capture_happened |= shieldwall_capture_check(
-                    &mut q_board,
-                    &q_figure,
-                    board_entity,
-                    figure_entity,
-                    &mut commands,
-                )
-            }
+        // every time the king is "part" of a capture it has to be checked whether it is
+        // surrounded
+        if king_was_attacked {
+            king_surrounded_check_event.send(KingSurroundedCheckEvent { board_entity });
         }
 
         end_move_event.send(EndMoveEvent {
             board_entity,
-            capture_happened,
+            capture_happened: !captured.is_empty(),
         });
     }
 }
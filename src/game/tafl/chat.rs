@@ -0,0 +1,394 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::ui::RelativeCursorPosition;
+use bevy::window::ReceivedCharacter;
+
+use crate::game::tafl::notation::format_side;
+use crate::game::tafl::record::*;
+use crate::game::tafl::*;
+use crate::game::widgets::*;
+
+/// The maximum number of characters in a chat message.
+pub const MAX_MESSAGE_LENGTH: usize = 200;
+
+// how far a single line of the mouse wheel scrolls the messages
+const SCROLL_LINE_HEIGHT: f32 = 20.;
+
+pub struct ChatPlugin;
+
+impl Plugin for ChatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChatMessageEvent>()
+            .add_systems(
+                Update,
+                (
+                    type_chat_message,
+                    mute_button,
+                    show_chat_messages,
+                    scroll_chat_messages,
+                )
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .init_resource::<ChatOptions>();
+    }
+}
+
+/// Sent whenever a player wrote a message, either on this instance or on another one.
+#[derive(Event, Clone)]
+pub struct ChatMessageEvent {
+    /// The side of the player who wrote the message.
+    pub side: Side,
+    pub text: String,
+}
+
+#[derive(Resource, Default)]
+pub struct ChatOptions {
+    /// Hides the messages written on other instances.
+    pub muted: bool,
+}
+
+/// Gets the side the messages written on this instance belong to, None if nobody plays here.
+fn writing_side(players: &Players, side_to_move: Side) -> Option<Side> {
+    match (
+        players.is_human(Side::Attacker),
+        players.is_human(Side::Defender),
+    ) {
+        // in hot-seat games the player whose turn it is writes
+        (true, true) => Some(side_to_move),
+        (true, false) => Some(Side::Attacker),
+        (false, true) => Some(Side::Defender),
+        (false, false) => None,
+    }
+}
+
+/// Determines whether the game has a chat, it doesn't when an engine plays.
+pub fn has_chat(players: &Players) -> bool {
+    [Side::Attacker, Side::Defender]
+        .into_iter()
+        .all(|side| !matches!(players.get(side), PlayerKind::ExternalEngine { .. }))
+}
+
+#[derive(Component)]
+struct ChatView;
+
+#[derive(Component, Default)]
+struct ChatMessages {
+    // the number of entries of the record that were checked for messages
+    checked_entries: usize,
+    // how far the messages are scrolled up
+    scroll: f32,
+}
+
+#[derive(Component, Default)]
+pub struct ChatInput {
+    focused: bool,
+    text: String,
+}
+
+/// Determines whether a message is being typed, the keys are left to the chat while it is.
+pub fn chat_focused(q_chat_input: Query<&ChatInput>) -> bool {
+    q_chat_input.iter().any(|chat_input| chat_input.focused)
+}
+
+#[derive(Component)]
+struct MuteButton;
+
+/// Spawns the chat panel below `parent` without moving its other children, used by
+/// `spawn_game_ui`.
+pub fn spawn_chat(parent: &mut ChildBuilder, asset_server: &AssetServer) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(100.),
+                right: Val::Px(0.),
+                width: Val::Px(260.),
+                margin: UiRect::top(Val::Px(30.)),
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            // Header
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        justify_content: JustifyContent::SpaceBetween,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::bottom(Val::Px(5.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Chat",
+                        text_style(asset_server, 20.),
+                    ));
+
+                    parent
+                        .spawn((
+                            MuteButton,
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::axes(Val::Px(8.), Val::Px(2.)),
+                                    ..default()
+                                },
+                                background_color: BUTTON_COLOR.into(),
+                                ..default()
+                            },
+                        ))
+                        .with_children(|parent| {
+                            parent
+                                .spawn(TextBundle::from_section("", text_style(asset_server, 16.)));
+                        });
+                });
+
+            // Messages
+            parent
+                .spawn((
+                    ChatView,
+                    RelativeCursorPosition::default(),
+                    NodeBundle {
+                        style: Style {
+                            height: Val::Px(160.),
+                            padding: UiRect::all(Val::Px(5.)),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::FlexEnd,
+                            overflow: Overflow::clip_y(),
+                            ..default()
+                        },
+                        background_color: Color::rgba(0., 0., 0., 0.4).into(),
+                        ..default()
+                    },
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        ChatMessages::default(),
+                        NodeBundle {
+                            style: Style {
+                                flex_direction: FlexDirection::Column,
+                                flex_shrink: 0.,
+                                ..default()
+                            },
+                            ..default()
+                        },
+                    ));
+                });
+
+            // Input
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        min_height: Val::Px(26.),
+                        margin: UiRect::top(Val::Px(5.)),
+                        padding: UiRect::horizontal(Val::Px(5.)),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.6).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        ChatInput::default(),
+                        TextBundle::from_section("", text_style(asset_server, 16.)).with_style(
+                            Style {
+                                max_width: Val::Px(250.),
+                                ..default()
+                            },
+                        ),
+                    ));
+                });
+        });
+}
+
+/// Lets the players write messages, Enter starts and sends a message.
+fn type_chat_message(
+    mut event: EventReader<ReceivedCharacter>,
+    input: Res<ButtonInput<KeyCode>>,
+    players: Res<Players>,
+    q_turn_tracker: Query<&TurnTracker>,
+    mut q_chat_input: Query<(&mut ChatInput, &mut Text)>,
+    mut chat_message_event: EventWriter<ChatMessageEvent>,
+) {
+    let characters: Vec<ReceivedCharacter> = event.read().cloned().collect();
+
+    let Ok((mut chat_input, mut text)) = q_chat_input.get_single_mut() else {
+        return;
+    };
+
+    let side_to_move = q_turn_tracker
+        .get_single()
+        .map(|turn_tracker| turn_tracker.side)
+        .unwrap_or(Side::Attacker);
+
+    // spectators only read
+    let Some(side) = writing_side(&players, side_to_move) else {
+        if !text.sections[0].value.is_empty() {
+            text.sections[0].value = String::new();
+        }
+        return;
+    };
+
+    if chat_input.focused {
+        for ev in characters {
+            for c in ev.char.chars() {
+                if !c.is_control() && chat_input.text.chars().count() < MAX_MESSAGE_LENGTH {
+                    chat_input.text.push(c);
+                }
+            }
+        }
+
+        if input.just_pressed(KeyCode::Backspace) {
+            chat_input.text.pop();
+        }
+
+        if input.just_pressed(KeyCode::Enter) {
+            let message = chat_input.text.trim().to_string();
+
+            if !message.is_empty() {
+                chat_message_event.send(ChatMessageEvent {
+                    side,
+                    text: message,
+                });
+            }
+
+            chat_input.text.clear();
+            chat_input.focused = false;
+        }
+    } else if input.just_pressed(KeyCode::Enter) {
+        chat_input.focused = true;
+    }
+
+    let value = match chat_input.focused {
+        true => format!("{}_", chat_input.text),
+        false => "Press Enter to chat".to_string(),
+    };
+
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
+
+fn mute_button(
+    mut q_button: Query<(Ref<Interaction>, &mut BackgroundColor, &Children), With<MuteButton>>,
+    mut q_text: Query<&mut Text>,
+    mut chat_options: ResMut<ChatOptions>,
+    mut q_chat_messages: Query<(Entity, &mut ChatMessages)>,
+    mut commands: Commands,
+) {
+    for (interaction, mut background_color, children) in &mut q_button {
+        if interaction.is_changed() {
+            *background_color = button_interaction_color(*interaction);
+
+            if *interaction == Interaction::Pressed {
+                chat_options.muted = !chat_options.muted;
+
+                // show the messages again from the start
+                for (chat_messages_entity, mut chat_messages) in &mut q_chat_messages {
+                    *chat_messages = ChatMessages::default();
+                    commands.entity(chat_messages_entity).despawn_descendants();
+                }
+            }
+        }
+
+        let label = match chat_options.muted {
+            true => "Unmute",
+            false => "Mute",
+        };
+
+        for child in children {
+            if let Ok(mut text) = q_text.get_mut(*child) {
+                if text.sections[0].value != label {
+                    text.sections[0].value = label.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Adds the messages that were recorded since the last time to the chat.
+fn show_chat_messages(
+    record: Res<GameRecord>,
+    players: Res<Players>,
+    chat_options: Res<ChatOptions>,
+    mut q_chat_messages: Query<(Entity, &mut ChatMessages)>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    for (chat_messages_entity, mut chat_messages) in &mut q_chat_messages {
+        if chat_messages.checked_entries == record.entries.len() {
+            continue;
+        }
+
+        let entries = &record.entries[chat_messages.checked_entries.min(record.entries.len())..];
+        chat_messages.checked_entries = record.entries.len();
+
+        commands
+            .entity(chat_messages_entity)
+            .with_children(|parent| {
+                for entry in entries {
+                    let RecordEntry::Chat { side, text, time } = entry else {
+                        continue;
+                    };
+
+                    if chat_options.muted && !players.is_human(*side) {
+                        continue;
+                    }
+
+                    let secs = time.as_secs();
+                    let message = format!(
+                        "[{:0>2}:{:0>2}] {}: {}",
+                        secs / 60,
+                        secs % 60,
+                        format_side(*side),
+                        text
+                    );
+
+                    parent.spawn(
+                        TextBundle::from_section(message, text_style(&asset_server, 16.))
+                            .with_style(Style {
+                                max_width: Val::Px(250.),
+                                ..default()
+                            }),
+                    );
+                }
+            });
+    }
+}
+
+/// Scrolls through the messages with the mouse wheel while the cursor is over them.
+fn scroll_chat_messages(
+    mut event: EventReader<MouseWheel>,
+    q_chat_view: Query<(&RelativeCursorPosition, &Node), With<ChatView>>,
+    mut q_chat_messages: Query<(&mut ChatMessages, &mut Style, &Node)>,
+) {
+    let Ok((cursor_position, view_node)) = q_chat_view.get_single() else {
+        event.clear();
+        return;
+    };
+
+    let mut delta = 0.;
+    for ev in event.read() {
+        delta += match ev.unit {
+            MouseScrollUnit::Line => ev.y * SCROLL_LINE_HEIGHT,
+            MouseScrollUnit::Pixel => ev.y,
+        };
+    }
+
+    if !cursor_position.mouse_over() {
+        return;
+    }
+
+    for (mut chat_messages, mut style, node) in &mut q_chat_messages {
+        // the newest messages are at the bottom, scrolling up reveals older ones
+        let max_scroll = (node.size().y - view_node.size().y).max(0.);
+        let scroll = (chat_messages.scroll + delta).clamp(0., max_scroll);
+
+        if scroll != chat_messages.scroll || style.top != Val::Px(scroll) {
+            chat_messages.scroll = scroll;
+            style.top = Val::Px(scroll);
+        }
+    }
+}
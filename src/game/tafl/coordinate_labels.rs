@@ -0,0 +1,126 @@
+use crate::game::settings::Settings;
+use crate::game::tafl::notation::format_square;
+use crate::game::tafl::*;
+
+/// Labels the files below and above the board and the ranks left and right of it, the same way the
+/// moves are written down.
+pub struct CoordinateLabelsPlugin;
+
+impl Plugin for CoordinateLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (spawn_coordinate_labels, place_coordinate_labels)
+                .chain()
+                .run_if(in_state(GameState::InGame)),
+        );
+    }
+}
+
+/// A label of a file (column) or a rank (row) of the board.
+#[derive(Component, Clone, Copy)]
+pub enum CoordinateLabel {
+    /// A file labeled below the board, or above it if `top`.
+    File { x: usize, top: bool },
+    /// A rank labeled left of the board, or right of it if `right`.
+    Rank { y: usize, right: bool },
+}
+
+/// Splits the notation of a square into its file and its rank, e.g. `a11` into `a` and `11`.
+fn split_square(position: Position, rows: usize) -> (String, String) {
+    let square = format_square(position, rows);
+    let rank_start = square
+        .find(|c: char| c.is_ascii_digit())
+        .unwrap_or(square.len());
+
+    let (file, rank) = square.split_at(rank_start);
+    (file.to_string(), rank.to_string())
+}
+
+fn spawn_coordinate_labels(
+    q_board: Query<(Entity, &Board), Added<MainBoard>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    for (board_entity, board) in &q_board {
+        let text_style = TextStyle {
+            font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+            font_size: 0.35 * board.field_size,
+            color: Color::rgb(0.9, 0.9, 0.9),
+        };
+
+        let files = (0..board.cols).flat_map(|x| {
+            let (file, _) = split_square(Position { x, y: 0 }, board.rows);
+            [false, true].map(|top| (CoordinateLabel::File { x, top }, file.clone()))
+        });
+        let ranks = (0..board.rows).flat_map(|y| {
+            let (_, rank) = split_square(Position { x: 0, y }, board.rows);
+            [false, true].map(|right| (CoordinateLabel::Rank { y, right }, rank.clone()))
+        });
+
+        commands.entity(board_entity).with_children(|parent| {
+            for (label, text) in files.chain(ranks) {
+                parent.spawn((
+                    label,
+                    Name::new("Coordinate"),
+                    Text2dBundle {
+                        text: Text::from_section(text, text_style.clone()),
+                        // placed by `place_coordinate_labels`
+                        visibility: Visibility::Hidden,
+                        ..default()
+                    },
+                ));
+            }
+        });
+    }
+}
+
+/// Keeps the labels next to their files and ranks, also when the board is rotated, and shows them
+/// if the coordinates are turned on in the settings.
+fn place_coordinate_labels(
+    q_board: Query<&Board, With<MainBoard>>,
+    mut q_label: Query<(&CoordinateLabel, &mut Transform, &mut Visibility)>,
+    settings: Res<Settings>,
+) {
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+
+    // the labels are children of the board, so they are placed relative to its corner
+    let corner = board.upper_left_corner_position;
+    // outside of the outer border
+    let distance = board.outer_border_width + 0.3 * board.field_size;
+    let left = corner.x - distance;
+    let right = corner.x + board.width + distance;
+    let top = corner.y + distance;
+    let bottom = corner.y - board.height - distance;
+
+    // the distance of the middle of the field displayed at `index` from the corner
+    let field_middle = |index: usize| index as f32 * board.field_offset + board.field_size / 2.;
+
+    let new_visibility = match settings.coordinates {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    };
+
+    for (label, mut transform, mut visibility) in &mut q_label {
+        let translation = match *label {
+            CoordinateLabel::File { x, top: on_top } => Vec2 {
+                x: corner.x + field_middle(board.view_position(Position { x, y: 0 }).x),
+                y: if on_top { top } else { bottom },
+            },
+            CoordinateLabel::Rank { y, right: on_right } => Vec2 {
+                x: if on_right { right } else { left },
+                y: corner.y - field_middle(board.view_position(Position { x: 0, y }).y),
+            },
+        };
+
+        if transform.translation.xy() != translation {
+            transform.translation = translation.extend(transform.translation.z);
+        }
+
+        if *visibility != new_visibility {
+            *visibility = new_visibility;
+        }
+    }
+}
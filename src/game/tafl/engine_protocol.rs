@@ -0,0 +1,599 @@
+//! Text protocol for talking to tafl engines over stdin/stdout, loosely based on the OpenTafl
+//! engine protocol.
+//!
+//! Every message is a single line. Squares, moves and positions use the formats of the
+//! `notation` module.
+//!
+//! GUI to engine:
+//! - `hello` - sent once after the engine was started
+//! - `rules <cols> <rows> <throne> <corners>` - the board, e.g. `rules 11 11 f6 a1,k1,a11,k11`
+//! - `position <position>` - the figures on the board, e.g. `position /3ttttt3/5t5/.../`
+//! - `clock <attacker clock> <defender clock> <time control>` - the clocks of both sides and the
+//!   time control of the game in the formats of the `time_control` module, e.g.
+//!   `clock 300000:3 0:2 600000 byo-yomi 3 30000`
+//! - `clock none` - the game has no clock
+//! - `play attackers|defenders` - asks the engine for a move of the side
+//! - `goodbye` - the engine should exit
+//!
+//! Engine to GUI:
+//! - `hello <name>` - the answer to `hello`
+//! - `move <from>-<to>` - the answer to `play`, e.g. `move d1-d4`
+//! - `error <message>` - something went wrong on the engine's side
+//!
+//! Unknown messages should be ignored by both parties.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::str::SplitWhitespace;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use crate::game::tafl::notation::*;
+use crate::game::tafl::time_control::*;
+use crate::game::tafl::*;
+
+/// How an engine is started: the program and the arguments passed to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EngineCommand {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl EngineCommand {
+    /// Parses a command line, its words are separated by whitespace unless they are quoted with
+    /// `"` or `'`, e.g. `"C:\Program Files\engine.exe" --depth 4`.
+    pub fn parse(line: &str) -> Result<Self, &'static str> {
+        let mut words: Vec<String> = vec![];
+        // the word being read, None between words
+        let mut word: Option<String> = None;
+        let mut quote: Option<char> = None;
+
+        for c in line.chars() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => word.get_or_insert_with(String::new).push(c),
+                None if c == '"' || c == '\'' => {
+                    quote = Some(c);
+                    word.get_or_insert_with(String::new);
+                }
+                None if c.is_whitespace() => words.extend(word.take()),
+                None => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+
+        if quote.is_some() {
+            return Err("a quote of the command should be closed");
+        }
+
+        words.extend(word);
+
+        let mut words = words.into_iter();
+        let Some(program) = words.next() else {
+            return Err("the command should not be empty");
+        };
+
+        Ok(Self {
+            program,
+            args: words.collect(),
+        })
+    }
+
+    /// Starts the engine with its stdin and stdout piped.
+    pub fn spawn(&self) -> io::Result<Child> {
+        Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+    }
+}
+
+/// Writes the command line that `EngineCommand::parse` reads back.
+impl fmt::Display for EngineCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = std::iter::once(&self.program).chain(&self.args);
+
+        for (i, word) in words.enumerate() {
+            if 0 < i {
+                write!(f, " ")?;
+            }
+
+            let needs_quotes =
+                word.is_empty() || word.contains(|c: char| c.is_whitespace() || c == '\'');
+
+            if word.contains('"') {
+                write!(f, "'{}'", word)?;
+            } else if needs_quotes {
+                write!(f, "\"{}\"", word)?;
+            } else {
+                write!(f, "{}", word)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An engine running as a child process.
+pub struct EngineProcess {
+    process: Child,
+    stdin: ChildStdin,
+    // lines written by the engine to its stdout, read on a separate thread
+    lines: Mutex<Receiver<String>>,
+}
+
+impl EngineProcess {
+    /// Starts the engine with `command`.
+    pub fn start(command: &EngineCommand) -> io::Result<Self> {
+        let mut process = command.spawn()?;
+
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            process,
+            stdin,
+            lines: Mutex::new(receiver),
+        })
+    }
+
+    pub fn send(&mut self, message: &GuiMessage) -> io::Result<()> {
+        writeln!(self.stdin, "{}", message.to_line())?;
+        self.stdin.flush()
+    }
+
+    /// Gets the next line written by the engine without waiting for one.
+    pub fn try_receive(&self) -> Result<String, TryRecvError> {
+        self.lines.lock().unwrap().try_recv()
+    }
+
+    /// Waits at most `timeout` for the next line written by the engine.
+    pub fn receive_timeout(&self, timeout: Duration) -> Result<String, RecvTimeoutError> {
+        self.lines.lock().unwrap().recv_timeout(timeout)
+    }
+
+    /// Asks the engine to exit and kills it if it doesn't do so in time.
+    pub fn stop(mut self) {
+        _ = self.send(&GuiMessage::Goodbye);
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_secs(1));
+            _ = self.process.kill();
+            _ = self.process.wait();
+        });
+    }
+}
+
+/// The clocks of both sides as sent in `clock`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct EngineClock {
+    pub attacker: ClockState,
+    pub defender: ClockState,
+    /// Games without a clock can't be lost on time, the clocks of the sides are ignored then.
+    pub time_control: TimeControl,
+}
+
+impl EngineClock {
+    /// Gets the state of the clocks of both sides, which share the same time control.
+    pub fn new(attacker: &Clock, defender: &Clock) -> Self {
+        Self {
+            attacker: attacker.state(),
+            defender: defender.state(),
+            time_control: attacker.time_control.clone(),
+        }
+    }
+
+    pub fn side(&self, side: Side) -> ClockState {
+        match side {
+            Side::Attacker => self.attacker,
+            Side::Defender => self.defender,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let time_control = format_time_control(&self.time_control).join(" ");
+
+        if self.time_control.unlimited {
+            return format!("clock {}", time_control);
+        }
+
+        format!(
+            "clock {} {} {}",
+            format_clock_state(&self.attacker),
+            format_clock_state(&self.defender),
+            time_control
+        )
+    }
+
+    /// Parses the arguments of `clock`.
+    fn parse(tokens: SplitWhitespace) -> Result<Self, &'static str> {
+        let mut tokens = tokens.peekable();
+
+        if tokens.peek() == Some(&"none") {
+            return Ok(Self {
+                time_control: parse_time_control(tokens)?,
+                ..Self::default()
+            });
+        }
+
+        Ok(Self {
+            attacker: parse_clock_state(tokens.next())?,
+            defender: parse_clock_state(tokens.next())?,
+            time_control: parse_time_control(tokens)?,
+        })
+    }
+}
+
+/// A message sent by the GUI to an engine.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GuiMessage {
+    Hello,
+    Rules {
+        rows: usize,
+        cols: usize,
+        throne_position: Position,
+        end_positions: Vec<Position>,
+    },
+    Position {
+        position: String,
+    },
+    Clock {
+        clock: EngineClock,
+    },
+    Play {
+        side: Side,
+    },
+    Goodbye,
+}
+
+impl GuiMessage {
+    /// Converts the message to a line of the protocol (without the trailing newline).
+    pub fn to_line(&self) -> String {
+        match self {
+            GuiMessage::Hello => "hello".to_string(),
+            GuiMessage::Rules {
+                rows,
+                cols,
+                throne_position,
+                end_positions,
+            } => {
+                let end_positions: Vec<String> = end_positions
+                    .iter()
+                    .map(|position| format_square(*position, *rows))
+                    .collect();
+
+                format!(
+                    "rules {} {} {} {}",
+                    cols,
+                    rows,
+                    format_square(*throne_position, *rows),
+                    end_positions.join(",")
+                )
+            }
+            GuiMessage::Position { position } => format!("position {}", position),
+            GuiMessage::Clock { clock } => clock.to_line(),
+            GuiMessage::Play { side } => format!("play {}", format_side(*side)),
+            GuiMessage::Goodbye => "goodbye".to_string(),
+        }
+    }
+
+    /// Parses a line of the protocol sent by the GUI.
+    pub fn parse(line: &str) -> Result<Self, &'static str> {
+        let mut tokens = line.split_whitespace();
+
+        match tokens.next() {
+            Some("hello") => Ok(GuiMessage::Hello),
+            Some("rules") => {
+                let not_enough_tokens = "rules should consist of the columns, rows, throne and corners";
+
+                let mut size = || {
+                    tokens
+                        .next()
+                        .ok_or(not_enough_tokens)?
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|size| (1..=MAX_BOARD_SIZE).contains(size))
+                        .ok_or("the board should have between 1 and 26 columns and rows")
+                };
+
+                let cols = size()?;
+                let rows = size()?;
+
+                let throne_position =
+                    parse_square(tokens.next().ok_or(not_enough_tokens)?, rows, cols)?;
+
+                let mut end_positions = vec![];
+                for square in tokens.next().unwrap_or("").split(',') {
+                    if !square.is_empty() {
+                        end_positions.push(parse_square(square, rows, cols)?);
+                    }
+                }
+
+                Ok(GuiMessage::Rules {
+                    rows,
+                    cols,
+                    throne_position,
+                    end_positions,
+                })
+            }
+            Some("position") => Ok(GuiMessage::Position {
+                position: tokens.next().ok_or("position should be followed by a position")?.to_string(),
+            }),
+            Some("clock") => Ok(GuiMessage::Clock {
+                clock: EngineClock::parse(tokens)?,
+            }),
+            Some("play") => Ok(GuiMessage::Play {
+                side: parse_side(tokens.next().unwrap_or(""))?,
+            }),
+            Some("goodbye") => Ok(GuiMessage::Goodbye),
+            _ => Err("unknown message"),
+        }
+    }
+}
+
+/// A message sent by an engine to the GUI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EngineMessage {
+    Hello { name: String },
+    Move { from: Position, to: Position },
+    Error { message: String },
+}
+
+impl EngineMessage {
+    /// Converts the message to a line of the protocol for a board with `rows` rows.
+    pub fn to_line(&self, rows: usize) -> String {
+        match self {
+            EngineMessage::Hello { name } => format!("hello {}", name),
+            EngineMessage::Move { from, to } => format!("move {}", format_move(*from, *to, rows)),
+            EngineMessage::Error { message } => format!("error {}", message),
+        }
+    }
+
+    /// Parses a line of the protocol sent by an engine playing on a board with the provided size.
+    pub fn parse(line: &str, rows: usize, cols: usize) -> Result<Self, &'static str> {
+        let line = line.trim();
+        let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "hello" => Ok(EngineMessage::Hello {
+                name: arguments.to_string(),
+            }),
+            "move" => {
+                let (from, to) = parse_move(arguments, rows, cols)?;
+                Ok(EngineMessage::Move { from, to })
+            }
+            "error" => Ok(EngineMessage::Error {
+                message: arguments.to_string(),
+            }),
+            _ => Err("unknown message"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tafl::time_control::TimeControl;
+
+    fn side_clock(millis: u64, periods: u32) -> ClockState {
+        ClockState {
+            remaining: Duration::from_millis(millis),
+            periods,
+        }
+    }
+
+    #[test]
+    fn gui_messages_round_trip() {
+        let messages = [
+            GuiMessage::Hello,
+            GuiMessage::Rules {
+                rows: 11,
+                cols: 11,
+                throne_position: Position { x: 5, y: 5 },
+                end_positions: vec![Position { x: 0, y: 0 }, Position { x: 10, y: 10 }],
+            },
+            GuiMessage::Rules {
+                rows: 7,
+                cols: 9,
+                throne_position: Position { x: 4, y: 3 },
+                end_positions: vec![],
+            },
+            GuiMessage::Position {
+                position: "/3t3/7/7/3K3/7/7/3T3/".to_string(),
+            },
+            GuiMessage::Clock {
+                clock: EngineClock {
+                    attacker: side_clock(300_000, 0),
+                    defender: side_clock(1_234, 0),
+                    time_control: TimeControl {
+                        time: Duration::from_secs(300),
+                        ..TimeControl::default()
+                    },
+                },
+            },
+            GuiMessage::Clock {
+                clock: EngineClock {
+                    attacker: side_clock(60_000, 0),
+                    defender: side_clock(55_000, 0),
+                    time_control: TimeControl {
+                        time: Duration::from_secs(60),
+                        bonus: TimeBonus::Increment(Duration::from_secs(2)),
+                        move_limit: Some(Duration::from_secs(30)),
+                        unlimited: false,
+                    },
+                },
+            },
+            GuiMessage::Clock {
+                clock: EngineClock {
+                    attacker: side_clock(1_000, 0),
+                    defender: side_clock(2_000, 0),
+                    time_control: TimeControl {
+                        time: Duration::from_secs(5),
+                        bonus: TimeBonus::Delay(Duration::from_secs(3)),
+                        ..TimeControl::default()
+                    },
+                },
+            },
+            GuiMessage::Clock {
+                clock: EngineClock {
+                    attacker: side_clock(0, 2),
+                    defender: side_clock(120_000, 3),
+                    time_control: TimeControl {
+                        time: Duration::from_secs(600),
+                        bonus: TimeBonus::ByoYomi {
+                            periods: 3,
+                            period: Duration::from_secs(30),
+                        },
+                        ..TimeControl::default()
+                    },
+                },
+            },
+            GuiMessage::Clock {
+                clock: EngineClock {
+                    time_control: TimeControl {
+                        unlimited: true,
+                        ..TimeControl::default()
+                    },
+                    ..EngineClock::default()
+                },
+            },
+            GuiMessage::Play {
+                side: Side::Attacker,
+            },
+            GuiMessage::Play {
+                side: Side::Defender,
+            },
+            GuiMessage::Goodbye,
+        ];
+
+        for message in messages {
+            assert_eq!(GuiMessage::parse(&message.to_line()), Ok(message));
+        }
+    }
+
+    #[test]
+    fn clocks_are_read_from_the_clocks_of_the_sides() {
+        let time_control = TimeControl {
+            time: Duration::from_secs(10),
+            bonus: TimeBonus::ByoYomi {
+                periods: 2,
+                period: Duration::from_secs(5),
+            },
+            move_limit: None,
+            unlimited: false,
+        };
+
+        let attacker = Clock::new(time_control.clone());
+        let mut defender = Clock::new(time_control);
+        defender.tick(Duration::from_secs(16));
+
+        let clock = EngineClock::new(&attacker, &defender);
+
+        assert_eq!(clock.attacker, side_clock(10_000, 2));
+        assert_eq!(clock.defender, side_clock(0, 1));
+        assert_eq!(
+            GuiMessage::Clock { clock }.to_line(),
+            "clock 10000:2 0:1 10000 byo-yomi 2 5000"
+        );
+    }
+
+    #[test]
+    fn engine_messages_round_trip() {
+        let messages = [
+            EngineMessage::Hello {
+                name: "some engine 1.0".to_string(),
+            },
+            EngineMessage::Move {
+                from: Position { x: 3, y: 0 },
+                to: Position { x: 3, y: 4 },
+            },
+            EngineMessage::Error {
+                message: "no position".to_string(),
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(EngineMessage::parse(&message.to_line(11), 11, 11), Ok(message));
+        }
+    }
+
+    #[test]
+    fn engine_move_uses_notation() {
+        let message = EngineMessage::Move {
+            from: Position { x: 0, y: 10 },
+            to: Position { x: 0, y: 7 },
+        };
+
+        assert_eq!(message.to_line(11), "move a1-a4");
+    }
+
+    #[test]
+    fn commands_are_split_into_words() {
+        assert_eq!(
+            EngineCommand::parse("  engine --depth 4 "),
+            Ok(EngineCommand {
+                program: "engine".to_string(),
+                args: vec!["--depth".to_string(), "4".to_string()],
+            })
+        );
+        assert_eq!(
+            EngineCommand::parse(r#""C:\Program Files\engine.exe" --name 'my engine' """#),
+            Ok(EngineCommand {
+                program: r"C:\Program Files\engine.exe".to_string(),
+                args: vec!["--name".to_string(), "my engine".to_string(), String::new()],
+            })
+        );
+        assert!(EngineCommand::parse(" ").is_err());
+        assert!(EngineCommand::parse("\"engine").is_err());
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let commands = [
+            EngineCommand {
+                program: "/opt/my engines/engine".to_string(),
+                args: vec!["--engine".to_string()],
+            },
+            EngineCommand {
+                program: "engine".to_string(),
+                args: vec![r#"say "hi""#.to_string(), "it's".to_string(), String::new()],
+            },
+        ];
+
+        for command in commands {
+            assert_eq!(EngineCommand::parse(&command.to_string()), Ok(command));
+        }
+    }
+
+    #[test]
+    fn invalid_lines_are_rejected() {
+        assert!(GuiMessage::parse("").is_err());
+        assert!(GuiMessage::parse("info depth 3").is_err());
+        assert!(GuiMessage::parse("clock 100").is_err());
+        assert!(GuiMessage::parse("clock 100:0 100:0").is_err());
+        assert!(GuiMessage::parse("clock 100 100 1000").is_err());
+        assert!(GuiMessage::parse("clock 100:0 100:0 1000 overtime 1000").is_err());
+        assert!(GuiMessage::parse("play nobody").is_err());
+        assert!(GuiMessage::parse("rules 11 11").is_err());
+        assert!(GuiMessage::parse("rules 27 11 f6 a1").is_err());
+        assert!(GuiMessage::parse("rules 11 100000000000 f6 a1").is_err());
+        assert!(EngineMessage::parse("info depth 3", 11, 11).is_err());
+        assert!(EngineMessage::parse("move a1-z99", 11, 11).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::io;
+use std::sync::mpsc::TryRecvError;
+
+use bevy::utils::HashMap;
+
+use crate::game::tafl::engine_protocol::*;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::*;
+
+// how often an engine may answer `play` with an illegal move or an error before it forfeits
+const MAX_ENGINE_FAILURES: u32 = 3;
+
+pub struct ExternalEnginePlugin;
+
+impl Plugin for ExternalEnginePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), start_external_engines)
+            .add_systems(OnExit(GameState::InGame), stop_external_engines)
+            .add_systems(
+                Update,
+                (request_engine_moves, receive_engine_moves)
+                    .chain()
+                    .before(try_move_figure)
+                    .run_if(in_state(GameState::InGame).and_then(in_state(TaflState::Playing))),
+            )
+            .insert_resource(ExternalEngines::default());
+    }
+}
+
+/// An engine running as a child process and what it was asked already.
+struct ExternalEngine {
+    process: EngineProcess,
+    // whether `hello` and `rules` were sent already
+    initialized: bool,
+    waiting_for_move: bool,
+    // the illegal moves and errors sent in answer to `play`
+    failures: u32,
+}
+
+impl ExternalEngine {
+    fn start(command: &EngineCommand) -> io::Result<Self> {
+        Ok(Self {
+            process: EngineProcess::start(command)?,
+            initialized: false,
+            waiting_for_move: false,
+            failures: 0,
+        })
+    }
+
+    fn send(&mut self, message: GuiMessage) {
+        if let Err(err) = self.process.send(&message) {
+            warn!("failed to write to engine: {}", err);
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ExternalEngines {
+    engines: HashMap<Side, ExternalEngine>,
+}
+
+fn start_external_engines(players: Res<Players>, mut external_engines: ResMut<ExternalEngines>) {
+    for side in [Side::Attacker, Side::Defender] {
+        let PlayerKind::ExternalEngine { command } = players.get(side) else {
+            continue;
+        };
+
+        match ExternalEngine::start(command) {
+            Ok(engine) => {
+                external_engines.engines.insert(side, engine);
+            }
+            Err(err) => error!("failed to start engine `{}`: {}", command, err),
+        }
+    }
+}
+
+fn stop_external_engines(mut external_engines: ResMut<ExternalEngines>) {
+    for (_, engine) in external_engines.engines.drain() {
+        engine.process.stop();
+    }
+}
+
+/// Sends the current position to the engine of the side to move and asks it for a move.
+fn request_engine_moves(
+    mut external_engines: ResMut<ExternalEngines>,
+    q_board: Query<(&Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    q_game_timer: Query<&GameTimer>,
+    q_sliding_figure: Query<(), With<FigureToSlideAndMove>>,
+) {
+    // the previous move is still in progress
+    if !q_sliding_figure.is_empty() {
+        return;
+    }
+
+    let Ok((board, turn_tracker)) = q_board.get_single() else {
+        return;
+    };
+
+    let Some(engine) = external_engines.engines.get_mut(&turn_tracker.side) else {
+        return;
+    };
+
+    if engine.waiting_for_move {
+        return;
+    }
+
+    if !engine.initialized {
+        engine.send(GuiMessage::Hello);
+        engine.send(GuiMessage::Rules {
+            rows: board.rows,
+            cols: board.cols,
+            throne_position: board.throne_position,
+            end_positions: board.end_positions.clone(),
+        });
+        engine.initialized = true;
+    }
+
+    let figures: HashMap<Position, FigureType> = board
+        .figures
+        .iter()
+        .map(|(position, figure_entity)| {
+            let figure = q_figure.get(*figure_entity).unwrap();
+            let figure_type = FigureType {
+                side: figure.side,
+                kind: figure.kind,
+            };
+
+            (*position, figure_type)
+        })
+        .collect();
+
+    let clock = |side: Side| {
+        q_game_timer
+            .iter()
+            .find(|game_timer| game_timer.side == side)
+            .map(|game_timer| game_timer.clock.clone())
+            .unwrap_or_default()
+    };
+
+    engine.send(GuiMessage::Position {
+        position: format_position(&figures, board.rows, board.cols),
+    });
+    engine.send(GuiMessage::Clock {
+        clock: EngineClock::new(&clock(Side::Attacker), &clock(Side::Defender)),
+    });
+    engine.send(GuiMessage::Play {
+        side: turn_tracker.side,
+    });
+    engine.waiting_for_move = true;
+}
+
+/// Handles the messages of the engines, submitting the moves they make.
+///
+/// An engine that exits or keeps failing to make a legal move forfeits the game, otherwise the
+/// game would wait for it forever.
+fn receive_engine_moves(
+    mut external_engines: ResMut<ExternalEngines>,
+    q_board: Query<(Entity, &Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    mut try_move_figure_event: EventWriter<TryMoveFigureEvent>,
+    mut end_game_event: EventWriter<EndGameEvent>,
+) {
+    let Ok((board_entity, board, turn_tracker)) = q_board.get_single() else {
+        return;
+    };
+
+    for (side, engine) in external_engines.engines.iter_mut() {
+        let mut lines = vec![];
+        let mut exited = false;
+
+        loop {
+            match engine.process.try_receive() {
+                Ok(line) => lines.push(line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    exited = true;
+                    break;
+                }
+            }
+        }
+
+        for line in lines {
+            let message = match EngineMessage::parse(&line, board.rows, board.cols) {
+                Ok(message) => message,
+                // unknown messages are ignored
+                Err(err) => {
+                    warn!("{} engine sent `{}`: {}", side, line, err);
+                    continue;
+                }
+            };
+
+            match message {
+                EngineMessage::Hello { name } => info!("{} engine: {}", side, name),
+                EngineMessage::Error { message } => {
+                    warn!("{} engine error: {}", side, message);
+
+                    // the move is requested again
+                    if engine.waiting_for_move && *side == turn_tracker.side {
+                        engine.waiting_for_move = false;
+                        engine.failures += 1;
+                    }
+                }
+                EngineMessage::Move { from, to } => {
+                    if !engine.waiting_for_move || *side != turn_tracker.side {
+                        warn!("{} engine moved when it wasn't asked to", side);
+                        continue;
+                    }
+
+                    // the move is requested again if it is illegal
+                    engine.waiting_for_move = false;
+
+                    let is_legal = board.figures.get(&from).is_some_and(|figure_entity| {
+                        let figure = q_figure.get(*figure_entity).unwrap();
+                        figure.side == *side && possible_moves(board, *figure).contains(&to)
+                    });
+
+                    if !is_legal {
+                        warn!(
+                            "{} engine made an illegal move {}",
+                            side,
+                            format_move(from, to, board.rows)
+                        );
+                        engine.failures += 1;
+                        continue;
+                    }
+
+                    engine.failures = 0;
+
+                    try_move_figure_event.send(TryMoveFigureEvent {
+                        board_entity,
+                        from,
+                        to,
+                        slide: true,
+                    });
+                }
+            }
+        }
+
+        if exited || MAX_ENGINE_FAILURES <= engine.failures {
+            error!("{} engine forfeits, it exited or failed to move", side);
+
+            // it isn't asked for moves anymore
+            engine.waiting_for_move = true;
+
+            end_game_event.send(EndGameEvent {
+                outcome: GameOutcome::Win(side.opponent()),
+            });
+        }
+    }
+}
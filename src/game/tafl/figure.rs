@@ -3,12 +3,24 @@ use core::fmt;
 use crate::game::tafl::board::Position;
 use bevy::prelude::*;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Side {
+    /// Usually makes the first move.
+    #[default]
     Attacker,
     Defender,
 }
 
+impl Side {
+    /// Returns the side playing against this one.
+    pub fn opponent(&self) -> Side {
+        match self {
+            Side::Attacker => Side::Defender,
+            Side::Defender => Side::Attacker,
+        }
+    }
+}
+
 impl fmt::Display for Side {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -0,0 +1,221 @@
+use bevy::ecs::system::SystemParam;
+use bevy::utils::{Duration, HashMap};
+
+use crate::game::tafl::notation::parse_position;
+use crate::game::tafl::record::*;
+use crate::game::tafl::rules::{variant, TaflGame};
+use crate::game::tafl::save::SavedClock;
+use crate::game::tafl::*;
+
+pub struct GameSetupPlugin;
+
+impl Plugin for GameSetupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(GameState::InGame),
+            pause_until_set_up.run_if(resource_exists::<GameSetup>),
+        )
+        .add_systems(
+            Update,
+            apply_game_setup
+                .after(setup_game_ui)
+                .run_if(in_state(GameState::InGame).and_then(resource_exists::<GameSetup>)),
+        );
+    }
+}
+
+/// How the next game is set up, e.g. from the command line or the new game screen.
+///
+/// A game set up from the command line starts right away instead of the main menu. The resource
+/// is removed once the board is set up.
+#[derive(Resource, Debug, Clone)]
+pub struct GameSetup {
+    pub variant: String,
+    pub initial_position: String,
+    pub first_side: Side,
+    /// The game with the moves of `entries` made.
+    pub game: TaflGame,
+    /// What happened in the game before, e.g. when it was loaded from a record file.
+    pub entries: Vec<RecordEntry>,
+    pub outcome: Option<GameOutcome>,
+    /// The time left on the clocks, otherwise it is replayed from the times of the moves.
+    pub clocks: Vec<SavedClock>,
+    /// The save slot the game was loaded from.
+    pub slot: Option<usize>,
+}
+
+impl GameSetup {
+    /// Sets up a game of `variant_name` starting from `position` or the initial position of the
+    /// variant with `first_side` to move, and replays the moves of `entries`.
+    pub fn new(
+        variant_name: &str,
+        position: Option<&str>,
+        first_side: Side,
+        entries: Vec<RecordEntry>,
+        outcome: Option<GameOutcome>,
+    ) -> Result<Self, &'static str> {
+        let (rules, initial_position) = variant(variant_name)?;
+        let initial_position = position.unwrap_or(initial_position);
+        let parsed = parse_position(initial_position)?;
+
+        if parsed.rows != rules.rows || parsed.cols != rules.cols {
+            return Err("the position should have the size of the board of the variant");
+        }
+
+        let mut game = TaflGame::new(rules, &parsed.figures, first_side)?;
+        for entry in &entries {
+            if let RecordEntry::Move { side, mv, .. } = entry {
+                if *side != game.side_to_move() {
+                    return Err("the moves should be made by the side to move");
+                }
+
+                game.make_move(*mv)?;
+            }
+        }
+
+        Ok(GameSetup {
+            variant: variant_name.to_string(),
+            initial_position: initial_position.to_string(),
+            first_side,
+            outcome: game.outcome().or(outcome),
+            game,
+            entries,
+            clocks: vec![],
+            slot: None,
+        })
+    }
+}
+
+// keeps the engines from moving on the initial position
+fn pause_until_set_up(mut next_tafl_state: ResMut<NextState<TaflState>>) {
+    next_tafl_state.set(TaflState::Paused);
+}
+
+/// The record and the events the set up game is passed on to.
+#[derive(SystemParam)]
+struct SetupTargets<'w> {
+    record: ResMut<'w, GameRecord>,
+    time: Res<'w, Time>,
+    next_tafl_state: ResMut<'w, NextState<TaflState>>,
+    reset_figures_event: EventWriter<'w, ResetFiguresEvent>,
+    end_game_event: EventWriter<'w, EndGameEvent>,
+}
+
+/// Replaces the figures of the spawned board and sets the clocks and the record to the ones of
+/// the set up game.
+fn apply_game_setup(
+    game_setup: Res<GameSetup>,
+    q_board: Query<(Entity, &Board)>,
+    mut q_game_timer: Query<(&mut GameTimer, &mut Text)>,
+    time_control: Res<TimeControl>,
+    mut targets: SetupTargets,
+    mut commands: Commands,
+) {
+    let Ok((board_entity, board)) = q_board.get_single() else {
+        return;
+    };
+
+    // the figures of `spawn_hnefatafl` would be spawned on top of the replaced ones
+    if board.figures.is_empty() {
+        return;
+    }
+
+    targets.reset_figures_event.send(ResetFiguresEvent {
+        board_entity,
+        figures: game_setup
+            .game
+            .iter_figures()
+            .map(|(position, figure_type)| Figure {
+                side: figure_type.side,
+                kind: figure_type.kind,
+                position,
+            })
+            .collect(),
+        side_to_move: game_setup.game.side_to_move(),
+    });
+
+    // replay the clocks, each side used the time between the previous move and its own
+    let mut clocks = HashMap::<Side, Clock>::new();
+    let mut previous = Duration::ZERO;
+
+    for entry in &game_setup.entries {
+        if let RecordEntry::Move { side, time, .. } = entry {
+            let clock = clocks
+                .entry(*side)
+                .or_insert_with(|| Clock::new(time_control.clone()));
+
+            clock.tick(time.saturating_sub(previous));
+            clock.end_move();
+            previous = *time;
+        }
+    }
+
+    for (mut game_timer, mut text) in &mut q_game_timer {
+        if let Some(clock) = clocks.remove(&game_timer.side) {
+            game_timer.clock = clock;
+        }
+
+        let saved_clock = game_setup
+            .clocks
+            .iter()
+            .find(|saved_clock| saved_clock.side == game_timer.side);
+
+        if let Some(saved_clock) = saved_clock {
+            game_timer.clock.set_remaining(saved_clock.remaining);
+            game_timer.clock.set_periods(saved_clock.periods);
+        }
+
+        // otherwise the side that made the last move would get its bonus a second time once the
+        // turn is indicated
+        game_timer.active = game_timer.side == game_setup.game.side_to_move();
+        set_timer_text(&game_timer, &mut text);
+    }
+
+    let now = targets.time.elapsed();
+    targets.record.resume(game_setup.entries.clone(), now);
+
+    if let Some(outcome) = game_setup.outcome {
+        targets.end_game_event.send(EndGameEvent { outcome });
+    }
+
+    targets.next_tafl_state.set(TaflState::Playing);
+    commands.remove_resource::<GameSetup>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tafl::notation::parse_move;
+    use crate::game::tafl::rules::Move;
+
+    fn entry(side: Side, mv: &str) -> RecordEntry {
+        let (from, to) = parse_move(mv, 11, 11).unwrap();
+
+        RecordEntry::Move {
+            side,
+            mv: Move { from, to },
+            time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn moves_are_replayed() {
+        let entries = vec![
+            entry(Side::Attacker, "a4-c4"),
+            entry(Side::Defender, "e5-e2"),
+        ];
+        let setup = GameSetup::new("hnefatafl", None, Side::Attacker, entries, None).unwrap();
+
+        assert_eq!(setup.game.side_to_move(), Side::Attacker);
+    }
+
+    #[test]
+    fn moves_of_the_wrong_side_are_rejected() {
+        let entries = vec![
+            entry(Side::Attacker, "a4-c4"),
+            entry(Side::Attacker, "a5-c5"),
+        ];
+
+        assert!(GameSetup::new("hnefatafl", None, Side::Attacker, entries, None).is_err());
+    }
+}
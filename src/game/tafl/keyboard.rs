@@ -0,0 +1,290 @@
+use bevy::ecs::system::SystemParam;
+use bevy::sprite::MaterialMesh2dBundle;
+
+use crate::game::tafl::chat::{chat_focused, has_chat};
+use crate::game::tafl::*;
+
+/// Lets the players move with the keyboard: the arrow keys move a cursor over the board, Space (or
+/// Enter in games without a chat) selects the figure under it or moves the selected figure to it,
+/// and Tab jumps between the figures that can move.
+pub struct KeyboardPlugin;
+
+impl Plugin for KeyboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<PressCursorEvent>()
+            .add_systems(
+                Update,
+                (
+                    (move_keyboard_cursor, press_cursor)
+                        .chain()
+                        .after(on_mouse_released)
+                        .before(try_move_figure)
+                        .run_if(
+                            in_state(TaflState::Playing)
+                                .and_then(selection_unlocked)
+                                .and_then(not(chat_focused)),
+                        ),
+                    update_cursor_indicator,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(OnExit(GameState::InGame), despawn_cursor_indicator)
+            .init_resource::<KeyboardCursor>();
+    }
+}
+
+/// The field the keyboard cursor is on, None until the keyboard is used.
+#[derive(Resource, Default)]
+pub struct KeyboardCursor {
+    pub position: Option<Position>,
+}
+
+/// Selects the figure under the cursor or moves the selected figure to the cursor.
+#[derive(Event)]
+struct PressCursorEvent;
+
+#[derive(Component)]
+struct CursorIndicator;
+
+fn selection_unlocked(selection_options: Res<SelectionOptions>) -> bool {
+    !selection_options.selection_locked
+}
+
+/// Gets the key of a position to sort the fields in the order they are read on the screen.
+fn reading_order(board: &Board, position: Position) -> (usize, usize) {
+    match board.rotated {
+        true => (board.rows - 1 - position.y, board.cols - 1 - position.x),
+        false => (position.y, position.x),
+    }
+}
+
+/// Gets the figures of the side to move that have a legal move, in reading order.
+fn movable_figures(board: &Board, side: Side, q_figure: &Query<&Figure>) -> Vec<Position> {
+    let mut positions: Vec<Position> = board
+        .figures
+        .values()
+        .filter_map(|figure_entity| q_figure.get(*figure_entity).ok())
+        .filter(|figure| figure.side == side && !possible_moves(board, **figure).is_empty())
+        .map(|figure| figure.position)
+        .collect();
+
+    positions.sort_by_key(|position| reading_order(board, *position));
+    positions
+}
+
+fn move_keyboard_cursor(
+    input: Res<ButtonInput<KeyCode>>,
+    mut cursor: ResMut<KeyboardCursor>,
+    q_board: Query<(&Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    players: Res<Players>,
+    mut press_cursor_event: EventWriter<PressCursorEvent>,
+) {
+    let Ok((board, turn_tracker)) = q_board.get_single() else {
+        return;
+    };
+
+    // starts on the throne
+    let position = cursor.position.unwrap_or(board.throne_position);
+
+    let (dx, dy) = if input.just_pressed(KeyCode::ArrowLeft) {
+        (-1, 0)
+    } else if input.just_pressed(KeyCode::ArrowRight) {
+        (1, 0)
+    } else if input.just_pressed(KeyCode::ArrowUp) {
+        (0, -1)
+    } else if input.just_pressed(KeyCode::ArrowDown) {
+        (0, 1)
+    } else {
+        (0, 0)
+    };
+
+    // the arrows move the cursor on the screen, not on the board
+    let (dx, dy) = match board.rotated {
+        true => (-dx, -dy),
+        false => (dx, dy),
+    };
+
+    if (dx, dy) != (0, 0) {
+        let x = (position.x as isize + dx).clamp(0, board.cols as isize - 1);
+        let y = (position.y as isize + dy).clamp(0, board.rows as isize - 1);
+
+        cursor.position = Some(Position {
+            x: x as usize,
+            y: y as usize,
+        });
+    }
+
+    if input.just_pressed(KeyCode::Tab) {
+        let figures = movable_figures(board, turn_tracker.side, &q_figure);
+        let backwards = input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+        let current = figures.iter().position(|figure| *figure == position);
+        let next = match (current, backwards) {
+            (Some(current), false) => Some((current + 1) % figures.len()),
+            (Some(current), true) => Some((current + figures.len() - 1) % figures.len()),
+            (None, false) => figures
+                .iter()
+                .position(|figure| reading_order(board, *figure) > reading_order(board, position))
+                .or((!figures.is_empty()).then_some(0)),
+            (None, true) => figures
+                .iter()
+                .rposition(|figure| reading_order(board, *figure) < reading_order(board, position))
+                .or(figures.len().checked_sub(1)),
+        };
+
+        if let Some(next) = next {
+            cursor.position = Some(figures[next]);
+            press_cursor_event.send(PressCursorEvent);
+        }
+    }
+
+    // Enter starts a chat message in games with a chat
+    let enter =
+        !has_chat(&players) && input.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]);
+
+    if enter || input.just_pressed(KeyCode::Space) {
+        cursor.position = Some(position);
+        press_cursor_event.send(PressCursorEvent);
+    }
+}
+
+/// What pressing the cursor does to the figures.
+#[derive(SystemParam)]
+struct FigureEvents<'w> {
+    select_figure: EventWriter<'w, SelectFigureEvent>,
+    try_move_figure: EventWriter<'w, TryMoveFigureEvent>,
+    release_selected_figure: EventWriter<'w, ReleaseSelectedFigureEvent>,
+}
+
+fn press_cursor(
+    mut event: EventReader<PressCursorEvent>,
+    cursor: Res<KeyboardCursor>,
+    players: Res<Players>,
+    selected_figure: Res<SelectedFigure>,
+    q_board: Query<(Entity, &Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    mut figure_events: FigureEvents,
+) {
+    if event.read().count() == 0 {
+        return;
+    }
+
+    let (Some(position), Ok((board_entity, board, turn_tracker))) =
+        (cursor.position, q_board.get_single())
+    else {
+        return;
+    };
+
+    if !players.is_human(turn_tracker.side) {
+        return;
+    }
+
+    // a figure of the side to move under the cursor
+    let own_figure_entity = board
+        .figures
+        .get(&position)
+        .copied()
+        .filter(|figure_entity| {
+            q_figure
+                .get(*figure_entity)
+                .is_ok_and(|figure| figure.side == turn_tracker.side)
+        });
+
+    let selected = match *selected_figure {
+        SelectedFigure::Some { figure_entity, .. } => q_figure.get(figure_entity).ok(),
+        SelectedFigure::None => None,
+    };
+
+    match (selected, own_figure_entity) {
+        // pressing the selected figure again lets go of it
+        (Some(selected), _) if selected.position == position => {
+            figure_events
+                .release_selected_figure
+                .send(ReleaseSelectedFigureEvent { board_entity });
+        }
+        (_, Some(figure_entity)) => {
+            figure_events.select_figure.send(SelectFigureEvent {
+                board_entity,
+                figure_entity,
+            });
+        }
+        (Some(selected), None) => {
+            if possible_moves(board, *selected).contains(&position) {
+                figure_events.try_move_figure.send(TryMoveFigureEvent {
+                    board_entity,
+                    from: selected.position,
+                    to: position,
+                    slide: true,
+                });
+            }
+        }
+        (None, None) => (),
+    }
+}
+
+/// Shows the cursor once the keyboard is used, it is spawned on the first use.
+fn update_cursor_indicator(
+    cursor: Res<KeyboardCursor>,
+    q_board: Query<&Board>,
+    mut q_cursor_indicator: Query<&mut Transform, With<CursorIndicator>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+) {
+    let (Some(position), Ok(board)) = (cursor.position, q_board.get_single()) else {
+        return;
+    };
+
+    // above the highlights and the selection indicator, below the figures
+    let translation = board.board_to_world(position).extend(2.5);
+
+    if let Ok(mut transform) = q_cursor_indicator.get_single_mut() {
+        transform.translation = translation;
+        return;
+    }
+
+    // a frame around the field
+    let size = board.field_size;
+    let thickness = 0.08 * size;
+    let material = materials.add(Color::rgb(1., 1., 1.));
+    let horizontal = meshes.add(Rectangle::new(size, thickness));
+    let vertical = meshes.add(Rectangle::new(thickness, size));
+
+    commands
+        .spawn((
+            CursorIndicator,
+            Name::new("Cursor"),
+            SpatialBundle::from_transform(Transform::from_translation(translation)),
+        ))
+        .with_children(|parent| {
+            let offset = (size - thickness) / 2.;
+
+            for (mesh, x, y) in [
+                (&horizontal, 0., offset),
+                (&horizontal, 0., -offset),
+                (&vertical, -offset, 0.),
+                (&vertical, offset, 0.),
+            ] {
+                parent.spawn(MaterialMesh2dBundle {
+                    mesh: mesh.clone().into(),
+                    material: material.clone(),
+                    transform: Transform::from_xyz(x, y, 0.),
+                    ..default()
+                });
+            }
+        });
+}
+
+fn despawn_cursor_indicator(
+    q_cursor_indicator: Query<Entity, With<CursorIndicator>>,
+    mut cursor: ResMut<KeyboardCursor>,
+    mut commands: Commands,
+) {
+    for cursor_indicator_entity in &q_cursor_indicator {
+        commands.entity(cursor_indicator_entity).despawn_recursive();
+    }
+
+    *cursor = KeyboardCursor::default();
+}
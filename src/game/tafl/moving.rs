@@ -21,10 +21,7 @@ pub struct TurnTracker {
 
 impl TurnTracker {
     pub fn next_turn(&mut self) {
-        match self.side {
-            Side::Attacker => self.side = Side::Defender,
-            Side::Defender => self.side = Side::Attacker,
-        }
+        self.side = self.side.opponent();
     }
 }
 
@@ -35,6 +32,15 @@ pub struct MoveFigureEvent {
     to: Position,
 }
 
+/// Sent whenever a figure was moved on a board.
+#[derive(Event)]
+pub struct FigureMovedEvent {
+    pub board_entity: Entity,
+    pub side: Side,
+    pub from: Position,
+    pub to: Position,
+}
+
 // Pre: The move is valid.
 pub fn move_figure(
     mut event: EventReader<MoveFigureEvent>,
@@ -42,6 +48,7 @@ pub fn move_figure(
     mut q_figure: Query<(&mut Figure, &mut Transform)>,
     mut king_on_corner_check_event: EventWriter<KingOnCornerCheckEvent>,
     mut capture_checks_event: EventWriter<CaptureCheckEvent>,
+    mut figure_moved_event: EventWriter<FigureMovedEvent>,
 ) {
     for ev in event.read() {
         let mut board = q_board.get_mut(ev.board_entity).unwrap();
@@ -59,6 +66,13 @@ pub fn move_figure(
 
         figure_transform.translation = board.board_to_world(figure.position).extend(board.figure_z);
 
+        figure_moved_event.send(FigureMovedEvent {
+            board_entity: ev.board_entity,
+            side: figure.side,
+            from: ev.from,
+            to: ev.to,
+        });
+
         if figure.side == Side::Defender && figure.kind == FigureKind::King {
             king_on_corner_check_event.send(KingOnCornerCheckEvent {
                 board_entity: ev.board_entity,
@@ -82,6 +96,7 @@ pub fn end_move(
     mut event: EventReader<EndMoveEvent>,
     mut q_board: Query<&mut TurnTracker, With<Board>>,
     mut indicate_turn_event: EventWriter<IndicateTurnEvent>,
+    mut no_legal_moves_check_event: EventWriter<NoLegalMovesCheckEvent>,
 ) {
     for ev in event.read() {
         let mut turn_tracker = q_board.get_mut(ev.board_entity).unwrap();
@@ -90,6 +105,10 @@ pub fn end_move(
         indicate_turn_event.send(IndicateTurnEvent {
             side: Some(turn_tracker.side),
         });
+
+        no_legal_moves_check_event.send(NoLegalMovesCheckEvent {
+            board_entity: ev.board_entity,
+        });
     }
 }
 
@@ -207,79 +226,7 @@ pub fn try_move_figure(
 /// Pre:
 /// - figure is on the board
 pub fn possible_moves(board: &Board, figure: Figure) -> Vec<Position> {
-    let position = figure.position;
-    let mut result: Vec<Position> = vec![];
-
-    result.extend(possible_moves_in_range(
-        board,
-        figure,
-        (0..position.x).rev(),
-        Axis2::X,
-    ));
-    result.extend(possible_moves_in_range(
-        board,
-        figure,
-        (position.x + 1)..board.cols,
-        Axis2::X,
-    ));
-
-    result.extend(possible_moves_in_range(
-        board,
-        figure,
-        (0..position.y).rev(),
-        Axis2::Y,
-    ));
-    result.extend(possible_moves_in_range(
-        board,
-        figure,
-        (position.y + 1)..board.rows,
-        Axis2::Y,
-    ));
-
-    result
-}
-
-/// Helper function for possible_moves.
-/// It checks on an axis sequentially whether a figure can be placed on the positions in the range,
-/// when the figure can't be placed on a positon the checking stops.
-fn possible_moves_in_range<T>(board: &Board, figure: Figure, range: T, axis: Axis2) -> Vec<Position>
-where
-    T: IntoIterator<Item = usize>,
-{
-    let position = figure.position;
-    let mut result: Vec<Position> = vec![];
-
-    for i in range {
-        let targeted_position = match axis {
-            Axis2::X => Position {
-                x: i,
-                y: position.y,
-            },
-            Axis2::Y => Position {
-                x: position.x,
-                y: i,
-            },
-        };
-
-        if can_be_placed_on(board, figure, targeted_position) {
-            result.push(targeted_position);
-        } else {
-            break;
-        }
-    }
-
-    return result;
-}
-
-/// Validates whether a figure may be placed on a certain field or not.
-/// Pre:
-/// - to_position is on the board
-fn can_be_placed_on(board: &Board, figure: Figure, to_position: Position) -> bool {
-    let is_king = figure.kind == FigureKind::King;
-
-    let target_contains_figure = board.figures.get(&to_position) != None;
-    let target_is_end_pos = board.end_positions.contains(&to_position);
-    let target_is_throne = board.throne_position == to_position;
-
-    !target_contains_figure && ((!target_is_end_pos && !target_is_throne) || is_king)
+    Rules::from_board(board).possible_moves(figure.kind, figure.position, |position| {
+        board.figures.contains_key(&position)
+    })
 }
@@ -0,0 +1,1369 @@
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bevy::utils::HashMap;
+
+use crate::game::settings::Settings;
+use crate::game::tafl::chat::*;
+use crate::game::tafl::network_protocol::*;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::*;
+use crate::game::tafl::*;
+
+// how many times a player tries to reconnect to an interrupted game, once per second
+const RECONNECT_ATTEMPTS: u32 = 60;
+
+pub struct NetworkPlugin;
+
+impl Plugin for NetworkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, start_network.run_if(resource_exists::<NetworkMode>))
+            .add_systems(
+                Update,
+                (
+                    accept_connection,
+                    receive_network_messages,
+                    handle_network_messages,
+                    (resume_game, resync_game).run_if(
+                        in_state(GameState::InGame).and_then(in_state(TaflState::Paused)),
+                    ),
+                    apply_remote_moves
+                        .run_if(in_state(GameState::InGame).and_then(in_state(TaflState::Playing))),
+                )
+                    .chain()
+                    .before(try_move_figure)
+                    .run_if(resource_exists::<NetworkSession>),
+            )
+            .add_systems(
+                Update,
+                (
+                    record_initial_position,
+                    send_moves.after(move_figure),
+                    send_chat_messages,
+                    update_reconnect_ui.run_if(in_state(TaflState::Paused)),
+                )
+                    .run_if(
+                        in_state(GameState::InGame).and_then(resource_exists::<NetworkSession>),
+                    ),
+            )
+            .add_systems(
+                OnEnter(GameState::InGame),
+                start_spectators.run_if(resource_exists::<NetworkSession>),
+            )
+            .add_systems(
+                OnExit(GameState::InGame),
+                disconnect.run_if(resource_exists::<NetworkSession>),
+            )
+            .add_systems(
+                OnEnter(TaflState::Paused),
+                spawn_reconnect_ui.run_if(resource_exists::<NetworkSession>),
+            )
+            .add_systems(OnExit(TaflState::Paused), despawn_reconnect_ui);
+    }
+}
+
+/// How the game is played over the network.
+#[derive(Resource, Debug, Clone)]
+pub enum NetworkMode {
+    /// Waits for another instance to join on `port`, the local player plays `side`.
+    Host { port: u16, side: Side },
+    /// Joins the instance hosting on `address`.
+    Join { address: String },
+    /// Watches the games of the instance hosting on `address` without playing.
+    Spectate { address: String },
+}
+
+impl NetworkMode {
+    fn is_host(&self) -> bool {
+        matches!(self, NetworkMode::Host { .. })
+    }
+
+    fn is_spectator(&self) -> bool {
+        matches!(self, NetworkMode::Spectate { .. })
+    }
+
+    /// Describes the state of the connection while no other instance is connected.
+    fn idle_status(&self) -> String {
+        match self {
+            NetworkMode::Host { port, .. } => format!("Waiting for an opponent on port {}...", port),
+            NetworkMode::Join { address } | NetworkMode::Spectate { address } => {
+                format!("Disconnected from {}", address)
+            }
+        }
+    }
+}
+
+fn version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+/// Creates an id for a game session that is unlikely to be guessed.
+fn new_session_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+
+    format!("{:016x}", hasher.finish())
+}
+
+/// Connects to `address` on a separate thread, trying at most `attempts` times.
+fn connect(address: String, attempts: u32) -> Receiver<io::Result<TcpStream>> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for attempt in 1..=attempts {
+            match TcpStream::connect(&address) {
+                Ok(stream) => {
+                    _ = sender.send(Ok(stream));
+                    return;
+                }
+                Err(err) if attempt == attempts => {
+                    _ = sender.send(Err(err));
+                    return;
+                }
+                Err(_) => thread::sleep(Duration::from_secs(1)),
+            }
+        }
+    });
+
+    receiver
+}
+
+enum PeerEvent {
+    Line(String),
+    Disconnected,
+}
+
+/// What another instance connected to this one does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PeerRole {
+    /// Connected to the host, but didn't introduce itself yet.
+    Connecting,
+    /// The instance hosting the game this one joined.
+    Host,
+    /// Joined the game hosted by this instance as the other player.
+    Opponent,
+    /// Joined the game hosted by this instance to watch it.
+    Spectator,
+}
+
+impl PeerRole {
+    /// Determines whether the moves of the remote player are received from a peer with this role.
+    fn sends_moves(&self) -> bool {
+        matches!(self, PeerRole::Host | PeerRole::Opponent)
+    }
+}
+
+/// Another instance of the game connected to this one.
+struct Peer {
+    stream: TcpStream,
+    role: PeerRole,
+    // events of the connection, read on a separate thread
+    events: Mutex<Receiver<PeerEvent>>,
+    // lines received from the peer that weren't handled yet
+    pending: VecDeque<String>,
+}
+
+impl Peer {
+    fn new(stream: TcpStream, role: PeerRole) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+
+                if sender.send(PeerEvent::Line(line)).is_err() {
+                    return;
+                }
+            }
+
+            _ = sender.send(PeerEvent::Disconnected);
+        });
+
+        Ok(Self {
+            stream,
+            role,
+            events: Mutex::new(receiver),
+            pending: VecDeque::new(),
+        })
+    }
+
+    fn send(&mut self, message: NetworkMessage, rows: usize) {
+        if let Err(err) = writeln!(self.stream, "{}", message.to_line(rows)) {
+            warn!("failed to send to another instance: {}", err);
+        }
+    }
+
+    fn send_error(&mut self, message: impl Into<String>) {
+        self.send(
+            NetworkMessage::Error {
+                message: message.into(),
+            },
+            0,
+        );
+    }
+
+    fn close(self) {
+        _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// A move of the current game together with the clocks after it.
+struct LoggedMove {
+    mv: Move,
+    attacker: ClockState,
+    defender: ClockState,
+}
+
+impl LoggedMove {
+    fn message(&self) -> NetworkMessage {
+        NetworkMessage::Move {
+            from: self.mv.from,
+            to: self.mv.to,
+            attacker: self.attacker,
+            defender: self.defender,
+        }
+    }
+}
+
+/// Everything that happened in the current game, kept by the host for spectators joining late and
+/// players resuming an interrupted game.
+struct GameLog {
+    rows: usize,
+    initial_position: String,
+    moves: Vec<LoggedMove>,
+}
+
+#[derive(Resource)]
+pub struct NetworkSession {
+    mode: NetworkMode,
+    // connections accepted by the host or made by the joining instance
+    incoming: Mutex<Receiver<io::Result<TcpStream>>>,
+    peers: Vec<Peer>,
+    // the id of the current game, needed to resume it after the connection was lost
+    session_id: Option<String>,
+    log: Option<GameLog>,
+    // whether a spectating instance is showing the current game of the host
+    watching: bool,
+    status: String,
+}
+
+impl NetworkSession {
+    /// Describes the state of the connection, e.g. for showing it in the main menu.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    fn has_opponent(&self) -> bool {
+        self.peers.iter().any(|peer| peer.role == PeerRole::Opponent)
+    }
+}
+
+fn start_network(network_mode: Res<NetworkMode>, mut commands: Commands) {
+    let (incoming, status) = match network_mode.clone() {
+        NetworkMode::Host { port, .. } => {
+            let listener = match TcpListener::bind(("0.0.0.0", port)) {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!("failed to host on port {}: {}", port, err);
+                    return;
+                }
+            };
+
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+            });
+
+            (receiver, network_mode.idle_status())
+        }
+        NetworkMode::Join { address } | NetworkMode::Spectate { address } => {
+            let status = format!("Connecting to {}...", address);
+            (connect(address, 1), status)
+        }
+    };
+
+    commands.insert_resource(NetworkSession {
+        mode: network_mode.clone(),
+        incoming: Mutex::new(incoming),
+        peers: vec![],
+        session_id: None,
+        log: None,
+        watching: false,
+        status,
+    });
+}
+
+fn accept_connection(mut session: ResMut<NetworkSession>) {
+    let streams: Vec<io::Result<TcpStream>> =
+        session.incoming.lock().unwrap().try_iter().collect();
+
+    for stream in streams {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("failed to connect: {}", err);
+                if let NetworkMode::Join { address } | NetworkMode::Spectate { address } =
+                    &session.mode
+                {
+                    session.status = format!("Couldn't connect to {}", address);
+                }
+                continue;
+            }
+        };
+
+        let role = if session.mode.is_host() {
+            PeerRole::Connecting
+        } else {
+            PeerRole::Host
+        };
+
+        let mut peer = match Peer::new(stream, role) {
+            Ok(peer) => peer,
+            Err(err) => {
+                warn!("failed to set up the connection: {}", err);
+                continue;
+            }
+        };
+
+        match &session.mode {
+            NetworkMode::Host { .. } => (),
+            NetworkMode::Join { address } => {
+                match &session.session_id {
+                    Some(session_id) => peer.send(
+                        NetworkMessage::Resume {
+                            session: session_id.clone(),
+                        },
+                        0,
+                    ),
+                    None => peer.send(NetworkMessage::Hello { version: version() }, 0),
+                }
+                session.status = format!("Connected to {}", address);
+            }
+            NetworkMode::Spectate { address } => {
+                peer.send(NetworkMessage::Spectate { version: version() }, 0);
+                session.status = format!("Connected to {}", address);
+            }
+        }
+
+        session.peers.push(peer);
+    }
+}
+
+fn receive_network_messages(
+    mut session: ResMut<NetworkSession>,
+    game_state: Res<State<GameState>>,
+    tafl_state: Res<State<TaflState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut next_tafl_state: ResMut<NextState<TaflState>>,
+) {
+    let session = session.as_mut();
+    let mut lost_player = false;
+
+    session.peers.retain_mut(|peer| {
+        let events: Vec<PeerEvent> = peer.events.lock().unwrap().try_iter().collect();
+
+        for event in events {
+            match event {
+                PeerEvent::Line(line) => peer.pending.push_back(line),
+                PeerEvent::Disconnected => {
+                    if peer.role.sends_moves() {
+                        warn!("the connection to the other player was lost");
+                        lost_player = true;
+                    }
+
+                    return false;
+                }
+            }
+        }
+
+        true
+    });
+
+    if !lost_player {
+        return;
+    }
+
+    session.status = session.mode.idle_status();
+
+    if *game_state.get() != GameState::InGame {
+        return;
+    }
+
+    // games that are still going on are paused until the players are connected again
+    if *tafl_state.get() == TaflState::Ended || session.mode.is_spectator() {
+        next_game_state.set(GameState::MainMenu);
+        return;
+    }
+
+    next_tafl_state.set(TaflState::Paused);
+
+    match &session.mode {
+        NetworkMode::Host { .. } => {
+            session.status = "Waiting for the opponent to reconnect...".to_string();
+        }
+        NetworkMode::Join { address } => {
+            session.status = format!("Reconnecting to {}...", address);
+            session.incoming = Mutex::new(connect(address.clone(), RECONNECT_ATTEMPTS));
+        }
+        NetworkMode::Spectate { .. } => (),
+    }
+}
+
+/// Handles everything but moves and resyncing, e.g. sets up the players and starts the game once
+/// both instances know about each other.
+fn handle_network_messages(
+    mut session: ResMut<NetworkSession>,
+    mut players: ResMut<Players>,
+    mut time_control: ResMut<TimeControl>,
+    game_state: Res<State<GameState>>,
+    tafl_state: Res<State<TaflState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut chat_message_event: EventWriter<ChatMessageEvent>,
+) {
+    let session = session.as_mut();
+    let in_game = *game_state.get() == GameState::InGame;
+    let is_paused = in_game && *tafl_state.get() == TaflState::Paused;
+    let has_opponent = session.has_opponent();
+
+    for peer in &mut session.peers {
+        while let Some(line) = peer.pending.front() {
+            if NetworkMessage::needs_board(line) {
+                // moves of the remote player have to wait for the game to start, except the ones
+                // of a game that a spectator left
+                if peer.role.sends_moves() && (session.watching || !session.mode.is_spectator()) {
+                    break;
+                }
+
+                // players may only resume a game that is waiting for them
+                if peer.role == PeerRole::Connecting && is_paused {
+                    break;
+                }
+
+                let line = peer.pending.pop_front().unwrap();
+                if peer.role == PeerRole::Connecting {
+                    peer.send_error(format!("unexpected message {}", line));
+                }
+                continue;
+            }
+
+            let line = peer.pending.pop_front().unwrap();
+
+            match NetworkMessage::parse(&line, 0, 0) {
+                Ok(NetworkMessage::Hello { version: other_version })
+                    if peer.role == PeerRole::Connecting =>
+                {
+                    let NetworkMode::Host { side, .. } = session.mode else {
+                        continue;
+                    };
+
+                    if other_version != version() {
+                        peer.send_error(format!("the host runs version {}", version()));
+                        continue;
+                    }
+
+                    if has_opponent || in_game {
+                        peer.send_error("the game is already full");
+                        continue;
+                    }
+
+                    let session_id = new_session_id();
+
+                    peer.role = PeerRole::Opponent;
+                    peer.send(
+                        NetworkMessage::Welcome {
+                            side: side.opponent(),
+                            session: session_id.clone(),
+                            time_control: time_control.clone(),
+                        },
+                        0,
+                    );
+
+                    players.set(side, PlayerKind::Human);
+                    players.set(side.opponent(), PlayerKind::Remote);
+                    session.session_id = Some(session_id);
+                    session.status = "An opponent joined".to_string();
+                    next_game_state.set(GameState::InGame);
+                }
+                Ok(NetworkMessage::Spectate { version: other_version })
+                    if peer.role == PeerRole::Connecting =>
+                {
+                    if other_version != version() {
+                        peer.send_error(format!("the host runs version {}", version()));
+                        continue;
+                    }
+
+                    peer.role = PeerRole::Spectator;
+                    peer.send(NetworkMessage::WelcomeSpectator, 0);
+
+                    // a spectator joining during a game catches up with the moves made so far
+                    if in_game {
+                        peer.send(
+                            NetworkMessage::Start {
+                                time_control: time_control.clone(),
+                            },
+                            0,
+                        );
+
+                        if let Some(log) = &session.log {
+                            for logged_move in &log.moves {
+                                peer.send(logged_move.message(), log.rows);
+                            }
+                        }
+                    }
+                }
+                Ok(NetworkMessage::Welcome {
+                    side,
+                    session: session_id,
+                    time_control: host_time_control,
+                }) if peer.role == PeerRole::Host => {
+                    *time_control = host_time_control;
+                    players.set(side, PlayerKind::Human);
+                    players.set(side.opponent(), PlayerKind::Remote);
+                    session.session_id = Some(session_id);
+                    next_game_state.set(GameState::InGame);
+                }
+                Ok(NetworkMessage::WelcomeSpectator) if peer.role == PeerRole::Host => {
+                    session.status = "Waiting for the game to start...".to_string();
+                }
+                Ok(NetworkMessage::Start {
+                    time_control: host_time_control,
+                }) if peer.role == PeerRole::Host => {
+                    *time_control = host_time_control;
+                    // both sides are played on the other instances
+                    players.set(Side::Attacker, PlayerKind::Remote);
+                    players.set(Side::Defender, PlayerKind::Remote);
+                    session.watching = true;
+                    next_game_state.set(GameState::InGame);
+                }
+                Ok(NetworkMessage::Quit) if peer.role == PeerRole::Host => {
+                    if in_game {
+                        next_game_state.set(GameState::MainMenu);
+                    }
+                }
+                Ok(NetworkMessage::Chat { side, text }) if peer.role.sends_moves() => {
+                    // players may only write as themselves
+                    if in_game && *players.get(side) == PlayerKind::Remote {
+                        chat_message_event.send(ChatMessageEvent {
+                            side,
+                            text: text.chars().take(MAX_MESSAGE_LENGTH).collect(),
+                        });
+                    }
+                }
+                Ok(NetworkMessage::Error { message }) => {
+                    warn!("another instance sent an error: {}", message);
+                    session.status = message;
+                }
+                Ok(_) => warn!("unexpected message `{}`", line),
+                Err(err) => warn!("another instance sent `{}`: {}", line, err),
+            }
+        }
+    }
+}
+
+/// Lets the opponent continue the paused game if it reconnected, sending it everything needed to
+/// catch up.
+fn resume_game(
+    mut session: ResMut<NetworkSession>,
+    q_game_timer: Query<&GameTimer>,
+    mut next_tafl_state: ResMut<NextState<TaflState>>,
+) {
+    let session = session.as_mut();
+
+    if !session.mode.is_host() {
+        return;
+    }
+
+    let mut has_opponent = session.has_opponent();
+
+    for peer in &mut session.peers {
+        if peer.role != PeerRole::Connecting {
+            continue;
+        }
+
+        let Some(line) = peer.pending.pop_front() else {
+            continue;
+        };
+
+        let Ok(NetworkMessage::Resume {
+            session: session_id,
+        }) = NetworkMessage::parse(&line, 0, 0)
+        else {
+            peer.send_error(format!("unexpected message {}", line));
+            continue;
+        };
+
+        let (Some(log), false) = (&session.log, has_opponent) else {
+            peer.send_error("there is no game to resume");
+            continue;
+        };
+
+        if session.session_id.as_ref() != Some(&session_id) {
+            peer.send_error("the game to resume doesn't exist");
+            continue;
+        }
+
+        let clock = |side: Side| {
+            q_game_timer
+                .iter()
+                .find(|game_timer| game_timer.side == side)
+                .map(|game_timer| game_timer.clock.state())
+                .unwrap_or_default()
+        };
+
+        peer.role = PeerRole::Opponent;
+        peer.send(
+            NetworkMessage::Resync {
+                attacker: clock(Side::Attacker),
+                defender: clock(Side::Defender),
+                initial_position: log.initial_position.clone(),
+                moves: log.moves.iter().map(|logged_move| logged_move.mv).collect(),
+            },
+            log.rows,
+        );
+
+        has_opponent = true;
+        session.status = "The opponent reconnected".to_string();
+        next_tafl_state.set(TaflState::Playing);
+    }
+}
+
+/// Rebuilds the paused game from the history sent by the host after reconnecting.
+fn resync_game(
+    mut session: ResMut<NetworkSession>,
+    q_board: Query<(Entity, &Board)>,
+    mut q_game_timer: Query<(&mut GameTimer, &mut Text)>,
+    mut next_tafl_state: ResMut<NextState<TaflState>>,
+    mut reset_figures_event: EventWriter<ResetFiguresEvent>,
+) {
+    let Ok((board_entity, board)) = q_board.get_single() else {
+        return;
+    };
+
+    if !matches!(session.mode, NetworkMode::Join { .. }) {
+        return;
+    }
+
+    let Some(peer) = session
+        .peers
+        .iter_mut()
+        .find(|peer| peer.role == PeerRole::Host)
+    else {
+        return;
+    };
+
+    let Some(line) = peer.pending.pop_front() else {
+        return;
+    };
+
+    let Ok(NetworkMessage::Resync {
+        attacker,
+        defender,
+        initial_position,
+        moves,
+    }) = NetworkMessage::parse(&line, board.rows, board.cols)
+    else {
+        warn!("expected the host to resync, but got `{}`", line);
+        return;
+    };
+
+    // replay the game without the world to know where the figures are now
+    let game = parse_position(&initial_position).and_then(|parsed| {
+        if parsed.rows != board.rows || parsed.cols != board.cols {
+            return Err("the initial position should have the size of the board");
+        }
+
+        let mut game = TaflGame::new(Rules::from_board(board), &parsed.figures, Side::Attacker)?;
+        for mv in moves {
+            game.make_move(mv)?;
+        }
+
+        Ok(game)
+    });
+
+    let game = match game {
+        Ok(game) => game,
+        Err(err) => {
+            warn!("failed to resync the game: {}", err);
+            peer.send_error(format!("failed to resync: {}", err));
+            return;
+        }
+    };
+
+    reset_figures_event.send(ResetFiguresEvent {
+        board_entity,
+        figures: game
+            .iter_figures()
+            .map(|(position, figure_type)| Figure {
+                side: figure_type.side,
+                kind: figure_type.kind,
+                position,
+            })
+            .collect(),
+        side_to_move: game.side_to_move(),
+    });
+
+    for (mut game_timer, mut text) in &mut q_game_timer {
+        let state = match game_timer.side {
+            Side::Attacker => attacker,
+            Side::Defender => defender,
+        };
+
+        game_timer.clock.set_state(state);
+        set_timer_text(&game_timer, &mut text);
+    }
+
+    session.status = "Reconnected".to_string();
+    next_tafl_state.set(TaflState::Playing);
+}
+
+/// Submits the moves of the remote player, one per frame.
+fn apply_remote_moves(
+    mut session: ResMut<NetworkSession>,
+    players: Res<Players>,
+    q_board: Query<(Entity, &Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    q_sliding_figure: Query<(), With<FigureToSlideAndMove>>,
+    mut q_game_timer: Query<(&mut GameTimer, &mut Text)>,
+    mut try_move_figure_event: EventWriter<TryMoveFigureEvent>,
+) {
+    // the previous move is still in progress
+    if !q_sliding_figure.is_empty() {
+        return;
+    }
+
+    let Ok((board_entity, board, turn_tracker)) = q_board.get_single() else {
+        return;
+    };
+
+    let Some(peer) = session
+        .peers
+        .iter_mut()
+        .find(|peer| peer.role.sends_moves())
+    else {
+        return;
+    };
+
+    let Some(line) = peer.pending.pop_front() else {
+        return;
+    };
+
+    let message = match NetworkMessage::parse(&line, board.rows, board.cols) {
+        Ok(message) => message,
+        Err(err) => {
+            warn!("the other player sent `{}`: {}", line, err);
+            return;
+        }
+    };
+
+    let NetworkMessage::Move {
+        from,
+        to,
+        attacker,
+        defender,
+    } = message
+    else {
+        warn!("unexpected message `{}`", line);
+        return;
+    };
+
+    let is_legal = *players.get(turn_tracker.side) == PlayerKind::Remote
+        && board.figures.get(&from).is_some_and(|figure_entity| {
+            let figure = q_figure.get(*figure_entity).unwrap();
+            figure.side == turn_tracker.side && possible_moves(board, *figure).contains(&to)
+        });
+
+    if !is_legal {
+        warn!("the other player sent an illegal move `{}`", line);
+        peer.send_error(format!("illegal move {}", line));
+        return;
+    }
+
+    for (mut game_timer, mut text) in &mut q_game_timer {
+        let reported = match game_timer.side {
+            Side::Attacker => attacker,
+            Side::Defender => defender,
+        };
+
+        if let Some(state) = accepted_clock(peer.role, game_timer.clock.state(), reported) {
+            game_timer.clock.set_state(state);
+            set_timer_text(&game_timer, &mut text);
+        }
+    }
+
+    // moves received while catching up are applied without sliding
+    let slide = !peer
+        .pending
+        .iter()
+        .any(|line| NetworkMessage::needs_board(line));
+
+    try_move_figure_event.send(TryMoveFigureEvent {
+        board_entity,
+        from,
+        to,
+        slide,
+    });
+}
+
+/// Decides which clock received from `role` along with a move replaces the `tracked` one.
+///
+/// The host keeps the clocks of the game, so the ones of the opponent are ignored, and the host
+/// can only take time away, e.g. the time its messages took, never give any.
+fn accepted_clock(role: PeerRole, tracked: ClockState, reported: ClockState) -> Option<ClockState> {
+    if role != PeerRole::Host {
+        return None;
+    }
+
+    if tracked.remaining < reported.remaining || tracked.periods < reported.periods {
+        return None;
+    }
+
+    Some(reported)
+}
+
+/// Remembers the position the game started from once the figures are spawned.
+fn record_initial_position(
+    mut session: ResMut<NetworkSession>,
+    q_board: Query<&Board>,
+    q_figure: Query<&Figure>,
+) {
+    if !session.mode.is_host() || session.log.is_some() {
+        return;
+    }
+
+    let Ok(board) = q_board.get_single() else {
+        return;
+    };
+
+    if board.figures.is_empty() {
+        return;
+    }
+
+    let mut figures = HashMap::<Position, FigureType>::new();
+
+    for (position, figure_entity) in &board.figures {
+        // the figures might not exist yet
+        let Ok(figure) = q_figure.get(*figure_entity) else {
+            return;
+        };
+
+        figures.insert(
+            *position,
+            FigureType {
+                side: figure.side,
+                kind: figure.kind,
+            },
+        );
+    }
+
+    session.log = Some(GameLog {
+        rows: board.rows,
+        initial_position: format_position(&figures, board.rows, board.cols),
+        moves: vec![],
+    });
+}
+
+/// Sends the moves made on this instance to the other player and all moves to the spectators.
+fn send_moves(
+    mut event: EventReader<FigureMovedEvent>,
+    mut session: ResMut<NetworkSession>,
+    players: Res<Players>,
+    q_board: Query<&Board>,
+    q_game_timer: Query<&GameTimer>,
+) {
+    let session = session.as_mut();
+
+    for ev in event.read() {
+        let board = q_board.get(ev.board_entity).unwrap();
+
+        let clock = |side: Side| {
+            q_game_timer
+                .iter()
+                .find(|game_timer| game_timer.side == side)
+                .map(|game_timer| game_timer.clock.state())
+                .unwrap_or_default()
+        };
+
+        let logged_move = LoggedMove {
+            mv: Move {
+                from: ev.from,
+                to: ev.to,
+            },
+            attacker: clock(Side::Attacker),
+            defender: clock(Side::Defender),
+        };
+
+        let is_remote_move = *players.get(ev.side) == PlayerKind::Remote;
+
+        for peer in &mut session.peers {
+            let should_send = match peer.role {
+                PeerRole::Host | PeerRole::Opponent => !is_remote_move,
+                PeerRole::Spectator => true,
+                PeerRole::Connecting => false,
+            };
+
+            if should_send {
+                peer.send(logged_move.message(), board.rows);
+            }
+        }
+
+        if let Some(log) = &mut session.log {
+            log.moves.push(logged_move);
+        }
+    }
+}
+
+/// Sends the messages written on this instance to the other player and all messages to the
+/// spectators.
+fn send_chat_messages(
+    mut event: EventReader<ChatMessageEvent>,
+    mut session: ResMut<NetworkSession>,
+    players: Res<Players>,
+) {
+    for ev in event.read() {
+        let is_remote_message = *players.get(ev.side) == PlayerKind::Remote;
+
+        for peer in &mut session.peers {
+            let should_send = match peer.role {
+                PeerRole::Host | PeerRole::Opponent => !is_remote_message,
+                PeerRole::Spectator => true,
+                PeerRole::Connecting => false,
+            };
+
+            if should_send {
+                peer.send(
+                    NetworkMessage::Chat {
+                        side: ev.side,
+                        text: ev.text.clone(),
+                    },
+                    0,
+                );
+            }
+        }
+    }
+}
+
+/// Lets the spectators know that a game started.
+fn start_spectators(mut session: ResMut<NetworkSession>, time_control: Res<TimeControl>) {
+    for peer in &mut session.peers {
+        if peer.role == PeerRole::Spectator {
+            peer.send(
+                NetworkMessage::Start {
+                    time_control: time_control.clone(),
+                },
+                0,
+            );
+        }
+    }
+}
+
+/// Ends the connection to the other player when the game is left, spectators stay connected to
+/// the host to watch the next game.
+fn disconnect(
+    mut session: ResMut<NetworkSession>,
+    mut time_control: ResMut<TimeControl>,
+    settings: Res<Settings>,
+) {
+    let session = session.as_mut();
+
+    // the clock of the host only applies to its games
+    if !matches!(session.mode, NetworkMode::Host { .. }) {
+        *time_control = settings.time_control();
+    }
+
+    for mut peer in std::mem::take(&mut session.peers) {
+        match peer.role {
+            PeerRole::Spectator => {
+                peer.send(NetworkMessage::Quit, 0);
+                session.peers.push(peer);
+            }
+            PeerRole::Host if session.mode.is_spectator() => session.peers.push(peer),
+            _ => peer.close(),
+        }
+    }
+
+    // stop reconnecting to a game that was left
+    if let NetworkMode::Join { .. } = session.mode {
+        let (_, receiver) = mpsc::channel();
+        session.incoming = Mutex::new(receiver);
+    }
+
+    session.session_id = None;
+    session.log = None;
+    session.watching = false;
+    session.status = if session.mode.is_spectator() && !session.peers.is_empty() {
+        "Waiting for the next game...".to_string()
+    } else {
+        session.mode.idle_status()
+    };
+}
+
+#[derive(Component)]
+struct ReconnectUi;
+
+#[derive(Component)]
+struct ReconnectText;
+
+fn spawn_reconnect_ui(
+    session: Res<NetworkSession>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    commands
+        .spawn((
+            ReconnectUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        justify_content: JustifyContent::Center,
+                        padding: UiRect::all(Val::Px(20.)),
+                        ..default()
+                    },
+                    background_color: Color::rgba(0., 0., 0., 0.8).into(),
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        ReconnectText,
+                        TextBundle::from_section(
+                            session.status(),
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 30.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                    ));
+                });
+        });
+}
+
+fn update_reconnect_ui(
+    session: Res<NetworkSession>,
+    mut q_text: Query<&mut Text, With<ReconnectText>>,
+) {
+    for mut text in &mut q_text {
+        if text.sections[0].value != session.status() {
+            text.sections[0].value = session.status().to_string();
+        }
+    }
+}
+
+fn despawn_reconnect_ui(q_reconnect_ui: Query<Entity, With<ReconnectUi>>, mut commands: Commands) {
+    for reconnect_ui_entity in &q_reconnect_ui {
+        commands.entity(reconnect_ui_entity).despawn_recursive();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An instance running only the systems that connect it and set up the game.
+    fn instance(mode: NetworkMode, incoming: Receiver<io::Result<TcpStream>>) -> App {
+        let mut app = App::new();
+
+        app.init_state::<GameState>()
+            .init_state::<TaflState>()
+            .add_event::<ChatMessageEvent>()
+            .init_resource::<Players>()
+            .init_resource::<TimeControl>()
+            .insert_resource(NetworkSession {
+                status: mode.idle_status(),
+                mode,
+                incoming: Mutex::new(incoming),
+                peers: vec![],
+                session_id: None,
+                log: None,
+                watching: false,
+            })
+            .add_systems(
+                Update,
+                (
+                    accept_connection,
+                    receive_network_messages,
+                    handle_network_messages,
+                )
+                    .chain(),
+            );
+
+        app
+    }
+
+    /// Accepts connections on a free local port like a host does, returns the address.
+    fn listen() -> (String, Receiver<io::Result<TcpStream>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if sender.send(stream).is_err() {
+                    break;
+                }
+            }
+        });
+
+        (address, receiver)
+    }
+
+    /// Updates the instances until `done` holds for all of them.
+    fn update_until(instances: &mut [&mut App], done: impl Fn(&mut App) -> bool) {
+        for _ in 0..500 {
+            for app in instances.iter_mut() {
+                app.update();
+            }
+
+            if instances.iter_mut().all(|app| done(app)) {
+                return;
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        panic!("the instances should be done in time");
+    }
+
+    fn game_state(app: &App) -> GameState {
+        app.world.resource::<State<GameState>>().get().clone()
+    }
+
+    fn session(app: &mut App) -> Mut<'_, NetworkSession> {
+        app.world.resource_mut::<NetworkSession>()
+    }
+
+    #[test]
+    fn players_join_and_exchange_moves() {
+        let (address, incoming) = listen();
+
+        let mut host = instance(
+            NetworkMode::Host {
+                port: 0,
+                side: Side::Defender,
+            },
+            incoming,
+        );
+        let mut joiner = instance(
+            NetworkMode::Join {
+                address: address.clone(),
+            },
+            connect(address, 1),
+        );
+
+        let host_time_control = TimeControl {
+            time: Duration::from_secs(300),
+            bonus: TimeBonus::Delay(Duration::from_secs(2)),
+            move_limit: None,
+            unlimited: false,
+        };
+        host.insert_resource(host_time_control.clone());
+
+        update_until(&mut [&mut host, &mut joiner], |app| {
+            game_state(app) == GameState::InGame
+        });
+
+        // the game is played with the clock of the host
+        assert_eq!(*joiner.world.resource::<TimeControl>(), host_time_control);
+
+        let host_players = host.world.resource::<Players>();
+        assert_eq!(host_players.defender, PlayerKind::Human);
+        assert_eq!(host_players.attacker, PlayerKind::Remote);
+
+        let joiner_players = joiner.world.resource::<Players>();
+        assert_eq!(joiner_players.attacker, PlayerKind::Human);
+        assert_eq!(joiner_players.defender, PlayerKind::Remote);
+
+        // both know the game, so that it can be resumed
+        assert!(session(&mut host).session_id.is_some());
+        assert_eq!(
+            session(&mut host).session_id,
+            session(&mut joiner).session_id
+        );
+
+        // the moves wait for the board, which doesn't exist here
+        let attacker_move = NetworkMessage::Move {
+            from: Position { x: 3, y: 0 },
+            to: Position { x: 3, y: 4 },
+            attacker: ClockState {
+                remaining: Duration::from_millis(299_000),
+                periods: 0,
+            },
+            defender: ClockState {
+                remaining: Duration::from_millis(300_000),
+                periods: 0,
+            },
+        };
+        session(&mut joiner).peers[0].send(attacker_move.clone(), 11);
+
+        let defender_move = NetworkMessage::Move {
+            from: Position { x: 5, y: 3 },
+            to: Position { x: 8, y: 3 },
+            attacker: ClockState {
+                remaining: Duration::from_millis(299_000),
+                periods: 0,
+            },
+            defender: ClockState {
+                remaining: Duration::from_millis(298_000),
+                periods: 0,
+            },
+        };
+        session(&mut host).peers[0].send(defender_move.clone(), 11);
+
+        update_until(&mut [&mut host, &mut joiner], |app| {
+            !session(app).peers[0].pending.is_empty()
+        });
+
+        let received = |app: &mut App| {
+            let line = session(app).peers[0].pending.pop_front().unwrap();
+            NetworkMessage::parse(&line, 11, 11).unwrap()
+        };
+
+        assert_eq!(received(&mut host), attacker_move);
+        assert_eq!(received(&mut joiner), defender_move);
+    }
+
+    #[test]
+    fn only_the_host_sets_the_clocks() {
+        let clock = |secs: u64, periods: u32| ClockState {
+            remaining: Duration::from_secs(secs),
+            periods,
+        };
+
+        // the opponent can't change the clocks of the host
+        assert_eq!(
+            accepted_clock(PeerRole::Opponent, clock(300, 0), clock(200, 0)),
+            None
+        );
+        assert_eq!(
+            accepted_clock(PeerRole::Opponent, clock(300, 0), clock(400, 0)),
+            None
+        );
+
+        // the host can take time away, but not give any
+        assert_eq!(
+            accepted_clock(PeerRole::Host, clock(300, 3), clock(298, 3)),
+            Some(clock(298, 3))
+        );
+        assert_eq!(
+            accepted_clock(PeerRole::Host, clock(300, 3), clock(301, 3)),
+            None
+        );
+        assert_eq!(
+            accepted_clock(PeerRole::Host, clock(0, 2), clock(0, 3)),
+            None
+        );
+    }
+
+    #[test]
+    fn a_second_opponent_is_turned_away() {
+        let (address, incoming) = listen();
+
+        let mut host = instance(
+            NetworkMode::Host {
+                port: 0,
+                side: Side::Attacker,
+            },
+            incoming,
+        );
+        let mut joiner = instance(
+            NetworkMode::Join {
+                address: address.clone(),
+            },
+            connect(address.clone(), 1),
+        );
+
+        update_until(&mut [&mut host, &mut joiner], |app| {
+            game_state(app) == GameState::InGame
+        });
+
+        let mut late_joiner = instance(
+            NetworkMode::Join {
+                address: address.clone(),
+            },
+            connect(address, 1),
+        );
+
+        update_until(&mut [&mut host, &mut late_joiner], |app| {
+            session(app).mode.is_host() || session(app).status == "the game is already full"
+        });
+
+        assert_eq!(game_state(&late_joiner), GameState::MainMenu);
+    }
+
+    #[test]
+    fn spectators_watch_with_the_clock_of_the_host() {
+        let (address, incoming) = listen();
+
+        let mut host = instance(
+            NetworkMode::Host {
+                port: 0,
+                side: Side::Attacker,
+            },
+            incoming,
+        );
+        let mut joiner = instance(
+            NetworkMode::Join {
+                address: address.clone(),
+            },
+            connect(address.clone(), 1),
+        );
+
+        let host_time_control = TimeControl {
+            unlimited: true,
+            ..TimeControl::default()
+        };
+        host.insert_resource(host_time_control.clone());
+
+        update_until(&mut [&mut host, &mut joiner], |app| {
+            game_state(app) == GameState::InGame
+        });
+
+        // joins the game that already started
+        let mut spectator = instance(
+            NetworkMode::Spectate {
+                address: address.clone(),
+            },
+            connect(address, 1),
+        );
+
+        update_until(&mut [&mut host, &mut spectator], |app| {
+            game_state(app) == GameState::InGame
+        });
+
+        assert_eq!(
+            *spectator.world.resource::<TimeControl>(),
+            host_time_control
+        );
+
+        let spectator_players = spectator.world.resource::<Players>();
+        assert_eq!(spectator_players.attacker, PlayerKind::Remote);
+        assert_eq!(spectator_players.defender, PlayerKind::Remote);
+    }
+}
@@ -0,0 +1,373 @@
+//! Text protocol spoken between two instances of the game playing over the network.
+//!
+//! Every message is a single line. Squares and moves use the formats of the `notation` module.
+//!
+//! - `hello <version>` - sent by the joining instance after connecting
+//! - `spectate <version>` - sent instead of `hello` by an instance that only watches the games
+//! - `resume <session>` - sent instead of `hello` by a player reconnecting to an interrupted game
+//! - `welcome <attackers|defenders> <session> <time control>` - the answer of the host, with the
+//!   side of the joining player, the id of the game session and the time control of the game
+//! - `welcome spectator` - the answer of the host to a spectator
+//! - `resync <attacker clock> <defender clock> <initial position> <moves>...` - the answer of the
+//!   host to a resuming player, with the clocks, the position the game started from and all moves
+//!   made
+//! - `start <time control>` - sent by the host to spectators when a game starts, the moves made so
+//!   far follow
+//! - `quit` - sent by the host to spectators when the game is left
+//! - `move <from>-<to> <attacker clock> <defender clock>` - a move of the sender's player, followed
+//!   by the clocks of both sides after the move
+//! - `chat <attackers|defenders> <text>` - a chat message written by the player of the side, the
+//!   host forwards the messages of both players to spectators
+//! - `error <message>` - the sender couldn't handle a message
+//!
+//! Clocks and time controls use the formats of the `time_control` module.
+
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::Move;
+use crate::game::tafl::time_control::*;
+use crate::game::tafl::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NetworkMessage {
+    Hello {
+        version: String,
+    },
+    Spectate {
+        version: String,
+    },
+    Resume {
+        session: String,
+    },
+    Welcome {
+        side: Side,
+        session: String,
+        time_control: TimeControl,
+    },
+    WelcomeSpectator,
+    Resync {
+        attacker: ClockState,
+        defender: ClockState,
+        initial_position: String,
+        moves: Vec<Move>,
+    },
+    Start {
+        time_control: TimeControl,
+    },
+    Quit,
+    Move {
+        from: Position,
+        to: Position,
+        attacker: ClockState,
+        defender: ClockState,
+    },
+    Chat {
+        side: Side,
+        text: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl NetworkMessage {
+    /// Converts the message to a line of the protocol for a board with `rows` rows.
+    pub fn to_line(&self, rows: usize) -> String {
+        match self {
+            NetworkMessage::Hello { version } => format!("hello {}", version),
+            NetworkMessage::Spectate { version } => format!("spectate {}", version),
+            NetworkMessage::Resume { session } => format!("resume {}", session),
+            NetworkMessage::Welcome {
+                side,
+                session,
+                time_control,
+            } => format!(
+                "welcome {} {} {}",
+                format_side(*side),
+                session,
+                format_time_control(time_control).join(" ")
+            ),
+            NetworkMessage::WelcomeSpectator => "welcome spectator".to_string(),
+            NetworkMessage::Resync {
+                attacker,
+                defender,
+                initial_position,
+                moves,
+            } => {
+                let mut line = format!(
+                    "resync {} {} {}",
+                    format_clock_state(attacker),
+                    format_clock_state(defender),
+                    initial_position
+                );
+
+                for mv in moves {
+                    line.push(' ');
+                    line.push_str(&format_move(mv.from, mv.to, rows));
+                }
+
+                line
+            }
+            NetworkMessage::Start { time_control } => {
+                format!("start {}", format_time_control(time_control).join(" "))
+            }
+            NetworkMessage::Quit => "quit".to_string(),
+            NetworkMessage::Move {
+                from,
+                to,
+                attacker,
+                defender,
+            } => format!(
+                "move {} {} {}",
+                format_move(*from, *to, rows),
+                format_clock_state(attacker),
+                format_clock_state(defender)
+            ),
+            NetworkMessage::Chat { side, text } => format!("chat {} {}", format_side(*side), text),
+            NetworkMessage::Error { message } => format!("error {}", message),
+        }
+    }
+
+    /// Parses a line of the protocol for a board with the provided size.
+    pub fn parse(line: &str, rows: usize, cols: usize) -> Result<Self, &'static str> {
+        let line = line.trim();
+        let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "hello" => Ok(NetworkMessage::Hello {
+                version: arguments.to_string(),
+            }),
+            "spectate" => Ok(NetworkMessage::Spectate {
+                version: arguments.to_string(),
+            }),
+            "resume" => Ok(NetworkMessage::Resume {
+                session: arguments.to_string(),
+            }),
+            "welcome" if arguments == "spectator" => Ok(NetworkMessage::WelcomeSpectator),
+            "welcome" => {
+                let mut tokens = arguments.split_whitespace();
+                let side = parse_side(tokens.next().unwrap_or(""))?;
+
+                let Some(session) = tokens.next() else {
+                    return Err(
+                        "welcome should be followed by a side, a session and a time control",
+                    );
+                };
+
+                Ok(NetworkMessage::Welcome {
+                    side,
+                    session: session.to_string(),
+                    time_control: parse_time_control(tokens)?,
+                })
+            }
+            "resync" => {
+                let mut tokens = arguments.split_whitespace();
+                let attacker = parse_clock_state(tokens.next())?;
+                let defender = parse_clock_state(tokens.next())?;
+
+                let Some(initial_position) = tokens.next() else {
+                    return Err("resync should contain the initial position");
+                };
+
+                let moves = tokens
+                    .map(|token| {
+                        parse_move(token, rows, cols).map(|(from, to)| Move { from, to })
+                    })
+                    .collect::<Result<Vec<Move>, &'static str>>()?;
+
+                Ok(NetworkMessage::Resync {
+                    attacker,
+                    defender,
+                    initial_position: initial_position.to_string(),
+                    moves,
+                })
+            }
+            "start" => Ok(NetworkMessage::Start {
+                time_control: parse_time_control(arguments.split_whitespace())?,
+            }),
+            "quit" => Ok(NetworkMessage::Quit),
+            "move" => {
+                let mut tokens = arguments.split_whitespace();
+                let (from, to) = parse_move(tokens.next().unwrap_or(""), rows, cols)?;
+
+                Ok(NetworkMessage::Move {
+                    from,
+                    to,
+                    attacker: parse_clock_state(tokens.next())?,
+                    defender: parse_clock_state(tokens.next())?,
+                })
+            }
+            "chat" => {
+                let (side, text) = arguments.split_once(' ').unwrap_or((arguments, ""));
+
+                Ok(NetworkMessage::Chat {
+                    side: parse_side(side)?,
+                    text: text.to_string(),
+                })
+            }
+            "error" => Ok(NetworkMessage::Error {
+                message: arguments.to_string(),
+            }),
+            _ => Err("unknown message"),
+        }
+    }
+
+    /// Determines whether the message can only be handled once a board exists.
+    pub fn needs_board(line: &str) -> bool {
+        let line = line.trim_start();
+        line.starts_with("move") || line.starts_with("resume") || line.starts_with("resync")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn square(data: &str) -> Position {
+        parse_square(data, 11, 11).unwrap()
+    }
+
+    fn clock(millis: u64, periods: u32) -> ClockState {
+        ClockState {
+            remaining: Duration::from_millis(millis),
+            periods,
+        }
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let messages = [
+            NetworkMessage::Hello {
+                version: "0.1.0".to_string(),
+            },
+            NetworkMessage::Spectate {
+                version: "0.1.0".to_string(),
+            },
+            NetworkMessage::Resume {
+                session: "0123456789abcdef".to_string(),
+            },
+            NetworkMessage::Welcome {
+                side: Side::Defender,
+                session: "0123456789abcdef".to_string(),
+                time_control: TimeControl::default(),
+            },
+            NetworkMessage::Welcome {
+                side: Side::Attacker,
+                session: "0123456789abcdef".to_string(),
+                time_control: TimeControl {
+                    time: Duration::from_secs(300),
+                    bonus: TimeBonus::ByoYomi {
+                        periods: 3,
+                        period: Duration::from_secs(30),
+                    },
+                    move_limit: Some(Duration::from_secs(60)),
+                    unlimited: false,
+                },
+            },
+            NetworkMessage::WelcomeSpectator,
+            NetworkMessage::Resync {
+                attacker: clock(61_000, 0),
+                defender: clock(0, 2),
+                initial_position: "/3t3/7/7/3K3/7/7/3T3/".to_string(),
+                moves: vec![
+                    Move {
+                        from: square("d1"),
+                        to: square("d4"),
+                    },
+                    Move {
+                        from: square("k5"),
+                        to: square("h5"),
+                    },
+                ],
+            },
+            NetworkMessage::Resync {
+                attacker: ClockState::default(),
+                defender: ClockState::default(),
+                initial_position: "/3t3/7/7/3K3/7/7/3T3/".to_string(),
+                moves: vec![],
+            },
+            NetworkMessage::Start {
+                time_control: TimeControl {
+                    bonus: TimeBonus::Increment(Duration::from_secs(5)),
+                    ..TimeControl::default()
+                },
+            },
+            NetworkMessage::Start {
+                time_control: TimeControl {
+                    bonus: TimeBonus::Delay(Duration::from_secs(3)),
+                    ..TimeControl::default()
+                },
+            },
+            NetworkMessage::Start {
+                time_control: TimeControl {
+                    unlimited: true,
+                    ..TimeControl::default()
+                },
+            },
+            NetworkMessage::Quit,
+            NetworkMessage::Move {
+                from: square("a4"),
+                to: square("c4"),
+                attacker: clock(1_234, 0),
+                defender: clock(5_678, 0),
+            },
+            NetworkMessage::Chat {
+                side: Side::Attacker,
+                text: "good game, well played".to_string(),
+            },
+            NetworkMessage::Chat {
+                side: Side::Defender,
+                text: String::new(),
+            },
+            NetworkMessage::Error {
+                message: "the game is already full".to_string(),
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(
+                NetworkMessage::parse(&message.to_line(11), 11, 11),
+                Ok(message)
+            );
+        }
+    }
+
+    #[test]
+    fn moves_are_written_in_notation() {
+        let message = NetworkMessage::Move {
+            from: square("a4"),
+            to: square("c4"),
+            attacker: clock(1_234, 0),
+            defender: clock(0, 3),
+        };
+
+        assert_eq!(message.to_line(11), "move a4-c4 1234:0 0:3");
+    }
+
+    #[test]
+    fn invalid_lines_are_rejected() {
+        assert!(NetworkMessage::parse("", 11, 11).is_err());
+        assert!(NetworkMessage::parse("ping", 11, 11).is_err());
+        assert!(NetworkMessage::parse("welcome attackers", 11, 11).is_err());
+        assert!(NetworkMessage::parse("welcome attackers 0123", 11, 11).is_err());
+        assert!(NetworkMessage::parse("welcome nobody 0123 600000", 11, 11).is_err());
+        assert!(NetworkMessage::parse("start", 11, 11).is_err());
+        assert!(NetworkMessage::parse("start 600000 overtime 1000", 11, 11).is_err());
+        assert!(NetworkMessage::parse("move a4-c4 1234:0", 11, 11).is_err());
+        assert!(NetworkMessage::parse("move a4-c4 1234 5678", 11, 11).is_err());
+        assert!(NetworkMessage::parse("move a4-c4 1234:0 5678:x", 11, 11).is_err());
+        assert!(NetworkMessage::parse("move a4-z4 1234:0 5678:0", 11, 11).is_err());
+        assert!(NetworkMessage::parse("resync 1000:0 1000:0", 11, 11).is_err());
+        assert!(NetworkMessage::parse("chat nobody hi", 11, 11).is_err());
+    }
+
+    #[test]
+    fn only_moves_and_resuming_need_a_board() {
+        assert!(NetworkMessage::needs_board("move a4-c4 1234:0 5678:0"));
+        assert!(NetworkMessage::needs_board("resume 0123456789abcdef"));
+        assert!(NetworkMessage::needs_board("resync 1000 1000 /7/ a1-a2"));
+        assert!(!NetworkMessage::needs_board("hello 0.1.0"));
+        assert!(!NetworkMessage::needs_board("chat attackers move fast"));
+    }
+}
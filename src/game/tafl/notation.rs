@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use bevy::utils::HashMap;
+
+use crate::game::tafl::*;
+
+/// Converts a side to its notation, `attackers` or `defenders`.
+pub fn format_side(side: Side) -> &'static str {
+    match side {
+        Side::Attacker => "attackers",
+        Side::Defender => "defenders",
+    }
+}
+
+/// Parses the notation of a side.
+pub fn parse_side(data: &str) -> Result<Side, &'static str> {
+    match data {
+        "attackers" => Ok(Side::Attacker),
+        "defenders" => Ok(Side::Defender),
+        _ => Err("side should be either attackers or defenders"),
+    }
+}
+
+/// Parses a duration given in milliseconds.
+pub fn parse_millis(data: Option<&str>) -> Result<Duration, &'static str> {
+    data.and_then(|millis| millis.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .ok_or("time should be given in milliseconds")
+}
+
+/// The most files and ranks a board can have, since every file is written as a single letter.
+pub const MAX_BOARD_SIZE: usize = 26;
+
+// Squares are written as a file letter followed by a rank number, e.g. `a1`, where files are
+// counted from the left and ranks from the bottom of the board. Since `Position` counts rows from
+// the top converting between the two requires the row count of the board.
+
+/// Converts a position on a board with `rows` rows to its notation, e.g. `a1`.
+pub fn format_square(position: Position, rows: usize) -> String {
+    let file = (b'a' + position.x as u8) as char;
+    let rank = rows - position.y;
+
+    format!("{}{}", file, rank)
+}
+
+/// Parses the notation of a square on a board with the provided size.
+pub fn parse_square(data: &str, rows: usize, cols: usize) -> Result<Position, &'static str> {
+    let mut chars = data.chars();
+
+    let file = match chars.next() {
+        Some(c) if c.is_ascii_lowercase() => c as usize - 'a' as usize,
+        _ => return Err("a square should start with a lowercase file letter"),
+    };
+
+    let Ok(rank) = chars.as_str().parse::<usize>() else {
+        return Err("a square should end with a rank number");
+    };
+
+    if cols <= file || rank == 0 || rows < rank {
+        return Err("the square should be on the board");
+    }
+
+    Ok(Position {
+        x: file,
+        y: rows - rank,
+    })
+}
+
+/// Converts a move to its notation, e.g. `a4-d4`.
+pub fn format_move(from: Position, to: Position, rows: usize) -> String {
+    format!("{}-{}", format_square(from, rows), format_square(to, rows))
+}
+
+/// Parses the notation of a move on a board with the provided size.
+pub fn parse_move(
+    data: &str,
+    rows: usize,
+    cols: usize,
+) -> Result<(Position, Position), &'static str> {
+    let Some((from, to)) = data.split_once('-') else {
+        return Err("a move should consist of two squares separated by -");
+    };
+
+    Ok((
+        parse_square(from, rows, cols)?,
+        parse_square(to, rows, cols)?,
+    ))
+}
+
+// Positions are written row by row from the top of the board, each row is terminated by a `/`
+// and the whole string starts with one as well. Inside a row `t` is an attacker soldier, `T` a
+// defender soldier, `K` the king and a number is that many empty squares, e.g. `/3t3/7/.../`.
+
+/// Gets the letter standing for a figure type in position strings.
+pub fn figure_type_to_char(figure_type: FigureType) -> char {
+    match (figure_type.side, figure_type.kind) {
+        (Side::Attacker, FigureKind::Soldier) => 't',
+        (Side::Attacker, FigureKind::King) => 'k',
+        (Side::Defender, FigureKind::Soldier) => 'T',
+        (Side::Defender, FigureKind::King) => 'K',
+    }
+}
+
+fn char_to_figure_type(c: char) -> Option<FigureType> {
+    let (side, kind) = match c {
+        't' => (Side::Attacker, FigureKind::Soldier),
+        'k' => (Side::Attacker, FigureKind::King),
+        'T' => (Side::Defender, FigureKind::Soldier),
+        'K' => (Side::Defender, FigureKind::King),
+        _ => return None,
+    };
+
+    Some(FigureType { side, kind })
+}
+
+/// Converts the figures on a board with the provided size to a position string.
+pub fn format_position(figures: &HashMap<Position, FigureType>, rows: usize, cols: usize) -> String {
+    let mut result = String::from("/");
+
+    for y in 0..rows {
+        let mut empty = 0;
+
+        for x in 0..cols {
+            let Some(figure_type) = figures.get(&Position { x, y }) else {
+                empty += 1;
+                continue;
+            };
+
+            if 0 < empty {
+                result.push_str(&empty.to_string());
+                empty = 0;
+            }
+
+            result.push(figure_type_to_char(*figure_type));
+        }
+
+        if 0 < empty {
+            result.push_str(&empty.to_string());
+        }
+
+        result.push('/');
+    }
+
+    result
+}
+
+pub struct ParsedPosition {
+    pub rows: usize,
+    pub cols: usize,
+    pub figures: Vec<Figure>,
+}
+
+/// Parses a position string.
+pub fn parse_position(data: &str) -> Result<ParsedPosition, &'static str> {
+    let Some(data) = data.strip_prefix('/') else {
+        return Err("a position should start with /");
+    };
+
+    let Some(data) = data.strip_suffix('/') else {
+        return Err("a position should end with /");
+    };
+
+    let too_large = "a position should have at most 26 files and ranks";
+
+    let mut rows = 0;
+    let mut cols = 0;
+    let mut figures = vec![];
+
+    for (y, row) in data.split('/').enumerate() {
+        let mut x = 0;
+        let mut empty: usize = 0;
+
+        for c in row.chars() {
+            if let Some(digit) = c.to_digit(10) {
+                empty = empty
+                    .checked_mul(10)
+                    .and_then(|empty| empty.checked_add(digit as usize))
+                    .filter(|empty| x + empty <= MAX_BOARD_SIZE)
+                    .ok_or(too_large)?;
+                continue;
+            }
+
+            x += empty;
+            empty = 0;
+
+            let Some(figure_type) = char_to_figure_type(c) else {
+                return Err("a row should only consist of numbers and t, k, T or K");
+            };
+
+            figures.push(Figure {
+                side: figure_type.side,
+                kind: figure_type.kind,
+                position: Position { x, y },
+            });
+
+            x += 1;
+
+            if MAX_BOARD_SIZE < x {
+                return Err(too_large);
+            }
+        }
+
+        x += empty;
+
+        if y == 0 {
+            cols = x;
+        } else if x != cols {
+            return Err("position should have consistent row length");
+        }
+
+        rows += 1;
+
+        if MAX_BOARD_SIZE < rows {
+            return Err(too_large);
+        }
+    }
+
+    Ok(ParsedPosition {
+        rows,
+        cols,
+        figures,
+    })
+}
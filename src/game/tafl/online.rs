@@ -0,0 +1,543 @@
+//! Plays games against players met in the lobby of a server started with `hnefatafl-server`, the
+//! messages are described in `server_protocol`.
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::Message;
+
+use crate::game::settings::Settings;
+use crate::game::tafl::chat::*;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::{self, Move};
+use crate::game::tafl::server_protocol::*;
+use crate::game::tafl::*;
+
+// how often the connection checks for messages to send
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+pub struct OnlinePlugin;
+
+impl Plugin for OnlinePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                receive_server_messages,
+                apply_server_moves
+                    .run_if(in_state(GameState::InGame).and_then(in_state(TaflState::Playing))),
+            )
+                .chain()
+                .before(try_move_figure)
+                .run_if(resource_exists::<OnlineSession>),
+        )
+        .add_systems(
+            Update,
+            (
+                send_moves.after(move_figure),
+                receive_chat_messages,
+                send_chat_messages,
+            )
+                .run_if(in_state(GameState::InGame).and_then(resource_exists::<OnlineSession>)),
+        )
+        .add_systems(
+            OnExit(GameState::InGame),
+            leave_game.run_if(resource_exists::<OnlineSession>),
+        )
+        .init_resource::<OnlineServer>();
+    }
+}
+
+/// The server the lobby is on.
+#[derive(Resource, Debug, Clone)]
+pub struct OnlineServer {
+    /// E.g. `localhost:7878`.
+    pub address: String,
+}
+
+impl Default for OnlineServer {
+    fn default() -> Self {
+        Self {
+            address: "localhost:7878".to_string(),
+        }
+    }
+}
+
+enum ServerEvent {
+    Connected,
+    Message(ServerMessage),
+    Disconnected(String),
+}
+
+/// Connects to the server on a separate thread, which sends the messages put into the returned
+/// sender and receives the ones of the server until the sender is dropped.
+fn connect(address: &str) -> (Sender<ClientMessage>, Receiver<ServerEvent>) {
+    let url = format!("ws://{}", address);
+    let (outbox_sender, outbox) = mpsc::channel();
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let reason = match serve(&url, &outbox, &sender) {
+            Ok(()) => "the connection was closed".to_string(),
+            Err(err) => err.to_string(),
+        };
+
+        _ = sender.send(ServerEvent::Disconnected(reason));
+    });
+
+    (outbox_sender, receiver)
+}
+
+fn serve(
+    url: &str,
+    outbox: &Receiver<ClientMessage>,
+    events: &Sender<ServerEvent>,
+) -> io::Result<()> {
+    let (mut socket, _) =
+        tungstenite::connect(url).map_err(|err| io::Error::other(err.to_string()))?;
+
+    // reads time out so the messages of the player can be sent in between
+    if let MaybeTlsStream::Plain(stream) = socket.get_ref() {
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    }
+
+    _ = events.send(ServerEvent::Connected);
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str(&text) {
+                Ok(message) => _ = events.send(ServerEvent::Message(message)),
+                Err(err) => warn!("the server sent `{}`: {}", text, err),
+            },
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(err) => return Err(io::Error::other(err)),
+        }
+
+        loop {
+            match outbox.try_recv() {
+                Ok(message) => {
+                    let text =
+                        serde_json::to_string(&message).expect("messages should be serializable");
+                    socket.send(Message::Text(text)).map_err(io::Error::other)?;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    // the session was dropped
+                    _ = socket.close(None);
+                    _ = socket.flush();
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// A game played on the server.
+struct OnlineGame {
+    game_id: u64,
+    // the side played on this instance
+    side: Side,
+    rows: usize,
+    cols: usize,
+    // moves of the opponent that weren't applied yet
+    pending: VecDeque<Move>,
+    // the time left of both sides sent by the server, if it wasn't applied yet
+    clock: Option<(ClockState, ClockState)>,
+    // messages of the opponent that weren't shown yet
+    chat: Vec<ChatMessageEvent>,
+    // whether the server ended the game
+    over: bool,
+    // the players before the game, restored once it is left
+    previous_players: Players,
+}
+
+/// The connection to the server, it is closed when the session is dropped.
+#[derive(Resource)]
+pub struct OnlineSession {
+    outbox: Sender<ClientMessage>,
+    events: Mutex<Receiver<ServerEvent>>,
+    connected: bool,
+    name: Option<String>,
+    open_games: Vec<OpenGame>,
+    queued: bool,
+    // the challenge created on this instance that wasn't accepted yet
+    challenge: Option<u64>,
+    game: Option<OnlineGame>,
+    status: String,
+}
+
+impl OnlineSession {
+    pub fn connect(address: &str) -> Self {
+        let (outbox, events) = connect(address);
+
+        Self {
+            outbox,
+            events: Mutex::new(events),
+            connected: true,
+            name: None,
+            open_games: vec![],
+            queued: false,
+            challenge: None,
+            game: None,
+            status: format!("Connecting to {}...", address),
+        }
+    }
+
+    /// Sends `message` to the server, messages sent before the connection is established are sent
+    /// once it is.
+    pub fn send(&self, message: ClientMessage) {
+        _ = self.outbox.send(message);
+    }
+
+    /// Determines whether the connection is established or still being established.
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    /// The name the player registered with.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The games listed by the last `list_games` message.
+    pub fn open_games(&self) -> &[OpenGame] {
+        &self.open_games
+    }
+
+    pub fn is_queued(&self) -> bool {
+        self.queued
+    }
+
+    /// The challenge created by the player that nobody accepted yet.
+    pub fn challenge(&self) -> Option<u64> {
+        self.challenge
+    }
+
+    /// Describes what happened last, e.g. for showing it in the lobby.
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+}
+
+fn receive_server_messages(
+    mut session: ResMut<OnlineSession>,
+    mut players: ResMut<Players>,
+    mut time_control: ResMut<TimeControl>,
+    game_state: Res<State<GameState>>,
+    tafl_state: Res<State<TaflState>>,
+    mut next_game_state: ResMut<NextState<GameState>>,
+    mut end_game_event: EventWriter<EndGameEvent>,
+) {
+    let session = session.as_mut();
+    let events: Vec<ServerEvent> = session.events.lock().unwrap().try_iter().collect();
+    let in_game = *game_state.get() == GameState::InGame;
+
+    for event in events {
+        let message = match event {
+            ServerEvent::Connected => {
+                session.status = "Connected".to_string();
+                continue;
+            }
+            ServerEvent::Message(message) => message,
+            ServerEvent::Disconnected(reason) => {
+                warn!("disconnected from the server: {}", reason);
+                session.connected = false;
+                session.name = None;
+                session.queued = false;
+                session.challenge = None;
+                session.status = format!("Disconnected from the server: {}", reason);
+
+                // the game can't go on without the server
+                if in_game && session.game.is_some() {
+                    next_game_state.set(GameState::MainMenu);
+                }
+                continue;
+            }
+        };
+
+        match message {
+            ServerMessage::Registered { name } => {
+                session.status = format!("Registered as {}", name);
+                session.name = Some(name);
+            }
+            ServerMessage::GameList { games } => session.open_games = games,
+            ServerMessage::GameCreated { game_id, .. } => {
+                session.challenge = Some(game_id);
+                session.status = "Waiting for someone to accept the challenge...".to_string();
+            }
+            ServerMessage::Queued { .. } => {
+                session.queued = true;
+                session.status = "Waiting for an opponent...".to_string();
+            }
+            ServerMessage::LeftQueue => {
+                session.queued = false;
+                session.status = "Left the queue".to_string();
+            }
+            ServerMessage::GameStarted {
+                game_id,
+                variant,
+                side,
+                opponent,
+                attackers_ms,
+                bonus,
+                move_limit_ms,
+                ..
+            } => {
+                let rules = match rules::variant(&variant) {
+                    Ok((rules, _)) => rules,
+                    Err(err) => {
+                        warn!("can't play the game of {}: {}", variant, err);
+                        session.send(ClientMessage::Resign { game_id });
+                        continue;
+                    }
+                };
+
+                session.game = Some(OnlineGame {
+                    game_id,
+                    side,
+                    rows: rules.rows,
+                    cols: rules.cols,
+                    pending: VecDeque::new(),
+                    clock: None,
+                    chat: vec![],
+                    over: false,
+                    previous_players: players.clone(),
+                });
+                session.queued = false;
+                session.challenge = None;
+                session.status = format!("Playing against {}", opponent);
+
+                players.set(side, PlayerKind::Human);
+                players.set(side.opponent(), PlayerKind::Remote);
+                *time_control = server_protocol::time_control(attackers_ms, bonus, move_limit_ms);
+                next_game_state.set(GameState::InGame);
+            }
+            ServerMessage::MoveMade {
+                game_id,
+                side,
+                from,
+                to,
+                ..
+            } => {
+                let Some(game) = session.game.as_mut().filter(|game| game.game_id == game_id)
+                else {
+                    continue;
+                };
+
+                // the moves made on this instance are already on the board
+                if side == game.side {
+                    continue;
+                }
+
+                let mv = parse_square(&from, game.rows, game.cols).and_then(|from| {
+                    Ok(Move {
+                        from,
+                        to: parse_square(&to, game.rows, game.cols)?,
+                    })
+                });
+
+                match mv {
+                    Ok(mv) => game.pending.push_back(mv),
+                    Err(err) => warn!("the server sent the move {}-{}: {}", from, to, err),
+                }
+            }
+            ServerMessage::Clock {
+                game_id,
+                attackers_ms,
+                defenders_ms,
+                attackers_periods,
+                defenders_periods,
+            } => {
+                if let Some(game) = session.game.as_mut().filter(|game| game.game_id == game_id) {
+                    game.clock = Some((
+                        ClockState {
+                            remaining: Duration::from_millis(attackers_ms),
+                            periods: attackers_periods,
+                        },
+                        ClockState {
+                            remaining: Duration::from_millis(defenders_ms),
+                            periods: defenders_periods,
+                        },
+                    ));
+                }
+            }
+            ServerMessage::Chat {
+                game_id,
+                side,
+                text,
+            } => {
+                let Some(game) = session.game.as_mut().filter(|game| game.game_id == game_id)
+                else {
+                    continue;
+                };
+
+                // the messages written on this instance are already shown
+                if side != game.side {
+                    game.chat.push(ChatMessageEvent { side, text });
+                }
+            }
+            ServerMessage::DrawOffered { .. } | ServerMessage::DrawDeclined { .. } => (),
+            ServerMessage::GameOver {
+                game_id,
+                result,
+                reason,
+            } => {
+                let Some(game) = session.game.as_mut().filter(|game| game.game_id == game_id)
+                else {
+                    continue;
+                };
+
+                game.over = true;
+
+                // games decided on the board end once the last move is applied
+                if reason != GameOverReason::Rules
+                    && in_game
+                    && *tafl_state.get() != TaflState::Ended
+                {
+                    end_game_event.send(EndGameEvent {
+                        outcome: result.into(),
+                    });
+                }
+
+                session.status = match reason {
+                    GameOverReason::Rules => "The game is over",
+                    GameOverReason::Timeout => "The game is over, the time ran out",
+                    GameOverReason::Resignation => "The game is over, a player resigned",
+                    GameOverReason::Agreement => "The game is over, the players agreed to a draw",
+                    GameOverReason::Abandoned => "The game is over, a player left",
+                }
+                .to_string();
+            }
+            ServerMessage::Error { message } => {
+                warn!("the server sent an error: {}", message);
+                session.status = message;
+            }
+        }
+    }
+}
+
+/// Submits the moves of the opponent, one per frame.
+fn apply_server_moves(
+    mut session: ResMut<OnlineSession>,
+    q_board: Query<Entity, With<Board>>,
+    q_sliding_figure: Query<(), With<FigureToSlideAndMove>>,
+    mut q_game_timer: Query<(&mut GameTimer, &mut Text)>,
+    mut try_move_figure_event: EventWriter<TryMoveFigureEvent>,
+) {
+    let Some(game) = &mut session.game else {
+        return;
+    };
+
+    if let Some((attacker, defender)) = game.clock.take() {
+        for (mut game_timer, mut text) in &mut q_game_timer {
+            let state = match game_timer.side {
+                Side::Attacker => attacker,
+                Side::Defender => defender,
+            };
+
+            game_timer.clock.set_state(state);
+            set_timer_text(&game_timer, &mut text);
+        }
+    }
+
+    // the previous move is still in progress
+    if !q_sliding_figure.is_empty() {
+        return;
+    }
+
+    let Ok(board_entity) = q_board.get_single() else {
+        return;
+    };
+
+    let Some(mv) = game.pending.pop_front() else {
+        return;
+    };
+
+    try_move_figure_event.send(TryMoveFigureEvent {
+        board_entity,
+        from: mv.from,
+        to: mv.to,
+        // moves received while catching up are applied without sliding
+        slide: game.pending.is_empty(),
+    });
+}
+
+/// Sends the moves made on this instance to the server.
+fn send_moves(
+    mut event: EventReader<FigureMovedEvent>,
+    session: Res<OnlineSession>,
+    q_board: Query<&Board>,
+) {
+    for ev in event.read() {
+        let Some(game) = &session.game else {
+            continue;
+        };
+
+        if ev.side != game.side {
+            continue;
+        }
+
+        let board = q_board.get(ev.board_entity).unwrap();
+
+        session.send(ClientMessage::Move {
+            game_id: game.game_id,
+            from: format_square(ev.from, board.rows),
+            to: format_square(ev.to, board.rows),
+        });
+    }
+}
+
+fn receive_chat_messages(
+    mut session: ResMut<OnlineSession>,
+    mut chat_message_event: EventWriter<ChatMessageEvent>,
+) {
+    if let Some(game) = &mut session.game {
+        chat_message_event.send_batch(game.chat.drain(..));
+    }
+}
+
+/// Sends the messages written on this instance to the server.
+fn send_chat_messages(mut event: EventReader<ChatMessageEvent>, session: Res<OnlineSession>) {
+    for ev in event.read() {
+        let Some(game) = &session.game else {
+            continue;
+        };
+
+        if ev.side == game.side {
+            session.send(ClientMessage::Chat {
+                game_id: game.game_id,
+                text: ev.text.clone(),
+            });
+        }
+    }
+}
+
+/// Resigns the game if it is left before it is over.
+fn leave_game(
+    mut session: ResMut<OnlineSession>,
+    mut players: ResMut<Players>,
+    mut time_control: ResMut<TimeControl>,
+    settings: Res<Settings>,
+    tafl_state: Res<State<TaflState>>,
+) {
+    let Some(game) = session.game.take() else {
+        return;
+    };
+
+    if !game.over && *tafl_state.get() != TaflState::Ended {
+        session.send(ClientMessage::Resign {
+            game_id: game.game_id,
+        });
+    }
+
+    *players = game.previous_players;
+    *time_control = settings.time_control();
+}
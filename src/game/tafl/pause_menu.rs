@@ -0,0 +1,460 @@
+use bevy::ecs::system::SystemParam;
+
+use crate::game::settings::*;
+use crate::game::tafl::chat::ChatOptions;
+use crate::game::tafl::save::*;
+use crate::game::tafl::*;
+use crate::game::widgets::*;
+
+/// The menu opened with Escape during a game, the game is paused while it is open.
+pub struct PauseMenuPlugin;
+
+impl Plugin for PauseMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveFromMenuEvent>()
+            .add_systems(
+                Update,
+                (
+                    toggle_pause_menu,
+                    pause_menu_buttons,
+                    mute_chat_button,
+                    save_from_menu,
+                    pause_game,
+                    update_pause_menu_ui,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .add_systems(OnExit(GameState::InGame), close_pause_menu)
+            .init_resource::<PauseMenu>();
+    }
+}
+
+/// The page of the pause menu that is shown.
+#[derive(Resource, Debug, Clone, PartialEq, Eq, Default)]
+enum PauseMenu {
+    #[default]
+    Closed,
+    Open {
+        // e.g. where the game was saved
+        status: String,
+    },
+    Settings,
+    Confirm(ConfirmedAction),
+}
+
+/// An action that ends the game or loses a saved one, it is only taken once the player confirms
+/// it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfirmedAction {
+    Resign,
+    Quit,
+    /// Saves the game in the slot, replacing the game saved there.
+    ReplaceSave(usize),
+}
+
+// the buttons of the menu are larger than the ones of the main menu
+const PAUSE_MENU_BUTTON: ButtonSize = ButtonSize {
+    width: 300.,
+    height: 45.,
+    font_size: 28.,
+};
+
+/// What the buttons of the menu do besides switching pages.
+#[derive(SystemParam)]
+struct PauseMenuActions<'w> {
+    next_game_state: ResMut<'w, NextState<GameState>>,
+    save_event: EventWriter<'w, SaveFromMenuEvent>,
+    end_game_event: EventWriter<'w, EndGameEvent>,
+}
+
+/// The options shown on the settings page of the menu.
+#[derive(SystemParam)]
+struct ShownOptions<'w> {
+    settings: Res<'w, Settings>,
+    chat_options: Res<'w, ChatOptions>,
+}
+
+#[derive(Event)]
+struct SaveFromMenuEvent {
+    // the slot of another game to save in, otherwise the game keeps its slot or takes a free one
+    replace: Option<usize>,
+}
+
+#[derive(Component)]
+struct PauseMenuUi;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+enum PauseMenuButton {
+    Resume,
+    Save,
+    Settings,
+    Resign,
+    Quit,
+    Confirm(ConfirmedAction),
+    /// Goes back to the first page.
+    Back,
+}
+
+/// Mutes the chat of the game, the other settings are the ones of the settings screen.
+#[derive(Component)]
+struct MuteChatButton;
+
+/// Determines whether everyone playing the game plays on this instance, only those games can be
+/// paused, saved and resigned from the menu.
+fn is_local(players: &Players) -> bool {
+    *players.get(Side::Attacker) != PlayerKind::Remote
+        && *players.get(Side::Defender) != PlayerKind::Remote
+}
+
+/// Gets the side resigning from the menu, None if nobody plays on this instance.
+fn resigning_side(players: &Players, side_to_move: Side) -> Option<Side> {
+    match (
+        players.is_human(Side::Attacker),
+        players.is_human(Side::Defender),
+    ) {
+        // in hot-seat games the player whose turn it is resigns
+        (true, true) => Some(side_to_move),
+        (true, false) => Some(Side::Attacker),
+        (false, true) => Some(Side::Defender),
+        (false, false) => None,
+    }
+}
+
+fn toggle_pause_menu(input: Res<ButtonInput<KeyCode>>, mut pause_menu: ResMut<PauseMenu>) {
+    if !input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+
+    *pause_menu = match *pause_menu {
+        PauseMenu::Closed => PauseMenu::Open {
+            status: String::new(),
+        },
+        PauseMenu::Open { .. } => PauseMenu::Closed,
+        PauseMenu::Settings | PauseMenu::Confirm(_) => PauseMenu::Open {
+            status: String::new(),
+        },
+    };
+}
+
+fn pause_menu_buttons(
+    mut q_button: Query<
+        (&Interaction, &PauseMenuButton, &mut BackgroundColor),
+        Changed<Interaction>,
+    >,
+    mut pause_menu: ResMut<PauseMenu>,
+    players: Res<Players>,
+    q_turn_tracker: Query<&TurnTracker>,
+    tafl_state: Res<State<TaflState>>,
+    mut actions: PauseMenuActions,
+) {
+    for (interaction, button, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        match button {
+            PauseMenuButton::Resume => *pause_menu = PauseMenu::Closed,
+            PauseMenuButton::Save => {
+                actions.save_event.send(SaveFromMenuEvent { replace: None });
+            }
+            PauseMenuButton::Settings => *pause_menu = PauseMenu::Settings,
+            PauseMenuButton::Resign => *pause_menu = PauseMenu::Confirm(ConfirmedAction::Resign),
+            // finished games have nothing left to abandon
+            PauseMenuButton::Quit if *tafl_state.get() == TaflState::Ended => {
+                actions.next_game_state.set(GameState::MainMenu);
+            }
+            PauseMenuButton::Quit => *pause_menu = PauseMenu::Confirm(ConfirmedAction::Quit),
+            PauseMenuButton::Confirm(ConfirmedAction::Resign) => {
+                let side_to_move = q_turn_tracker
+                    .iter()
+                    .next()
+                    .map(|turn_tracker| turn_tracker.side)
+                    .unwrap_or(Side::Attacker);
+
+                if let Some(side) = resigning_side(&players, side_to_move) {
+                    actions.end_game_event.send(EndGameEvent {
+                        outcome: GameOutcome::Win(side.opponent()),
+                    });
+                }
+
+                *pause_menu = PauseMenu::Closed;
+            }
+            PauseMenuButton::Confirm(ConfirmedAction::Quit) => {
+                actions.next_game_state.set(GameState::MainMenu);
+            }
+            PauseMenuButton::Confirm(ConfirmedAction::ReplaceSave(slot)) => {
+                actions.save_event.send(SaveFromMenuEvent {
+                    replace: Some(*slot),
+                });
+            }
+            PauseMenuButton::Back => {
+                *pause_menu = PauseMenu::Open {
+                    status: String::new(),
+                };
+            }
+        }
+    }
+}
+
+type MuteChatButtonQuery<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Interaction, &'static mut BackgroundColor),
+    (Changed<Interaction>, With<MuteChatButton>),
+>;
+
+fn mute_chat_button(
+    mut q_button: MuteChatButtonQuery,
+    mut pause_menu: ResMut<PauseMenu>,
+    mut chat_options: ResMut<ChatOptions>,
+) {
+    for (interaction, mut background_color) in &mut q_button {
+        *background_color = button_interaction_color(*interaction);
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+
+        chat_options.muted = !chat_options.muted;
+
+        // shows the new value
+        pause_menu.set_changed();
+    }
+}
+
+fn save_from_menu(
+    mut event: EventReader<SaveFromMenuEvent>,
+    current_game: CurrentGame,
+    mut save_slot: ResMut<SaveSlot>,
+    mut pause_menu: ResMut<PauseMenu>,
+) {
+    let Some(replace) = event.read().last().map(|ev| ev.replace) else {
+        return;
+    };
+
+    if replace.is_some() {
+        save_slot.0 = replace;
+    }
+
+    let status = match current_game.saved_game() {
+        Some(saved_game) => match save_in_slot(&saved_game, &mut save_slot) {
+            Ok(Some(slot)) => format!("Saved in slot {}", slot + 1),
+            Ok(None) => {
+                *pause_menu = PauseMenu::Confirm(ConfirmedAction::ReplaceSave(oldest_slot()));
+                return;
+            }
+            Err(err) => format!("Failed to save the game: {}", err),
+        },
+        None => "There is nothing to save before the first move".to_string(),
+    };
+
+    *pause_menu = PauseMenu::Open { status };
+}
+
+/// Stops the clocks and the players while the menu is open, games against remote players go on.
+fn pause_game(
+    pause_menu: Res<PauseMenu>,
+    players: Res<Players>,
+    tafl_state: Res<State<TaflState>>,
+    mut next_tafl_state: ResMut<NextState<TaflState>>,
+) {
+    if !pause_menu.is_changed() {
+        return;
+    }
+
+    match (*pause_menu == PauseMenu::Closed, tafl_state.get()) {
+        (false, TaflState::Playing) if is_local(&players) => {
+            next_tafl_state.set(TaflState::Menu);
+        }
+        (true, TaflState::Menu) => next_tafl_state.set(TaflState::Playing),
+        _ => (),
+    }
+}
+
+fn update_pause_menu_ui(
+    pause_menu: Res<PauseMenu>,
+    q_pause_menu_ui: Query<Entity, With<PauseMenuUi>>,
+    players: Res<Players>,
+    tafl_state: Res<State<TaflState>>,
+    options: ShownOptions,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    if !pause_menu.is_changed() {
+        return;
+    }
+
+    for pause_menu_ui_entity in &q_pause_menu_ui {
+        commands.entity(pause_menu_ui_entity).despawn_recursive();
+    }
+
+    if *pause_menu == PauseMenu::Closed {
+        return;
+    }
+
+    let is_local = is_local(&players);
+    let is_over = *tafl_state.get() == TaflState::Ended;
+
+    commands
+        .spawn((
+            PauseMenuUi,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    position_type: PositionType::Absolute,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.7).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            let title = match *pause_menu {
+                PauseMenu::Settings => "Settings".to_string(),
+                PauseMenu::Confirm(ConfirmedAction::Resign) => "Resign the game?".to_string(),
+                PauseMenu::Confirm(ConfirmedAction::Quit) => "Quit to the menu?".to_string(),
+                PauseMenu::Confirm(ConfirmedAction::ReplaceSave(slot)) => {
+                    format!("Replace the game in slot {}?", slot + 1)
+                }
+                _ if is_over => "Game over".to_string(),
+                _ => "Paused".to_string(),
+            };
+
+            parent.spawn(TextBundle::from_section(
+                title,
+                text_style(&asset_server, 50.),
+            ));
+
+            let status = match &*pause_menu {
+                PauseMenu::Open { status } if status.is_empty() && !is_local => {
+                    "The game goes on while the menu is open"
+                }
+                PauseMenu::Open { status } => status.as_str(),
+                PauseMenu::Confirm(ConfirmedAction::Quit) if is_local => {
+                    "Unfinished games are saved and can be continued later"
+                }
+                PauseMenu::Confirm(ConfirmedAction::Quit) => "The game will be abandoned",
+                PauseMenu::Confirm(ConfirmedAction::ReplaceSave(_)) => {
+                    "All save slots are taken, the game saved first would be lost"
+                }
+                _ => "",
+            };
+
+            parent.spawn(
+                TextBundle::from_section(status, text_style(&asset_server, 24.)).with_style(
+                    Style {
+                        margin: UiRect::vertical(Val::Px(10.)),
+                        ..default()
+                    },
+                ),
+            );
+
+            match *pause_menu {
+                PauseMenu::Closed => (),
+                PauseMenu::Open { .. } => {
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Resume,
+                        "Resume",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+
+                    if is_local && !is_over {
+                        spawn_button(
+                            parent,
+                            PauseMenuButton::Save,
+                            "Save",
+                            PAUSE_MENU_BUTTON,
+                            &asset_server,
+                        );
+                    }
+
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Settings,
+                        "Settings",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+
+                    let can_resign =
+                        players.is_human(Side::Attacker) || players.is_human(Side::Defender);
+                    if is_local && !is_over && can_resign {
+                        spawn_button(
+                            parent,
+                            PauseMenuButton::Resign,
+                            "Resign",
+                            PAUSE_MENU_BUTTON,
+                            &asset_server,
+                        );
+                    }
+
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Quit,
+                        "Quit to menu",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+                }
+                PauseMenu::Settings => {
+                    spawn_setting_buttons(parent, &options.settings, &asset_server);
+
+                    let chat = match options.chat_options.muted {
+                        true => "Chat: muted",
+                        false => "Chat: shown",
+                    };
+                    spawn_button(
+                        parent,
+                        MuteChatButton,
+                        chat,
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Back,
+                        "Back",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+                }
+                PauseMenu::Confirm(action) => {
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Confirm(action),
+                        "Yes",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+                    spawn_button(
+                        parent,
+                        PauseMenuButton::Back,
+                        "No",
+                        PAUSE_MENU_BUTTON,
+                        &asset_server,
+                    );
+                }
+            }
+        });
+}
+
+fn close_pause_menu(
+    mut pause_menu: ResMut<PauseMenu>,
+    q_pause_menu_ui: Query<Entity, With<PauseMenuUi>>,
+    mut commands: Commands,
+) {
+    *pause_menu = PauseMenu::Closed;
+
+    for pause_menu_ui_entity in &q_pause_menu_ui {
+        commands.entity(pause_menu_ui_entity).despawn_recursive();
+    }
+}
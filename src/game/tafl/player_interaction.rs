@@ -21,6 +21,22 @@ pub enum SelectedFigure {
 #[derive(Component)]
 pub struct SelectionIndicator;
 
+/// Keeps the selection indicator hidden while it is turned off.
+pub fn hide_selection_indicator(
+    highlight_options: Res<HighlightOptions>,
+    mut q_selection_indicator: Query<&mut Visibility, With<SelectionIndicator>>,
+) {
+    if highlight_options.selection {
+        return;
+    }
+
+    for mut visibility in &mut q_selection_indicator {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
 pub fn despawn_selection_indicator(
     q_selection_indicator: Query<Entity, With<SelectionIndicator>>,
     mut commands: Commands,
@@ -37,14 +53,14 @@ pub struct Grabbed {
 /// Selects the figure at the mouse position.
 pub fn on_mouse_pressed(
     selection_options: Res<SelectionOptions>,
+    players: Res<Players>,
     buttons: Res<ButtonInput<MouseButton>>,
     q_mouse_position: Query<&MousePositionTracker, With<MainCamera>>,
-    mut selected_figure: ResMut<SelectedFigure>,
-    q_board: Query<(Entity, &Board, &Transform, &TurnTracker), Without<SelectionIndicator>>,
+    selected_figure: Res<SelectedFigure>,
+    q_board: Query<(Entity, &Board, &Transform, &TurnTracker)>,
     q_figure: Query<&Figure>,
-    mut q_selection_indicator: Query<(&mut Transform, &mut Visibility), With<SelectionIndicator>>,
     mut commands: Commands,
-    mut spawn_highlights_event: EventWriter<SpawnHighlightsEvent>,
+    mut select_figure_event: EventWriter<SelectFigureEvent>,
 ) {
     if selection_options.selection_locked {
         return;
@@ -91,6 +107,11 @@ pub fn on_mouse_pressed(
             return;
         }
 
+        if !players.is_human(figure.side) {
+            // the figures of the side are moved by someone else, spectators move neither side
+            return;
+        }
+
         commands.entity(selected_figure_entity).insert(Grabbed {
             z: board.figure_z + 1.,
         });
@@ -101,9 +122,39 @@ pub fn on_mouse_pressed(
             }
         }
 
-        *selected_figure = SelectedFigure::Some {
+        select_figure_event.send(SelectFigureEvent {
             board_entity,
             figure_entity: selected_figure_entity,
+        });
+    }
+}
+
+/// Selects a figure, either with the mouse or the keyboard.
+#[derive(Event)]
+pub struct SelectFigureEvent {
+    pub board_entity: Entity,
+    pub figure_entity: Entity,
+}
+
+/// Shows the selection indicator on the selected figure and highlights its possible moves.
+pub fn select_figure(
+    mut event: EventReader<SelectFigureEvent>,
+    mut selected_figure: ResMut<SelectedFigure>,
+    q_board: Query<&Board>,
+    q_figure: Query<&Figure>,
+    mut q_selection_indicator: Query<(&mut Transform, &mut Visibility), With<SelectionIndicator>>,
+    mut spawn_highlights_event: EventWriter<SpawnHighlightsEvent>,
+) {
+    for ev in event.read() {
+        let (Ok(board), Ok(figure)) =
+            (q_board.get(ev.board_entity), q_figure.get(ev.figure_entity))
+        else {
+            continue;
+        };
+
+        *selected_figure = SelectedFigure::Some {
+            board_entity: ev.board_entity,
+            figure_entity: ev.figure_entity,
             was_put_down_once: false,
         };
 
@@ -111,12 +162,12 @@ pub fn on_mouse_pressed(
         {
             let (mut transform, mut visibility) = q_selection_indicator.get_single_mut().unwrap();
             let z = transform.translation.z;
-            transform.translation = board.board_to_world(selected_field).extend(z);
+            transform.translation = board.board_to_world(figure.position).extend(z);
             *visibility = Visibility::Inherited;
         }
 
         spawn_highlights_event.send(SpawnHighlightsEvent {
-            board_entity,
+            board_entity: ev.board_entity,
             positions: possible_moves(board, *figure),
         });
     }
@@ -245,3 +296,30 @@ pub fn release_selected_figure(
         });
     }
 }
+
+/// Lets go of the selected figure without moving it, e.g. when the game is paused in the middle
+/// of a move.
+pub fn drop_selection(
+    mut selected_figure: ResMut<SelectedFigure>,
+    mut q_selection_indicator: Query<&mut Visibility, With<SelectionIndicator>>,
+    q_board: Query<(Entity, &Board)>,
+    mut q_grabbed: Query<(Entity, &Figure, &mut Transform), With<Grabbed>>,
+    mut commands: Commands,
+    mut despawn_highlights_event: EventWriter<DespawnHighlightsEvent>,
+) {
+    *selected_figure = SelectedFigure::None;
+
+    for mut visibility in &mut q_selection_indicator {
+        *visibility = Visibility::Hidden;
+    }
+
+    for (board_entity, board) in &q_board {
+        for (figure_entity, figure, mut figure_transform) in &mut q_grabbed {
+            figure_transform.translation =
+                board.board_to_world(figure.position).extend(board.figure_z);
+            commands.entity(figure_entity).remove::<Grabbed>();
+        }
+
+        despawn_highlights_event.send(DespawnHighlightsEvent { board_entity });
+    }
+}
@@ -0,0 +1,56 @@
+use crate::game::tafl::engine_protocol::EngineCommand;
+use crate::game::tafl::*;
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum PlayerKind {
+    /// Plays by interacting with the board.
+    #[default]
+    Human,
+    /// An engine started as a child process with `command`, see `engine_protocol`.
+    ExternalEngine { command: EngineCommand },
+    /// A player connected over the network, see `network` and `online`.
+    Remote,
+}
+
+impl PlayerKind {
+    /// The built-in AI, played by starting this executable as an engine with `--engine`.
+    pub fn built_in_ai() -> Result<Self, String> {
+        let executable = std::env::current_exe()
+            .map_err(|err| format!("failed to find the executable for the ai: {}", err))?;
+
+        Ok(PlayerKind::ExternalEngine {
+            command: EngineCommand {
+                program: executable.to_string_lossy().into_owned(),
+                args: vec!["--engine".to_string()],
+            },
+        })
+    }
+}
+
+/// Determines who makes the moves of each side.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Players {
+    pub attacker: PlayerKind,
+    pub defender: PlayerKind,
+}
+
+impl Players {
+    pub fn get(&self, side: Side) -> &PlayerKind {
+        match side {
+            Side::Attacker => &self.attacker,
+            Side::Defender => &self.defender,
+        }
+    }
+
+    pub fn set(&mut self, side: Side, player_kind: PlayerKind) {
+        match side {
+            Side::Attacker => self.attacker = player_kind,
+            Side::Defender => self.defender = player_kind,
+        }
+    }
+
+    /// Determines whether the figures of `side` may be moved through the board.
+    pub fn is_human(&self, side: Side) -> bool {
+        *self.get(side) == PlayerKind::Human
+    }
+}
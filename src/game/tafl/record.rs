@@ -0,0 +1,486 @@
+use bevy::utils::Duration;
+
+use crate::game::tafl::chat::ChatMessageEvent;
+use crate::game::tafl::game_setup::GameSetup;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::{Move, HNEFATAFL_POSITION};
+use crate::game::tafl::*;
+
+pub struct RecordPlugin;
+
+impl Plugin for RecordPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), start_record)
+            .add_systems(
+                Update,
+                (record_moves.after(move_figure), record_chat_messages)
+                    .run_if(in_state(GameState::InGame)),
+            )
+            .init_resource::<GameRecord>();
+    }
+}
+
+/// Everything that happened in the current game, in the order it happened.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct GameRecord {
+    pub variant: String,
+    pub initial_position: String,
+    pub first_side: Side,
+    pub entries: Vec<RecordEntry>,
+    // when the game started according to `Time`
+    started: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RecordEntry {
+    Move {
+        side: Side,
+        mv: Move,
+        /// How long after the start of the game the move was made.
+        time: Duration,
+    },
+    Chat {
+        side: Side,
+        text: String,
+        /// How long after the start of the game the message was written.
+        time: Duration,
+    },
+}
+
+impl GameRecord {
+    /// Continues the record of a game started earlier, `now` is the current `Time::elapsed`.
+    pub fn resume(&mut self, entries: Vec<RecordEntry>, now: Duration) {
+        let last = entries.last().map(RecordEntry::time).unwrap_or_default();
+
+        self.entries = entries;
+        self.started = now.saturating_sub(last);
+    }
+
+    /// Converts the record to the one of a record file.
+    pub fn to_file(&self, time_control: &TimeControl, outcome: Option<GameOutcome>) -> RecordFile {
+        RecordFile {
+            variant: self.variant.clone(),
+            time_control: time_control.clone(),
+            initial_position: self.initial_position.clone(),
+            first_side: self.first_side,
+            entries: self.entries.clone(),
+            outcome,
+        }
+    }
+}
+
+impl RecordEntry {
+    pub fn time(&self) -> Duration {
+        match self {
+            RecordEntry::Move { time, .. } | RecordEntry::Chat { time, .. } => *time,
+        }
+    }
+}
+
+// A record file stores a game line by line:
+//
+// - `variant <name>` - the variant the game was played in
+// - `time <ms|none>` - the time on the clock of each side at the start of the game, none for games
+//   without a clock
+// - `increment <ms>`, `delay <ms>` or `byo-yomi <periods> <ms>` - the bonus of the time control
+// - `move-limit <ms>` - the longest a move could take
+//
+// The time, the bonus and the move limit together are the notation of the `time_control` module,
+// split on lines of their own.
+// - `position <position>` - the position the game started from
+// - `first <attackers|defenders>` - the side that made the first move, the attackers if missing
+// - `move <attackers|defenders> <from>-<to> <ms>` - a move and how long after the start it was made
+// - `chat <attackers|defenders> <ms> <text>` - a chat message and when it was written
+// - `result <attackers|defenders|draw>` - the winner of the game, missing if it wasn't finished
+//
+// The variant, the time control, the position and the first side come before everything else.
+// Empty lines and lines starting with `#` are ignored.
+
+const HEADER_COMMANDS: &[&str] = &[
+    "variant",
+    "time",
+    "increment",
+    "delay",
+    "byo-yomi",
+    "move-limit",
+    "position",
+    "first",
+];
+
+/// A game stored in a record file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordFile {
+    pub variant: String,
+    pub time_control: TimeControl,
+    pub initial_position: String,
+    pub first_side: Side,
+    pub entries: Vec<RecordEntry>,
+    pub outcome: Option<GameOutcome>,
+}
+
+impl RecordFile {
+    /// Converts the game to the contents of a record file.
+    pub fn to_text(&self) -> String {
+        let rows = parse_position(&self.initial_position)
+            .map(|parsed| parsed.rows)
+            .unwrap_or_default();
+
+        let mut lines = vec![format!("variant {}", self.variant)];
+
+        let mut time_control = format_time_control(&self.time_control).into_iter();
+        if let Some(time) = time_control.next() {
+            lines.push(format!("time {}", time));
+        }
+        lines.extend(time_control);
+
+        lines.push(format!("position {}", self.initial_position));
+        lines.push(format!("first {}", format_side(self.first_side)));
+
+        for entry in &self.entries {
+            lines.push(match entry {
+                RecordEntry::Move { side, mv, time } => format!(
+                    "move {} {} {}",
+                    format_side(*side),
+                    format_move(mv.from, mv.to, rows),
+                    time.as_millis()
+                ),
+                RecordEntry::Chat { side, text, time } => {
+                    format!("chat {} {} {}", format_side(*side), time.as_millis(), text)
+                }
+            });
+        }
+
+        match self.outcome {
+            Some(GameOutcome::Win(side)) => lines.push(format!("result {}", format_side(side))),
+            Some(GameOutcome::Draw) => lines.push("result draw".to_string()),
+            None => (),
+        }
+
+        lines.join("\n") + "\n"
+    }
+
+    /// Parses the contents of a record file.
+    pub fn parse(data: &str) -> Result<Self, &'static str> {
+        let mut variant = None;
+        // the lines of the time control, parsed together once all of them are known
+        let mut time_control: Option<Vec<&str>> = None;
+        let mut initial_position: Option<(String, usize, usize)> = None;
+        let mut first_side = Side::Attacker;
+        let mut entries = vec![];
+        let mut outcome = None;
+
+        for line in data.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+
+            let header_complete =
+                variant.is_some() && time_control.is_some() && initial_position.is_some();
+            if !HEADER_COMMANDS.contains(&command) && !header_complete {
+                return Err("the variant, the time and the position should come first");
+            }
+
+            match command {
+                "variant" => variant = Some(arguments.to_string()),
+                "time" => time_control = Some(vec![arguments]),
+                "increment" | "delay" | "byo-yomi" | "move-limit" => {
+                    let Some(time_control) = &mut time_control else {
+                        return Err("the time should come before the rest of the time control");
+                    };
+
+                    time_control.push(line);
+                }
+                "position" => {
+                    let parsed = parse_position(arguments)?;
+                    initial_position = Some((arguments.to_string(), parsed.rows, parsed.cols));
+                }
+                "first" => first_side = parse_side(arguments)?,
+                "move" => {
+                    let (_, rows, cols) = initial_position.as_ref().unwrap();
+                    let mut tokens = arguments.split_whitespace();
+                    let side = parse_side(tokens.next().unwrap_or(""))?;
+                    let (from, to) = parse_move(tokens.next().unwrap_or(""), *rows, *cols)?;
+
+                    entries.push(RecordEntry::Move {
+                        side,
+                        mv: Move { from, to },
+                        time: parse_millis(tokens.next())?,
+                    });
+                }
+                "chat" => {
+                    let mut tokens = arguments.splitn(3, ' ');
+                    let side = parse_side(tokens.next().unwrap_or(""))?;
+                    let time = parse_millis(tokens.next())?;
+
+                    entries.push(RecordEntry::Chat {
+                        side,
+                        text: tokens.next().unwrap_or("").to_string(),
+                        time,
+                    });
+                }
+                "result" => {
+                    outcome = Some(match arguments {
+                        "draw" => GameOutcome::Draw,
+                        side => GameOutcome::Win(parse_side(side)?),
+                    });
+                }
+                _ => return Err("unknown line in the record"),
+            }
+        }
+
+        let (Some(variant), Some(time_control), Some((initial_position, _, _))) =
+            (variant, time_control, initial_position)
+        else {
+            return Err("a record should contain the variant, the time and the position");
+        };
+
+        let time_control =
+            parse_time_control(time_control.iter().flat_map(|line| line.split_whitespace()))?;
+
+        Ok(RecordFile {
+            variant,
+            time_control,
+            initial_position,
+            first_side,
+            entries,
+            outcome,
+        })
+    }
+}
+
+fn start_record(
+    game_setup: Option<Res<GameSetup>>,
+    mut record: ResMut<GameRecord>,
+    time: Res<Time>,
+) {
+    // games that aren't set up are the ones spawned by `spawn_hnefatafl`
+    let (variant, initial_position, first_side) = match game_setup {
+        Some(game_setup) => (
+            game_setup.variant.clone(),
+            game_setup.initial_position.clone(),
+            game_setup.first_side,
+        ),
+        None => (
+            "hnefatafl".to_string(),
+            HNEFATAFL_POSITION.to_string(),
+            Side::Attacker,
+        ),
+    };
+
+    *record = GameRecord {
+        variant,
+        initial_position,
+        first_side,
+        entries: vec![],
+        started: time.elapsed(),
+    };
+}
+
+fn record_moves(
+    mut event: EventReader<FigureMovedEvent>,
+    mut record: ResMut<GameRecord>,
+    time: Res<Time>,
+) {
+    for ev in event.read() {
+        let time = time.elapsed().saturating_sub(record.started);
+
+        record.entries.push(RecordEntry::Move {
+            side: ev.side,
+            mv: Move {
+                from: ev.from,
+                to: ev.to,
+            },
+            time,
+        });
+    }
+}
+
+fn record_chat_messages(
+    mut event: EventReader<ChatMessageEvent>,
+    mut record: ResMut<GameRecord>,
+    time: Res<Time>,
+) {
+    for ev in event.read() {
+        let time = time.elapsed().saturating_sub(record.started);
+
+        record.entries.push(RecordEntry::Chat {
+            side: ev.side,
+            text: ev.text.clone(),
+            time,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(time_control: TimeControl) -> RecordFile {
+        let (from, to) = parse_move("a4-c4", 11, 11).unwrap();
+
+        RecordFile {
+            variant: "hnefatafl".to_string(),
+            time_control,
+            initial_position: HNEFATAFL_POSITION.to_string(),
+            first_side: Side::Defender,
+            entries: vec![
+                RecordEntry::Chat {
+                    side: Side::Defender,
+                    text: "good luck, have fun".to_string(),
+                    time: Duration::from_millis(1500),
+                },
+                RecordEntry::Move {
+                    side: Side::Attacker,
+                    mv: Move { from, to },
+                    time: Duration::from_millis(4200),
+                },
+            ],
+            outcome: Some(GameOutcome::Win(Side::Attacker)),
+        }
+    }
+
+    fn assert_round_trip(record: RecordFile) {
+        assert_eq!(RecordFile::parse(&record.to_text()), Ok(record));
+    }
+
+    #[test]
+    fn records_round_trip() {
+        assert_round_trip(record(TimeControl::default()));
+    }
+
+    #[test]
+    fn records_without_a_clock_round_trip() {
+        let record = record(TimeControl {
+            unlimited: true,
+            ..default()
+        });
+
+        assert!(record.to_text().contains("time none\n"));
+        assert_round_trip(record);
+    }
+
+    #[test]
+    fn time_bonuses_round_trip() {
+        for bonus in [
+            TimeBonus::Increment(Duration::from_secs(5)),
+            TimeBonus::Delay(Duration::from_secs(3)),
+            TimeBonus::ByoYomi {
+                periods: 3,
+                period: Duration::from_secs(30),
+            },
+        ] {
+            assert_round_trip(record(TimeControl { bonus, ..default() }));
+        }
+    }
+
+    #[test]
+    fn move_limits_round_trip() {
+        assert_round_trip(record(TimeControl {
+            move_limit: Some(Duration::from_secs(60)),
+            ..default()
+        }));
+    }
+
+    #[test]
+    fn draws_round_trip() {
+        assert_round_trip(RecordFile {
+            outcome: Some(GameOutcome::Draw),
+            ..record(TimeControl::default())
+        });
+    }
+
+    #[test]
+    fn unfinished_games_round_trip() {
+        let record = RecordFile {
+            outcome: None,
+            ..record(TimeControl::default())
+        };
+
+        assert!(!record.to_text().contains("result"));
+        assert_round_trip(record);
+    }
+
+    #[test]
+    fn lines_are_parsed() {
+        let data = format!(
+            "\
+# a comment
+variant hnefatafl
+time 300000
+byo-yomi 2 10000
+move-limit 20000
+position {}
+first defenders
+
+chat defenders 1000 hello there
+move defenders e5-e2 2500
+result draw
+",
+            HNEFATAFL_POSITION
+        );
+
+        let record = RecordFile::parse(&data).unwrap();
+
+        assert_eq!(
+            record.time_control,
+            TimeControl {
+                time: Duration::from_secs(300),
+                bonus: TimeBonus::ByoYomi {
+                    periods: 2,
+                    period: Duration::from_secs(10),
+                },
+                move_limit: Some(Duration::from_secs(20)),
+                unlimited: false,
+            }
+        );
+        assert_eq!(record.first_side, Side::Defender);
+        assert_eq!(
+            record.entries[0],
+            RecordEntry::Chat {
+                side: Side::Defender,
+                text: "hello there".to_string(),
+                time: Duration::from_secs(1),
+            }
+        );
+        assert!(matches!(
+            record.entries[1],
+            RecordEntry::Move { side: Side::Defender, time, .. } if time == Duration::from_millis(2500)
+        ));
+        assert_eq!(record.outcome, Some(GameOutcome::Draw));
+    }
+
+    #[test]
+    fn the_first_side_defaults_to_the_attackers() {
+        let data = format!("variant hnefatafl\ntime none\nposition {}\n", HNEFATAFL_POSITION);
+
+        assert_eq!(RecordFile::parse(&data).unwrap().first_side, Side::Attacker);
+    }
+
+    #[test]
+    fn moves_before_the_header_are_rejected() {
+        let data = format!(
+            "variant hnefatafl\nmove attackers a4-c4 1000\ntime 600000\nposition {}\n",
+            HNEFATAFL_POSITION
+        );
+
+        assert!(RecordFile::parse(&data).is_err());
+    }
+
+    #[test]
+    fn bonuses_before_the_time_are_rejected() {
+        let data = format!(
+            "variant hnefatafl\nincrement 5000\ntime 600000\nposition {}\n",
+            HNEFATAFL_POSITION
+        );
+
+        assert!(RecordFile::parse(&data).is_err());
+    }
+
+    #[test]
+    fn records_without_a_position_are_rejected() {
+        assert!(RecordFile::parse("variant hnefatafl\ntime 600000\n").is_err());
+    }
+}
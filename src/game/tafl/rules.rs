@@ -0,0 +1,816 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use crate::game::tafl::*;
+
+// The rules of the game, without needing a world to run in. They are used wherever a game has to
+// be played without a window, e.g. by engines, and by the systems of the move pipeline (moving,
+// capturing and win_conditions), which build a `TaflGame` from the board to apply them.
+
+/// Determines what happens when the side to move has no figure that can be moved.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum NoLegalMovesOutcome {
+    /// The side that can't move loses.
+    #[default]
+    Loss,
+    /// The game ends in a draw.
+    Draw,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameOutcome {
+    Win(Side),
+    Draw,
+}
+
+/// The board and rule options a game is played with.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    pub rows: usize,
+    pub cols: usize,
+    pub throne_position: Position,
+    pub end_positions: Vec<Position>,
+    pub no_legal_moves: NoLegalMovesOutcome,
+}
+
+/// The position the 11x11 game of hnefatafl starts from, see `notation::parse_position`.
+pub const HNEFATAFL_POSITION: &str =
+    "/3ttttt3/5t5/11/t4T4t/t3TTT3t/tt1TTKTT1tt/t3TTT3t/t4T4t/11/5t5/3ttttt3/";
+
+/// The names of the variants known by `variant`.
+pub const VARIANTS: &[&str] = &["hnefatafl"];
+
+/// Gets the rules and the initial position of the variant called `name`.
+pub fn variant(name: &str) -> Result<(Rules, &'static str), &'static str> {
+    match name {
+        "hnefatafl" => Ok((Rules::hnefatafl(), HNEFATAFL_POSITION)),
+        _ => Err("unknown variant"),
+    }
+}
+
+impl Rules {
+    /// The rules of the 11x11 game of hnefatafl, the board spawned by `spawn_hnefatafl` is built
+    /// from them.
+    pub fn hnefatafl() -> Self {
+        Self {
+            rows: 11,
+            cols: 11,
+            throne_position: Position { x: 5, y: 5 },
+            end_positions: vec![
+                Position { x: 0, y: 0 },
+                Position { x: 10, y: 0 },
+                Position { x: 0, y: 10 },
+                Position { x: 10, y: 10 },
+            ],
+            no_legal_moves: NoLegalMovesOutcome::Loss,
+        }
+    }
+
+    /// Gets the rules of the game played on `board`.
+    pub fn from_board(board: &Board) -> Self {
+        Self {
+            rows: board.rows,
+            cols: board.cols,
+            throne_position: board.throne_position,
+            end_positions: board.end_positions.clone(),
+            no_legal_moves: board.no_legal_moves,
+        }
+    }
+
+    /// Returns the positions a figure of `kind` at `from` may move to, `is_occupied` tells whether
+    /// there is a figure on a field.
+    pub fn possible_moves(
+        &self,
+        kind: FigureKind,
+        from: Position,
+        is_occupied: impl Fn(Position) -> bool,
+    ) -> Vec<Position> {
+        let mut result = vec![];
+
+        let directions: [(isize, isize); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+        for (dx, dy) in directions {
+            let mut x = from.x as isize + dx;
+            let mut y = from.y as isize + dy;
+
+            while 0 <= x && 0 <= y && x < self.cols as isize && y < self.rows as isize {
+                let position = Position {
+                    x: x as usize,
+                    y: y as usize,
+                };
+
+                if is_occupied(position) || !self.can_be_placed_on(kind, position) {
+                    break;
+                }
+
+                result.push(position);
+
+                x += dx;
+                y += dy;
+            }
+        }
+
+        result
+    }
+
+    /// Validates whether a figure may be placed on an empty field or not.
+    fn can_be_placed_on(&self, kind: FigureKind, position: Position) -> bool {
+        let is_king = kind == FigureKind::King;
+
+        let target_is_end_pos = self.end_positions.contains(&position);
+        let target_is_throne = self.throne_position == position;
+
+        (!target_is_end_pos && !target_is_throne) || is_king
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    pub from: Position,
+    pub to: Position,
+}
+
+/// A game of tafl played without the ECS.
+#[derive(Debug, Clone)]
+pub struct TaflGame {
+    rules: Arc<Rules>,
+    // the figure on each field of the board, indexed by `y * cols + x`
+    fields: Vec<Option<FigureType>>,
+    side_to_move: Side,
+    outcome: Option<GameOutcome>,
+}
+
+impl TaflGame {
+    /// Creates a new game with the provided figures on the board.
+    pub fn new(rules: Rules, figures: &[Figure], side_to_move: Side) -> Result<Self, &'static str> {
+        let mut fields = vec![None; rules.rows * rules.cols];
+
+        for figure in figures {
+            let position = figure.position;
+
+            if rules.cols <= position.x || rules.rows <= position.y {
+                return Err("the figure should be on the board");
+            }
+
+            let field = &mut fields[position.y * rules.cols + position.x];
+            if field.is_some() {
+                return Err("there should be at most one figure on a field");
+            }
+
+            *field = Some(FigureType {
+                side: figure.side,
+                kind: figure.kind,
+            });
+        }
+
+        Ok(Self {
+            rules: Arc::new(rules),
+            fields,
+            side_to_move,
+            outcome: None,
+        })
+    }
+
+    /// Creates a game with the figures on `board`, e.g. to apply the rules to a move made on it.
+    pub fn from_board(board: &Board, q_figure: &Query<&Figure>, side_to_move: Side) -> Self {
+        let figures: Vec<Figure> = board
+            .figures
+            .values()
+            .map(|figure_entity| *q_figure.get(*figure_entity).unwrap())
+            .collect();
+
+        Self::new(Rules::from_board(board), &figures, side_to_move)
+            .expect("the figures should be on distinct fields of the board")
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    pub fn side_to_move(&self) -> Side {
+        self.side_to_move
+    }
+
+    /// Returns the outcome of the game or None if it is still going on.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        self.outcome
+    }
+
+    fn is_on_board(&self, position: Position) -> bool {
+        position.x < self.rules.cols && position.y < self.rules.rows
+    }
+
+    /// Gets the figure at `position`, None if the field is empty or not on the board.
+    pub fn get(&self, position: Position) -> Option<FigureType> {
+        if !self.is_on_board(position) {
+            return None;
+        }
+
+        self.fields[position.y * self.rules.cols + position.x]
+    }
+
+    fn set(&mut self, position: Position, figure_type: Option<FigureType>) {
+        let cols = self.rules.cols;
+        self.fields[position.y * cols + position.x] = figure_type;
+    }
+
+    /// Iterates over the figures on the board.
+    pub fn iter_figures(&self) -> impl Iterator<Item = (Position, FigureType)> + '_ {
+        let cols = self.rules.cols;
+
+        self.fields
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, field)| {
+                field.map(|figure_type| {
+                    let position = Position {
+                        x: i % cols,
+                        y: i / cols,
+                    };
+
+                    (position, figure_type)
+                })
+            })
+    }
+
+    /// Returns the positions the figure at `from` may move to.
+    pub fn possible_moves(&self, from: Position) -> Vec<Position> {
+        let Some(figure_type) = self.get(from) else {
+            return vec![];
+        };
+
+        let is_occupied = |position| self.get(position).is_some();
+        self.rules
+            .possible_moves(figure_type.kind, from, is_occupied)
+    }
+
+    /// Returns the moves the side to move can make, or no moves if the game is over.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut result = vec![];
+
+        if self.outcome.is_some() {
+            return result;
+        }
+
+        for y in 0..self.rules.rows {
+            for x in 0..self.rules.cols {
+                let from = Position { x, y };
+
+                match self.get(from) {
+                    Some(figure_type) if figure_type.side == self.side_to_move => {
+                        for to in self.possible_moves(from) {
+                            result.push(Move { from, to });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        result
+    }
+
+    fn has_legal_moves(&self, side: Side) -> bool {
+        self.iter_figures().any(|(position, figure_type)| {
+            figure_type.side == side && !self.possible_moves(position).is_empty()
+        })
+    }
+
+    /// Returns how the game ends if the side to move can't move any of its figures, None if it
+    /// can.
+    pub fn no_legal_moves_outcome(&self) -> Option<GameOutcome> {
+        if self.has_legal_moves(self.side_to_move) {
+            return None;
+        }
+
+        Some(match self.rules.no_legal_moves {
+            NoLegalMovesOutcome::Loss => GameOutcome::Win(self.side_to_move.opponent()),
+            NoLegalMovesOutcome::Draw => GameOutcome::Draw,
+        })
+    }
+
+    /// Makes a move for the side to move.
+    ///
+    /// Returns the positions of the figures captured by the move.
+    pub fn make_move(&mut self, mv: Move) -> Result<Vec<Position>, &'static str> {
+        if self.outcome.is_some() {
+            return Err("the game should not be over");
+        }
+
+        let Some(figure_type) = self.get(mv.from) else {
+            return Err("there should be a figure to move");
+        };
+
+        if figure_type.side != self.side_to_move {
+            return Err("figure's side should be matching the turn");
+        }
+
+        if !self.possible_moves(mv.from).contains(&mv.to) {
+            return Err("the figure should be able to move to the position");
+        }
+
+        self.set(mv.from, None);
+        self.set(mv.to, Some(figure_type));
+
+        let mut outcome = None;
+
+        if figure_type.side == Side::Defender
+            && figure_type.kind == FigureKind::King
+            && self.all_kings_on_end_positions()
+        {
+            outcome = Some(GameOutcome::Win(Side::Defender));
+        }
+
+        let (captured, king_was_attacked) = self.capture_around(mv.to);
+
+        if outcome.is_none() && king_was_attacked && self.all_kings_surrounded() {
+            outcome = Some(GameOutcome::Win(Side::Attacker));
+        }
+
+        self.side_to_move = self.side_to_move.opponent();
+
+        if outcome.is_none() {
+            outcome = self.no_legal_moves_outcome();
+        }
+
+        self.outcome = outcome;
+
+        Ok(captured)
+    }
+
+    /// Returns the neighboring positions of `position` that are on the board.
+    fn neighbors(&self, position: Position) -> Vec<Position> {
+        let mut result = vec![];
+
+        if 1 <= position.x {
+            result.push(Position {
+                x: position.x - 1,
+                y: position.y,
+            });
+        }
+
+        if position.x + 1 < self.rules.cols {
+            result.push(Position {
+                x: position.x + 1,
+                y: position.y,
+            });
+        }
+
+        if 1 <= position.y {
+            result.push(Position {
+                x: position.x,
+                y: position.y - 1,
+            });
+        }
+
+        if position.y + 1 < self.rules.rows {
+            result.push(Position {
+                x: position.x,
+                y: position.y + 1,
+            });
+        }
+
+        result
+    }
+
+    /// Captures the enemies of the figure that moved to `moved_position`.
+    ///
+    /// Returns the captured positions and whether the king was next to the moved figure.
+    pub fn capture_around(&mut self, moved_position: Position) -> (Vec<Position>, bool) {
+        let moved_side = self.get(moved_position).unwrap().side;
+
+        let mut captured = vec![];
+        let mut king_was_attacked = false;
+
+        for position in self.neighbors(moved_position) {
+            let Some(figure_type) = self.get(position) else {
+                continue;
+            };
+
+            // only enemies can be captured
+            if figure_type.side == moved_side {
+                continue;
+            }
+
+            // the king can't be captured, but may be part of a shieldwall capture
+            if figure_type.kind == FigureKind::King {
+                captured.extend(self.shieldwall_capture(position));
+                king_was_attacked = true;
+                continue;
+            }
+
+            let x = 2 * position.x as isize - moved_position.x as isize;
+            let y = 2 * position.y as isize - moved_position.y as isize;
+
+            if x < 0 || y < 0 {
+                continue;
+            }
+
+            let other_position = Position {
+                x: x as usize,
+                y: y as usize,
+            };
+
+            if self.is_blocked(figure_type.side, other_position) {
+                self.set(position, None);
+                captured.push(position);
+            } else {
+                captured.extend(self.shieldwall_capture(position));
+            }
+        }
+
+        (captured, king_was_attacked)
+    }
+
+    /// Determines whether a `position` on the board is blocked for a figure of `side`.
+    fn is_blocked(&self, side: Side, position: Position) -> bool {
+        if !self.is_on_board(position) {
+            return false;
+        }
+
+        let other = self.get(position);
+
+        // contains enemy
+        if let Some(other_figure_type) = other {
+            if other_figure_type.side != side {
+                return true;
+            }
+        }
+
+        // is end position
+        if self.rules.end_positions.contains(&position) {
+            return true;
+        }
+
+        // is empty throne
+        self.rules.throne_position == position && other.is_none()
+    }
+
+    /// Performs a shieldwall capture starting from the figure at `initial_position`.
+    ///
+    /// Returns the captured positions.
+    fn shieldwall_capture(&mut self, initial_position: Position) -> Vec<Position> {
+        let mut captured = vec![];
+
+        for position in self.determine_shieldwall_capture(initial_position) {
+            // the king can't be captured
+            if self.get(position).unwrap().kind == FigureKind::King {
+                continue;
+            }
+
+            self.set(position, None);
+            captured.push(position);
+        }
+
+        captured
+    }
+
+    /// Determines the positions of the figures that are in a shieldwall capture with the figure
+    /// at `initial_position`.
+    fn determine_shieldwall_capture(&self, initial_position: Position) -> Vec<Position> {
+        let side = self.get(initial_position).unwrap().side;
+        let rows = self.rules.rows;
+        let cols = self.rules.cols;
+
+        // the inward offset from the edge the figure is on
+        let (inward_x, inward_y): (isize, isize) = if initial_position.x == 0 {
+            (1, 0)
+        } else if initial_position.x == cols - 1 {
+            (-1, 0)
+        } else if initial_position.y == 0 {
+            (0, 1)
+        } else if initial_position.y == rows - 1 {
+            (0, -1)
+        } else {
+            return vec![];
+        };
+
+        let mut to_check: VecDeque<Position> = VecDeque::new();
+        let mut result: Vec<Position> = vec![];
+
+        to_check.push_front(initial_position);
+
+        while let Some(position) = to_check.pop_back() {
+            if result.contains(&position) {
+                continue;
+            }
+
+            // the field in front of the figure has to be occupied by an enemy
+            let front_position = Position {
+                x: (position.x as isize + inward_x) as usize,
+                y: (position.y as isize + inward_y) as usize,
+            };
+
+            match self.get(front_position) {
+                Some(front) if front.side != side => {}
+                _ => return vec![],
+            }
+
+            // both ends of the "row" along the edge have to be closed
+            let along_edge: [(isize, isize); 2] = if inward_x != 0 {
+                [(0, -1), (0, 1)]
+            } else {
+                [(-1, 0), (1, 0)]
+            };
+
+            for (dx, dy) in along_edge {
+                let x = position.x as isize + dx;
+                let y = position.y as isize + dy;
+
+                if x < 0 || y < 0 {
+                    continue;
+                }
+
+                let neighbor_position = Position {
+                    x: x as usize,
+                    y: y as usize,
+                };
+
+                if !self.is_on_board(neighbor_position) {
+                    continue;
+                }
+
+                match self.get(neighbor_position) {
+                    Some(neighbor) => {
+                        if neighbor.side == side && !result.contains(&neighbor_position) {
+                            to_check.push_front(neighbor_position);
+                        }
+                    }
+                    None => {
+                        if !self.rules.end_positions.contains(&neighbor_position) {
+                            return vec![];
+                        }
+                    }
+                }
+            }
+
+            result.push(position);
+        }
+
+        result
+    }
+
+    /// Returns the positions of the defender kings.
+    pub fn king_positions(&self) -> Vec<Position> {
+        self.iter_figures()
+            .filter(|(_, figure_type)| {
+                figure_type.side == Side::Defender && figure_type.kind == FigureKind::King
+            })
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    pub fn all_kings_on_end_positions(&self) -> bool {
+        self.king_positions()
+            .iter()
+            .all(|position| self.rules.end_positions.contains(position))
+    }
+
+    /// Being next to a wall, end position or the throne also counts as being surrounded.
+    pub fn all_kings_surrounded(&self) -> bool {
+        self.king_positions().iter().all(|king_position| {
+            let walls = 4 - self.neighbors(*king_position).len();
+            let surrounded = self
+                .neighbors(*king_position)
+                .into_iter()
+                .filter(|position| match self.get(*position) {
+                    Some(figure_type) => figure_type.side == Side::Attacker,
+                    None => {
+                        self.rules.end_positions.contains(position)
+                            || self.rules.throne_position == *position
+                    }
+                })
+                .count();
+
+            walls + surrounded == 4
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tafl::notation::*;
+
+    // a 7x7 board with the throne in the center and the 4 corners as end positions
+    fn game(position: &str, side_to_move: Side, no_legal_moves: NoLegalMovesOutcome) -> TaflGame {
+        let parsed = parse_position(position).unwrap();
+        let rules = Rules {
+            rows: 7,
+            cols: 7,
+            throne_position: Position { x: 3, y: 3 },
+            end_positions: vec![
+                Position { x: 0, y: 0 },
+                Position { x: 6, y: 0 },
+                Position { x: 0, y: 6 },
+                Position { x: 6, y: 6 },
+            ],
+            no_legal_moves,
+        };
+
+        TaflGame::new(rules, &parsed.figures, side_to_move).unwrap()
+    }
+
+    fn square(data: &str) -> Position {
+        parse_square(data, 7, 7).unwrap()
+    }
+
+    fn make_move(game: &mut TaflGame, from: &str, to: &str) -> Vec<Position> {
+        let mut captured = game
+            .make_move(Move {
+                from: square(from),
+                to: square(to),
+            })
+            .unwrap();
+
+        captured.sort_by_key(|position| (position.y, position.x));
+        captured
+    }
+
+    #[test]
+    fn soldiers_are_captured_between_enemies() {
+        let mut game = game(
+            "/7/7/1tT1t2/7/3K3/7/7/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert_eq!(make_move(&mut game, "e5", "d5"), vec![square("c5")]);
+        assert_eq!(game.get(square("c5")), None);
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn soldiers_are_captured_against_the_empty_throne_and_corners() {
+        let mut game = game(
+            "/1T1t3/7/3T3/7/7/K6/2t4/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert_eq!(make_move(&mut game, "d7", "d6"), vec![square("d5")]);
+        make_move(&mut game, "a2", "a3");
+        assert_eq!(make_move(&mut game, "c1", "c7"), vec![square("b7")]);
+    }
+
+    #[test]
+    fn soldiers_are_not_captured_next_to_friends() {
+        let mut game = game(
+            "/7/7/1TT1t2/7/3K3/7/7/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert!(make_move(&mut game, "e5", "d5").is_empty());
+    }
+
+    #[test]
+    fn shieldwalls_are_captured() {
+        let mut game = game(
+            "/7/7/5K1/7/4t2/2tt3/1tTT3/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert_eq!(
+            make_move(&mut game, "e3", "e1"),
+            vec![square("c1"), square("d1")]
+        );
+    }
+
+    #[test]
+    fn shieldwalls_are_closed_by_corners() {
+        let mut game = game(
+            "/7/7/5K1/7/3t3/1tt4/1TT4/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert_eq!(
+            make_move(&mut game, "d3", "d1"),
+            vec![square("b1"), square("c1")]
+        );
+    }
+
+    #[test]
+    fn shieldwalls_need_enemies_in_front() {
+        let mut game = game(
+            "/7/7/5K1/7/4t2/2t4/1tTT3/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert!(make_move(&mut game, "e3", "e1").is_empty());
+    }
+
+    #[test]
+    fn the_king_is_not_captured_between_two_attackers() {
+        let mut game = game(
+            "/7/7/2tK3/7/7/4t2/7/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        assert!(make_move(&mut game, "e2", "e5").is_empty());
+        assert_eq!(game.get(square("d5")).unwrap().kind, FigureKind::King);
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn the_surrounded_king_is_captured() {
+        let mut game = game(
+            "/1t5/tKt4/7/7/7/1t5/7/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        make_move(&mut game, "b2", "b5");
+        assert_eq!(game.outcome(), Some(GameOutcome::Win(Side::Attacker)));
+        assert!(game.legal_moves().is_empty());
+    }
+
+    #[test]
+    fn the_throne_helps_to_surround_the_king() {
+        let mut game = game(
+            "/3t3/7/2tKt2/7/7/7/7/",
+            Side::Attacker,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        make_move(&mut game, "d7", "d6");
+        assert_eq!(game.outcome(), Some(GameOutcome::Win(Side::Attacker)));
+    }
+
+    #[test]
+    fn the_king_escapes_to_a_corner() {
+        let mut game = game(
+            "/7/7/7/K6/7/7/3t3/",
+            Side::Defender,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        make_move(&mut game, "a4", "a7");
+        assert_eq!(game.outcome(), Some(GameOutcome::Win(Side::Defender)));
+    }
+
+    #[test]
+    fn only_the_king_may_enter_the_throne_and_the_corners() {
+        let game = game(
+            "/7/7/7/1T3K1/7/7/7/",
+            Side::Defender,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        let soldier_moves = game.possible_moves(square("b4"));
+        assert!(soldier_moves.contains(&square("c4")));
+        assert!(!soldier_moves.contains(&square("d4")));
+
+        let king_moves = game.possible_moves(square("f4"));
+        assert!(king_moves.contains(&square("d4")));
+        assert!(!king_moves.contains(&square("b4")));
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut game = game(
+            "/7/7/7/1T3K1/7/7/3t3/",
+            Side::Defender,
+            NoLegalMovesOutcome::Loss,
+        );
+
+        let mut try_move = |from: &str, to: &str| {
+            game.make_move(Move {
+                from: square(from),
+                to: square(to),
+            })
+        };
+
+        // onto the throne, through a figure, a figure of the other side and a diagonal move
+        assert!(try_move("b4", "d4").is_err());
+        assert!(try_move("f4", "a4").is_err());
+        assert!(try_move("d1", "d2").is_err());
+        assert!(try_move("b4", "c5").is_err());
+    }
+
+    #[test]
+    fn no_legal_moves_ends_the_game() {
+        let position = "/7/Tt5/t6/7/7/7/5t1/";
+
+        let mut lost = game(position, Side::Attacker, NoLegalMovesOutcome::Loss);
+        make_move(&mut lost, "f1", "f2");
+        assert_eq!(lost.outcome(), Some(GameOutcome::Win(Side::Attacker)));
+
+        let mut drawn = game(position, Side::Attacker, NoLegalMovesOutcome::Draw);
+        make_move(&mut drawn, "f1", "f2");
+        assert_eq!(drawn.outcome(), Some(GameOutcome::Draw));
+    }
+
+    #[test]
+    fn oversized_positions_are_rejected() {
+        assert!(parse_position(&format!("/{}/", "9".repeat(20))).is_err());
+        assert!(parse_position("/27/").is_err());
+        assert!(parse_position(&format!("/{}/", "t".repeat(27))).is_err());
+        assert!(parse_position(&format!("{}/", "/1".repeat(27))).is_err());
+        assert!(parse_position(&format!("{}/", "/26".repeat(26))).is_ok());
+    }
+}
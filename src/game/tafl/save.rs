@@ -0,0 +1,558 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bevy::ecs::system::SystemParam;
+use bevy::utils::Duration;
+use bevy::window::WindowCloseRequested;
+
+use crate::game::tafl::engine_protocol::EngineCommand;
+use crate::game::tafl::game_setup::GameSetup;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::record::*;
+use crate::game::tafl::*;
+
+/// The number of games that can be saved at the same time.
+pub const SAVE_SLOTS: usize = 3;
+
+// how often the clocks are autosaved while nobody moves
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::InGame), take_save_slot)
+            .add_systems(OnExit(GameState::InGame), save_on_exit)
+            .add_systems(OnEnter(TaflState::Ended), clear_finished_autosave)
+            .add_systems(
+                Update,
+                autosave.after(move_figure).run_if(
+                    // the window can be closed while the game is paused as well
+                    in_state(GameState::InGame).and_then(not(in_state(TaflState::Ended))),
+                ),
+            )
+            .init_resource::<SaveSlot>();
+    }
+}
+
+/// The save slot of the current game, None until the game is saved for the first time.
+#[derive(Resource, Debug, Default)]
+pub struct SaveSlot(pub Option<usize>);
+
+// A save file is a record file followed by the state of the game the record doesn't hold:
+//
+// - `clock <attackers|defenders> <ms> <periods>` - the main time and the byo-yomi periods left
+// - `player <attackers|defenders> human` or `player <attackers|defenders> engine <command>` - who
+//   makes the moves of the side
+
+/// A game stored in a save slot.
+#[derive(Debug, Clone)]
+pub struct SavedGame {
+    pub record: RecordFile,
+    pub clocks: Vec<SavedClock>,
+    pub players: Players,
+}
+
+/// The time left on the clock of a side when the game was saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SavedClock {
+    pub side: Side,
+    pub remaining: Duration,
+    pub periods: u32,
+}
+
+impl SavedGame {
+    /// Converts the game to the contents of a save file.
+    pub fn to_text(&self) -> String {
+        let mut text = self.record.to_text();
+
+        for clock in &self.clocks {
+            text.push_str(&format!(
+                "clock {} {} {}\n",
+                format_side(clock.side),
+                clock.remaining.as_millis(),
+                clock.periods
+            ));
+        }
+
+        for side in [Side::Attacker, Side::Defender] {
+            let player = match self.players.get(side) {
+                PlayerKind::ExternalEngine { command } => format!("engine {}", command),
+                PlayerKind::Human | PlayerKind::Remote => "human".to_string(),
+            };
+
+            text.push_str(&format!("player {} {}\n", format_side(side), player));
+        }
+
+        text
+    }
+
+    /// Parses the contents of a save file.
+    pub fn parse(data: &str) -> Result<Self, &'static str> {
+        let mut record_lines = vec![];
+        let mut clocks = vec![];
+        let mut players = Players::default();
+
+        for line in data.lines() {
+            let line = line.trim();
+            let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+
+            match command {
+                "clock" => {
+                    let mut tokens = arguments.split_whitespace();
+                    let side = parse_side(tokens.next().unwrap_or(""))?;
+                    let remaining = parse_millis(tokens.next())?;
+                    let periods = tokens
+                        .next()
+                        .and_then(|periods| periods.parse::<u32>().ok())
+                        .ok_or("a clock should end with the byo-yomi periods left")?;
+
+                    if tokens.next().is_some() {
+                        return Err("a clock should end with the byo-yomi periods left");
+                    }
+
+                    clocks.push(SavedClock {
+                        side,
+                        remaining,
+                        periods,
+                    });
+                }
+                "player" => {
+                    let (side, player) = arguments.split_once(' ').unwrap_or((arguments, ""));
+                    let side = parse_side(side)?;
+
+                    let player = match player.split_once(' ') {
+                        Some(("engine", command)) => PlayerKind::ExternalEngine {
+                            command: EngineCommand::parse(command)?,
+                        },
+                        _ if player == "human" => PlayerKind::Human,
+                        _ => return Err("a player should be human or an engine"),
+                    };
+
+                    players.set(side, player);
+                }
+                _ => record_lines.push(line),
+            }
+        }
+
+        Ok(SavedGame {
+            record: RecordFile::parse(&record_lines.join("\n"))?,
+            clocks,
+            players,
+        })
+    }
+
+    /// Gets the number of moves made in the game.
+    pub fn moves(&self) -> usize {
+        self.record
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, RecordEntry::Move { .. }))
+            .count()
+    }
+
+    /// Gets the side to move, the sides take turns.
+    pub fn side_to_move(&self) -> Side {
+        match self.moves() % 2 {
+            0 => self.record.first_side,
+            _ => self.record.first_side.opponent(),
+        }
+    }
+
+    /// Sets up the game to continue it, `slot` is the slot it was loaded from.
+    pub fn game_setup(&self, slot: Option<usize>) -> Result<GameSetup, &'static str> {
+        let mut game_setup = GameSetup::new(
+            &self.record.variant,
+            Some(&self.record.initial_position),
+            self.record.first_side,
+            self.record.entries.clone(),
+            self.record.outcome,
+        )?;
+
+        game_setup.clocks = self.clocks.clone();
+        game_setup.slot = slot;
+
+        Ok(game_setup)
+    }
+}
+
+/// Gets the directory the files of the game are kept in, e.g. `~/.local/share/hnefatafl`.
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(dir).join("hnefatafl")
+    } else if let Some(dir) = std::env::var_os("APPDATA") {
+        PathBuf::from(dir).join("hnefatafl")
+    } else if let Some(home) = std::env::var_os("HOME") {
+        PathBuf::from(home).join(".local/share/hnefatafl")
+    } else {
+        PathBuf::from("hnefatafl")
+    }
+}
+
+fn slot_path(slot: usize) -> PathBuf {
+    data_dir()
+        .join("saves")
+        .join(format!("slot-{}.txt", slot + 1))
+}
+
+fn autosave_path() -> PathBuf {
+    data_dir().join("saves").join("autosave.txt")
+}
+
+// reads a save file and when it was written, None if it doesn't exist
+fn read_save(path: &Path) -> Result<Option<(SavedGame, SystemTime)>, String> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("failed to read {}: {}", path.display(), err)),
+    };
+
+    let saved_game = SavedGame::parse(&data)
+        .map_err(|err| format!("failed to load {}: {}", path.display(), err))?;
+
+    let saved_at = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    Ok(Some((saved_game, saved_at)))
+}
+
+fn write_save(path: &Path, saved_game: &SavedGame) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    // a crash while writing leaves the previous save in place
+    let temporary_path = path.with_extension("tmp");
+    fs::write(&temporary_path, saved_game.to_text())?;
+    fs::rename(temporary_path, path)
+}
+
+fn remove_save(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+/// Reads the game saved in `slot` and when it was saved, None if the slot is empty.
+pub fn read_slot(slot: usize) -> Result<Option<(SavedGame, SystemTime)>, String> {
+    read_save(&slot_path(slot))
+}
+
+pub fn write_slot(slot: usize, saved_game: &SavedGame) -> io::Result<()> {
+    write_save(&slot_path(slot), saved_game)
+}
+
+/// Removes the game saved in `slot`, if there is one.
+pub fn clear_slot(slot: usize) -> io::Result<()> {
+    remove_save(&slot_path(slot))
+}
+
+/// Reads the game that was being played when the game was closed or crashed the last time, None
+/// if it was left normally.
+pub fn read_autosave() -> Result<Option<(SavedGame, SystemTime)>, String> {
+    read_save(&autosave_path())
+}
+
+pub fn clear_autosave() -> io::Result<()> {
+    remove_save(&autosave_path())
+}
+
+// when each slot was saved, None for the empty ones
+fn saved_times() -> Vec<Option<SystemTime>> {
+    (0..SAVE_SLOTS)
+        .map(|slot| {
+            fs::metadata(slot_path(slot))
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .collect()
+}
+
+/// Gets the slot saved last, None if all of them are empty.
+pub fn latest_slot() -> Option<usize> {
+    saved_times()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(slot, saved_at)| Some((slot, saved_at?)))
+        .max_by_key(|(_, saved_at)| *saved_at)
+        .map(|(slot, _)| slot)
+}
+
+/// Gets the first empty slot, None if all of them are taken.
+fn free_slot() -> Option<usize> {
+    saved_times().iter().position(Option::is_none)
+}
+
+/// Gets the slot saved first, the one offered for replacing once all of them are taken.
+pub fn oldest_slot() -> usize {
+    let saved_times = saved_times();
+
+    (0..SAVE_SLOTS)
+        .min_by_key(|slot| saved_times[*slot].unwrap_or(SystemTime::UNIX_EPOCH))
+        .unwrap_or_default()
+}
+
+/// The state of the current game, as it is saved.
+#[derive(SystemParam)]
+pub struct CurrentGame<'w, 's> {
+    record: Res<'w, GameRecord>,
+    time_control: Res<'w, TimeControl>,
+    players: Res<'w, Players>,
+    q_game_timer: Query<'w, 's, &'static GameTimer>,
+}
+
+impl CurrentGame<'_, '_> {
+    /// Collects the state of the current game, None if it isn't worth continuing later.
+    pub fn saved_game(&self) -> Option<SavedGame> {
+        // games against other computers are kept by the host or the server
+        let is_remote = |side| *self.players.get(side) == PlayerKind::Remote;
+        if is_remote(Side::Attacker) || is_remote(Side::Defender) {
+            return None;
+        }
+
+        let has_moves = self
+            .record
+            .entries
+            .iter()
+            .any(|entry| matches!(entry, RecordEntry::Move { .. }));
+        if !has_moves {
+            return None;
+        }
+
+        Some(SavedGame {
+            record: self.record.to_file(&self.time_control, None),
+            clocks: self
+                .q_game_timer
+                .iter()
+                .map(|game_timer| SavedClock {
+                    side: game_timer.side,
+                    remaining: game_timer.clock.remaining(),
+                    periods: game_timer.clock.periods(),
+                })
+                .collect(),
+            players: self.players.clone(),
+        })
+    }
+}
+
+/// Saves the current game in its slot, taking a free one if it wasn't saved before.
+///
+/// Returns the slot, None if the game wasn't saved because the other games take all of them.
+pub fn save_in_slot(saved_game: &SavedGame, save_slot: &mut SaveSlot) -> io::Result<Option<usize>> {
+    let Some(slot) = save_slot.0.or_else(free_slot) else {
+        return Ok(None);
+    };
+
+    write_slot(slot, saved_game)?;
+    save_slot.0 = Some(slot);
+
+    Ok(Some(slot))
+}
+
+fn take_save_slot(game_setup: Option<Res<GameSetup>>, mut save_slot: ResMut<SaveSlot>) {
+    save_slot.0 = game_setup.and_then(|game_setup| game_setup.slot);
+}
+
+/// Saves the game after every move, every now and then and when the window is closed, so it can
+/// be restored after a crash. Finished games aren't saved.
+fn autosave(
+    mut figure_moved_event: EventReader<FigureMovedEvent>,
+    mut window_close_requested_event: EventReader<WindowCloseRequested>,
+    current_game: CurrentGame,
+    time: Res<Time>,
+    mut since_autosave: Local<Duration>,
+) {
+    *since_autosave += time.delta();
+
+    let moved = figure_moved_event.read().count() != 0;
+    let closing = window_close_requested_event.read().count() != 0;
+
+    if !moved && !closing && *since_autosave < AUTOSAVE_INTERVAL {
+        return;
+    }
+
+    *since_autosave = Duration::ZERO;
+
+    if let Some(saved_game) = current_game.saved_game() {
+        if let Err(err) = write_save(&autosave_path(), &saved_game) {
+            warn!("failed to autosave the game: {}", err);
+        }
+    }
+}
+
+// finished games can't be continued
+fn clear_finished_autosave() {
+    if let Err(err) = clear_autosave() {
+        warn!("failed to clear the autosave: {}", err);
+    }
+}
+
+/// Saves the game when it is left before it ended, so it can be continued later.
+fn save_on_exit(
+    current_game: CurrentGame,
+    tafl_state: Res<State<TaflState>>,
+    mut save_slot: ResMut<SaveSlot>,
+) {
+    // the game is either saved in its slot or over, there is nothing left to restore
+    if let Err(err) = clear_autosave() {
+        warn!("failed to clear the autosave: {}", err);
+    }
+
+    // finished games can't be continued
+    if *tafl_state.get() == TaflState::Ended {
+        if let Some(slot) = save_slot.0.take() {
+            if let Err(err) = clear_slot(slot) {
+                warn!("failed to clear save slot {}: {}", slot + 1, err);
+            }
+        }
+
+        return;
+    }
+
+    let Some(saved_game) = current_game.saved_game() else {
+        return;
+    };
+
+    match save_in_slot(&saved_game, &mut save_slot) {
+        Ok(Some(_)) => (),
+        // other games are never replaced without asking, the game can still be restored from the
+        // main menu
+        Ok(None) => {
+            warn!("all save slots are taken, the game is kept as the autosave");
+
+            if let Err(err) = write_save(&autosave_path(), &saved_game) {
+                warn!("failed to autosave the game: {}", err);
+            }
+        }
+        Err(err) => warn!("failed to save the game: {}", err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tafl::rules::{Move, HNEFATAFL_POSITION};
+
+    fn entry(side: Side, mv: &str, secs: u64) -> RecordEntry {
+        let (from, to) = parse_move(mv, 11, 11).unwrap();
+
+        RecordEntry::Move {
+            side,
+            mv: Move { from, to },
+            time: Duration::from_secs(secs),
+        }
+    }
+
+    fn saved_game() -> SavedGame {
+        let mut players = Players::default();
+        players.set(
+            Side::Attacker,
+            PlayerKind::ExternalEngine {
+                command: EngineCommand::parse("my-engine --depth 3").unwrap(),
+            },
+        );
+
+        SavedGame {
+            record: RecordFile {
+                variant: "hnefatafl".to_string(),
+                time_control: TimeControl {
+                    time: Duration::from_secs(60),
+                    bonus: TimeBonus::ByoYomi {
+                        periods: 3,
+                        period: Duration::from_secs(10),
+                    },
+                    move_limit: None,
+                    unlimited: false,
+                },
+                initial_position: HNEFATAFL_POSITION.to_string(),
+                first_side: Side::Attacker,
+                entries: vec![
+                    entry(Side::Attacker, "a4-c4", 50),
+                    RecordEntry::Chat {
+                        side: Side::Defender,
+                        text: "take your time".to_string(),
+                        time: Duration::from_secs(55),
+                    },
+                    entry(Side::Defender, "e5-e2", 70),
+                    entry(Side::Attacker, "a5-c5", 95),
+                ],
+                outcome: None,
+            },
+            clocks: vec![
+                SavedClock {
+                    side: Side::Attacker,
+                    remaining: Duration::ZERO,
+                    periods: 1,
+                },
+                SavedClock {
+                    side: Side::Defender,
+                    remaining: Duration::from_millis(39_500),
+                    periods: 3,
+                },
+            ],
+            players,
+        }
+    }
+
+    #[test]
+    fn saved_games_round_trip() {
+        let saved_game = saved_game();
+        let loaded = SavedGame::parse(&saved_game.to_text()).unwrap();
+
+        assert_eq!(loaded.record, saved_game.record);
+        assert_eq!(loaded.clocks, saved_game.clocks);
+        for side in [Side::Attacker, Side::Defender] {
+            assert_eq!(loaded.players.get(side), saved_game.players.get(side));
+        }
+        assert_eq!(loaded.side_to_move(), Side::Defender);
+    }
+
+    #[test]
+    fn loaded_games_continue_from_the_saved_state() {
+        let game_setup = SavedGame::parse(&saved_game().to_text())
+            .unwrap()
+            .game_setup(Some(1))
+            .unwrap();
+
+        assert_eq!(game_setup.game.side_to_move(), Side::Defender);
+        assert_eq!(game_setup.clocks, saved_game().clocks);
+        assert_eq!(game_setup.entries, saved_game().record.entries);
+        assert_eq!(game_setup.slot, Some(1));
+    }
+
+    #[test]
+    fn malformed_clocks_are_rejected() {
+        let text = saved_game().to_text();
+
+        for clock in [
+            "clock attackers 1000",
+            "clock attackers soon 1",
+            "clock attackers 1000 many",
+            "clock attackers 1000 1 2",
+            "clock everyone 1000 1",
+        ] {
+            let data = format!("{}{}\n", text, clock);
+            assert!(SavedGame::parse(&data).is_err(), "{}", clock);
+        }
+    }
+
+    #[test]
+    fn malformed_players_are_rejected() {
+        let text = saved_game().to_text();
+
+        for player in [
+            "player attackers",
+            "player attackers robot",
+            "player attackers human please",
+            "player attackers engine",
+            "player everyone human",
+        ] {
+            let data = format!("{}{}\n", text, player);
+            assert!(SavedGame::parse(&data).is_err(), "{}", player);
+        }
+    }
+}
@@ -0,0 +1,352 @@
+//! JSON protocol spoken between the game server and its clients over WebSockets.
+//!
+//! Every message is a JSON object sent in a text frame, its `type` field tells what kind of
+//! message it is. Squares use the format of the `notation` module, e.g. `"d1"`, sides are either
+//! `"attackers"` or `"defenders"` and times are given in milliseconds.
+//!
+//! The time control of a game is its main time `time_ms` with an optional `bonus` and
+//! `move_limit_ms`, the longest a single move may take. The bonus is one of
+//! `{"type": "increment", "ms": 5000}`, `{"type": "delay", "ms": 3000}` or
+//! `{"type": "byo_yomi", "periods": 3, "period_ms": 30000}`.
+//!
+//! Clients have to register a name before creating or joining games.
+//!
+//! Sent by clients:
+//! - `{"type": "register", "name": "ragnar"}` - names must be unique and consist of at most 20
+//!   letters, digits, `-` or `_`
+//! - `{"type": "list_games", "variant": "hnefatafl", "time_ms": 600000}` - lists the games waiting
+//!   for an opponent, both filters are optional, with `time_ms` only the games with the time
+//!   control made of it, `bonus` and `move_limit_ms` are listed
+//! - `{"type": "create_game", "variant": "hnefatafl", "side": "attackers", "time_ms": 600000,
+//!   "bonus": {"type": "increment", "ms": 5000}}` - challenges anyone to a game in which the client
+//!   plays `side` with the time control, the variant defaults to `hnefatafl`
+//! - `{"type": "join_game", "game_id": 1}` - accepts a challenge, playing the other side
+//! - `{"type": "join_queue", "variant": "hnefatafl", "time_ms": 600000}` - waits for someone else
+//!   in the queue who wants to play the same variant and time control, including the bonus and the
+//!   move limit, the one who waited longer plays the attackers
+//! - `{"type": "leave_queue"}`
+//! - `{"type": "move", "game_id": 1, "from": "d1", "to": "d4"}`
+//! - `{"type": "resign", "game_id": 1}`
+//! - `{"type": "offer_draw", "game_id": 1}` - offers a draw, or accepts the offer of the opponent
+//! - `{"type": "decline_draw", "game_id": 1}`
+//! - `{"type": "chat", "game_id": 1, "text": "good luck"}` - at most 200 characters
+//!
+//! Sent by the server:
+//! - `{"type": "registered", "name": "ragnar"}`
+//! - `{"type": "game_list", "games": [{"game_id": 1, "variant": "hnefatafl", "time_ms": 600000,
+//!   "bonus": null, "move_limit_ms": null, "creator": "ragnar", "side": "attackers"}]}` - the
+//!   answer to `list_games`, `side` is the side played by the creator
+//! - `{"type": "game_created", "game_id": 1, "variant": "hnefatafl", "side": "attackers",
+//!   "time_ms": 600000, "bonus": null, "move_limit_ms": null}` - the answer to `create_game`
+//! - `{"type": "queued", "variant": "hnefatafl", "time_ms": 600000, "bonus": null,
+//!   "move_limit_ms": null}` - the answer to `join_queue`
+//! - `{"type": "left_queue"}`
+//! - `{"type": "game_started", "game_id": 1, "variant": "hnefatafl", "side": "attackers",
+//!   "opponent": "bjorn", "position": "/3ttttt3/.../", "side_to_move": "attackers",
+//!   "attackers_ms": 600000, "defenders_ms": 600000, "bonus": null, "move_limit_ms": null}` - sent
+//!   to both players once the second one joined or they were matched, `side` is the side of the
+//!   receiving player
+//! - `{"type": "move_made", "game_id": 1, "side": "attackers", "from": "d1", "to": "d4",
+//!   "captured": ["e4"], "side_to_move": "defenders"}` - sent to both players after every move
+//! - `{"type": "clock", "game_id": 1, "attackers_ms": 598000, "defenders_ms": 600000,
+//!   "attackers_periods": 0, "defenders_periods": 0}` - the remaining main time and byo-yomi
+//!   periods of both sides, sent after every move
+//! - `{"type": "draw_offered", "game_id": 1, "side": "attackers"}`
+//! - `{"type": "draw_declined", "game_id": 1, "side": "defenders"}`
+//! - `{"type": "chat", "game_id": 1, "side": "attackers", "text": "good luck"}` - sent to both
+//!   players, `side` is the side of the player who wrote the message
+//! - `{"type": "game_over", "game_id": 1, "result": "attackers", "reason": "rules"}` - `result` is
+//!   `attackers`, `defenders` or `draw`, `reason` is `rules`, `timeout`, `resignation`,
+//!   `agreement` or `abandoned`
+//! - `{"type": "error", "message": "..."}` - the last message of the client couldn't be handled
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::tafl::rules::GameOutcome;
+use crate::game::tafl::time_control::{TimeBonus, TimeControl};
+use crate::game::tafl::*;
+
+/// The maximum number of characters in the name of a player.
+pub const MAX_NAME_LENGTH: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Register {
+        name: String,
+    },
+    ListGames {
+        #[serde(default)]
+        variant: Option<String>,
+        #[serde(default)]
+        time_ms: Option<u64>,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    CreateGame {
+        #[serde(default = "default_variant")]
+        variant: String,
+        #[serde(with = "side_notation")]
+        side: Side,
+        time_ms: u64,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    JoinGame {
+        game_id: u64,
+    },
+    JoinQueue {
+        #[serde(default = "default_variant")]
+        variant: String,
+        time_ms: u64,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    LeaveQueue,
+    Move {
+        game_id: u64,
+        from: String,
+        to: String,
+    },
+    Resign {
+        game_id: u64,
+    },
+    OfferDraw {
+        game_id: u64,
+    },
+    DeclineDraw {
+        game_id: u64,
+    },
+    Chat {
+        game_id: u64,
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Registered {
+        name: String,
+    },
+    GameList {
+        games: Vec<OpenGame>,
+    },
+    GameCreated {
+        game_id: u64,
+        variant: String,
+        #[serde(with = "side_notation")]
+        side: Side,
+        time_ms: u64,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    Queued {
+        variant: String,
+        time_ms: u64,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    LeftQueue,
+    GameStarted {
+        game_id: u64,
+        variant: String,
+        #[serde(with = "side_notation")]
+        side: Side,
+        opponent: String,
+        position: String,
+        #[serde(with = "side_notation")]
+        side_to_move: Side,
+        attackers_ms: u64,
+        defenders_ms: u64,
+        #[serde(default)]
+        bonus: Option<Bonus>,
+        #[serde(default)]
+        move_limit_ms: Option<u64>,
+    },
+    MoveMade {
+        game_id: u64,
+        #[serde(with = "side_notation")]
+        side: Side,
+        from: String,
+        to: String,
+        captured: Vec<String>,
+        #[serde(with = "side_notation")]
+        side_to_move: Side,
+    },
+    Clock {
+        game_id: u64,
+        attackers_ms: u64,
+        defenders_ms: u64,
+        #[serde(default)]
+        attackers_periods: u32,
+        #[serde(default)]
+        defenders_periods: u32,
+    },
+    DrawOffered {
+        game_id: u64,
+        #[serde(with = "side_notation")]
+        side: Side,
+    },
+    DrawDeclined {
+        game_id: u64,
+        #[serde(with = "side_notation")]
+        side: Side,
+    },
+    Chat {
+        game_id: u64,
+        #[serde(with = "side_notation")]
+        side: Side,
+        text: String,
+    },
+    GameOver {
+        game_id: u64,
+        result: GameResult,
+        reason: GameOverReason,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// A game waiting for an opponent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenGame {
+    pub game_id: u64,
+    pub variant: String,
+    pub time_ms: u64,
+    #[serde(default)]
+    pub bonus: Option<Bonus>,
+    #[serde(default)]
+    pub move_limit_ms: Option<u64>,
+    /// The name of the player who created the game.
+    pub creator: String,
+    /// The side played by the creator.
+    #[serde(with = "side_notation")]
+    pub side: Side,
+}
+
+/// The extra time of a time control, see `TimeBonus`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Bonus {
+    Increment { ms: u64 },
+    Delay { ms: u64 },
+    ByoYomi { periods: u32, period_ms: u64 },
+}
+
+impl Bonus {
+    /// Gets the bonus of `bonus`, None if there is none.
+    pub fn from_time_bonus(bonus: TimeBonus) -> Option<Self> {
+        match bonus {
+            TimeBonus::None => None,
+            TimeBonus::Increment(increment) => Some(Bonus::Increment {
+                ms: increment.as_millis() as u64,
+            }),
+            TimeBonus::Delay(delay) => Some(Bonus::Delay {
+                ms: delay.as_millis() as u64,
+            }),
+            TimeBonus::ByoYomi { periods, period } => Some(Bonus::ByoYomi {
+                periods,
+                period_ms: period.as_millis() as u64,
+            }),
+        }
+    }
+}
+
+impl From<Bonus> for TimeBonus {
+    fn from(bonus: Bonus) -> Self {
+        match bonus {
+            Bonus::Increment { ms } => TimeBonus::Increment(Duration::from_millis(ms)),
+            Bonus::Delay { ms } => TimeBonus::Delay(Duration::from_millis(ms)),
+            Bonus::ByoYomi { periods, period_ms } => TimeBonus::ByoYomi {
+                periods,
+                period: Duration::from_millis(period_ms),
+            },
+        }
+    }
+}
+
+/// Gets the time control described by the fields of a message.
+pub fn time_control(time_ms: u64, bonus: Option<Bonus>, move_limit_ms: Option<u64>) -> TimeControl {
+    TimeControl {
+        time: Duration::from_millis(time_ms),
+        bonus: bonus.map(TimeBonus::from).unwrap_or_default(),
+        move_limit: move_limit_ms.map(Duration::from_millis),
+        unlimited: false,
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameResult {
+    Attackers,
+    Defenders,
+    Draw,
+}
+
+impl From<GameOutcome> for GameResult {
+    fn from(outcome: GameOutcome) -> Self {
+        match outcome {
+            GameOutcome::Win(Side::Attacker) => GameResult::Attackers,
+            GameOutcome::Win(Side::Defender) => GameResult::Defenders,
+            GameOutcome::Draw => GameResult::Draw,
+        }
+    }
+}
+
+impl From<GameResult> for GameOutcome {
+    fn from(result: GameResult) -> Self {
+        match result {
+            GameResult::Attackers => GameOutcome::Win(Side::Attacker),
+            GameResult::Defenders => GameOutcome::Win(Side::Defender),
+            GameResult::Draw => GameOutcome::Draw,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GameOverReason {
+    /// The game was decided on the board, e.g. the king escaped.
+    Rules,
+    Timeout,
+    Resignation,
+    /// Both players agreed to a draw.
+    Agreement,
+    /// A player disconnected.
+    Abandoned,
+}
+
+fn default_variant() -> String {
+    "hnefatafl".to_string()
+}
+
+// (De)serializes sides using their notation.
+mod side_notation {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::game::tafl::figure::Side;
+    use crate::game::tafl::notation::*;
+
+    pub fn serialize<S: Serializer>(side: &Side, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(format_side(*side))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Side, D::Error> {
+        let data = String::deserialize(deserializer)?;
+        parse_side(&data).map_err(de::Error::custom)
+    }
+}
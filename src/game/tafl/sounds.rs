@@ -1,3 +1,4 @@
+use crate::game::settings::Settings;
 use crate::game::tafl::*;
 use bevy::audio::*;
 
@@ -16,6 +17,10 @@ impl Plugin for SoundsPlugin {
                 )
                     .run_if(in_state(GameState::InGame)),
             )
+            .add_systems(
+                Update,
+                apply_sound_settings.run_if(resource_changed::<Settings>),
+            )
             .insert_resource(Sounds::default());
     }
 }
@@ -39,6 +44,8 @@ pub struct Sounds {
     move_sound: String,
     capture_sound: String,
     game_end_sound: String,
+    /// The volume of the sounds, relative to the global volume.
+    volume: f32,
 }
 
 impl Default for Sounds {
@@ -47,10 +54,21 @@ impl Default for Sounds {
             move_sound: "sounds/move.ogg".to_string(),
             capture_sound: "sounds/capture.ogg".to_string(),
             game_end_sound: "sounds/game_end.ogg".to_string(),
+            volume: 1.,
         }
     }
 }
 
+fn apply_sound_settings(
+    settings: Res<Settings>,
+    mut sounds: ResMut<Sounds>,
+    mut global_volume: ResMut<GlobalVolume>,
+) {
+    sounds.volume = settings.effect_volume;
+    // only affects the sounds played from now on
+    *global_volume = GlobalVolume::new(settings.master_volume);
+}
+
 #[derive(Component)]
 struct SoundManager;
 
@@ -84,6 +102,7 @@ fn move_and_capture_sound(
                         source: asset_server.load(sounds.move_sound.clone()),
                         settings: PlaybackSettings {
                             mode: PlaybackMode::Once,
+                            volume: Volume::new(sounds.volume),
                             ..default()
                         },
                     },
@@ -97,6 +116,7 @@ fn move_and_capture_sound(
                         source: asset_server.load(sounds.capture_sound.clone()),
                         settings: PlaybackSettings {
                             mode: PlaybackMode::Once,
+                            volume: Volume::new(sounds.volume),
                             ..default()
                         },
                     },
@@ -125,6 +145,7 @@ fn game_end_sound(
                     source: asset_server.load(sounds.game_end_sound.clone()),
                     settings: PlaybackSettings {
                         mode: PlaybackMode::Once,
+                        volume: Volume::new(sounds.volume),
                         ..default()
                     },
                 },
@@ -1,11 +1,27 @@
-use std::time::Duration;
-
 use bevy::{
     sprite::{MaterialMesh2dBundle, Mesh2dHandle},
     utils::HashMap,
 };
 
+use crate::game::tafl::game_setup::GameSetup;
+use crate::game::tafl::notation::parse_position;
+use crate::game::tafl::rules::HNEFATAFL_POSITION;
 use crate::game::tafl::*;
+use crate::game::theme::ThemeAssets;
+
+/// The fields of the board spawned by `spawn_hnefatafl`, see `parse_board` for the digits.
+pub const HNEFATAFL_STRUCTURE: &str = "\
+                                       40033333004\n\
+                                       00000300000\n\
+                                       00000000000\n\
+                                       30000200003\n\
+                                       30002220003\n\
+                                       33022122033\n\
+                                       30002220003\n\
+                                       30000200003\n\
+                                       00000000000\n\
+                                       00000300000\n\
+                                       40033333004";
 
 #[derive(Resource, Default)]
 pub struct BoardId(SimpleId);
@@ -23,55 +39,33 @@ pub fn spawn_hnefatafl(
     mut spawn_board_event: EventWriter<SpawnBoardEvent>,
     mut spawn_figures_event: EventWriter<SpawnFiguresEvent>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     mut board_id: ResMut<BoardId>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
     mut setup_game_ui_event: EventWriter<SetupGameUiEvent>,
+    time_control: Res<TimeControl>,
+    game_setup: Option<Res<GameSetup>>,
+    theme_assets: Res<ThemeAssets>,
 ) {
     let id = board_id.get();
 
     let board = {
-        let mut colors = HashMap::<u8, Handle<ColorMaterial>>::new();
-        colors.insert(0, materials.add(Color::rgb_u8(130, 162, 157)));
-        colors.insert(1, materials.add(Color::rgb_u8(38, 53, 75)));
-        colors.insert(2, materials.add(Color::rgb_u8(78, 112, 165)));
-        colors.insert(3, materials.add(Color::rgb_u8(157, 79, 79)));
-        colors.insert(4, materials.add(Color::rgb_u8(55, 65, 104)));
-
-        let structure = "\
-                     40033333004\n\
-                     00000300000\n\
-                     00000000000\n\
-                     30000200003\n\
-                     30002220003\n\
-                     33022122033\n\
-                     30002220003\n\
-                     30000200003\n\
-                     00000000000\n\
-                     00000300000\n\
-                     40033333004";
-
-        let parsed = parse_board(structure).unwrap();
+        let parsed = parse_board(HNEFATAFL_STRUCTURE).unwrap();
 
         let field_materials: HashMap<Position, Handle<ColorMaterial>> = parsed
             .structure
             .iter()
-            .map(|(key, value)| (*key, colors.get(value).unwrap().clone()))
+            .map(|(key, value)| (*key, theme_assets.fields.get(value).unwrap().clone()))
             .collect();
 
         let field_size = 50.;
+        let rules = Rules::hnefatafl();
 
         let board = Board::new(BoardOptions {
-            cols: parsed.cols,
-            rows: parsed.rows,
-            throne_position: Position { x: 5, y: 5 },
-            end_positions: vec![
-                Position { x: 0, y: 0 },
-                Position { x: 10, y: 0 },
-                Position { x: 0, y: 10 },
-                Position { x: 10, y: 10 },
-            ],
+            cols: rules.cols,
+            rows: rules.rows,
+            throne_position: rules.throne_position,
+            end_positions: rules.end_positions,
+            no_legal_moves: rules.no_legal_moves,
             figures: HashMap::new(),
             field_size,
             border_width: 4.,
@@ -83,11 +77,11 @@ pub fn spawn_hnefatafl(
             id,
             position: Vec3::ZERO,
             board: board.clone(),
-            border_material: materials.add(Color::rgb(0., 0., 0.)),
+            border_material: theme_assets.border.clone(),
             border_z: -1.,
             field_materials,
             highlight_mesh: meshes.add(Circle::new(0.2 * field_size)),
-            highlight_material: materials.add(Color::rgba(0., 0., 0., 0.6)),
+            highlight_material: theme_assets.highlight.clone(),
             highlight_z: 1.,
         });
 
@@ -96,75 +90,12 @@ pub fn spawn_hnefatafl(
 
     // Figures
     {
-        let starting_position = "
-a s 0 3
-a s 0 4
-a s 0 5
-a s 0 6
-a s 0 7
-a s 1 5
-a s 3 0
-d s 3 5
-a s 3 10
-a s 4 0
-d s 4 4
-d s 4 5
-d s 4 6
-a s 4 10
-a s 5 0
-a s 5 1
-d s 5 3
-d s 5 4
-d k 5 5
-d s 5 6
-d s 5 7
-a s 5 9
-a s 5 10
-a s 6 0 
-d s 6 4
-d s 6 5
-d s 6 6
-a s 6 10
-a s 7 0
-d s 7 5
-a s 7 10
-a s 9 5
-a s 10 3
-a s 10 4
-a s 10 5
-a s 10 6
-a s 10 7
-";
-
-        let figures = parse_figures(starting_position, &board).unwrap();
-
-        let mut figure_textures = HashMap::<FigureType, Handle<Image>>::new();
-        figure_textures.insert(
-            FigureType {
-                side: Side::Defender,
-                kind: FigureKind::King,
-            },
-            asset_server.load("figures/defender_king.png"),
-        );
-        figure_textures.insert(
-            FigureType {
-                side: Side::Defender,
-                kind: FigureKind::Soldier,
-            },
-            asset_server.load("figures/defender_soldier.png"),
-        );
-        figure_textures.insert(
-            FigureType {
-                side: Side::Attacker,
-                kind: FigureKind::Soldier,
-            },
-            asset_server.load("figures/attacker_soldier.png"),
-        );
+        let figures = parse_position(HNEFATAFL_POSITION).unwrap().figures;
 
         spawn_figures_event.send(SpawnFiguresEvent {
             board_id: id,
             figures,
-            textures: figure_textures,
+            textures: theme_assets.figures.clone(),
         });
     };
 
@@ -174,7 +105,7 @@ a s 10 7
         commands.spawn((
             MaterialMesh2dBundle {
                 mesh,
-                material: materials.add(Color::rgb_u8(157, 71, 162)),
+                material: theme_assets.selection.clone(),
                 transform: Transform::from_xyz(0., 0., 2.),
                 visibility: Visibility::Hidden,
                 ..default()
@@ -184,19 +115,25 @@ a s 10 7
     }
 
     setup_game_ui_event.send(SetupGameUiEvent {
-        side_with_initial_turn: Side::Attacker,
-        timer_duration: Duration::from_secs(600),
+        side_with_initial_turn: game_setup
+            .map(|game_setup| game_setup.game.side_to_move())
+            .unwrap_or(Side::Attacker),
+        time_control: time_control.clone(),
     });
 }
 
-struct ParsedBoard {
-    rows: usize,
-    cols: usize,
-    structure: HashMap<Position, u8>,
+pub struct ParsedBoard {
+    pub rows: usize,
+    pub cols: usize,
+    pub structure: HashMap<Position, u8>,
 }
 
 /// Helper function for parsing boards from strings.
-fn parse_board(data: &str) -> Result<ParsedBoard, &str> {
+///
+/// Every row of the board is a line of digits, starting with the top one. The digits are the
+/// kinds of the fields: 0 a plain field, 1 the throne, 2 and 3 the starting areas of the
+/// defenders and the attackers, 4 a corner.
+pub fn parse_board(data: &str) -> Result<ParsedBoard, &str> {
     let mut rows = 0;
     let mut cols = 0;
     let mut structure: HashMap<Position, u8> = HashMap::new();
@@ -252,69 +189,3 @@ fn parse_board(data: &str) -> Result<ParsedBoard, &str> {
         structure,
     })
 }
-
-fn parse_figures(data: &str, board: &Board) -> Result<Vec<Figure>, &'static str> {
-    let not_enough_tokens = "a figure should be described by 4, space-separated values";
-
-    let mut result: Vec<Figure> = vec![];
-
-    for line in data.lines() {
-        if line == "" {
-            continue;
-        }
-
-        let mut token_iter = line.split(' ');
-
-        let side = if let Some(side_unparsed) = token_iter.next() {
-            match side_unparsed {
-                "a" => Side::Attacker,
-                "d" => Side::Defender,
-                _ => return Err("side described by data should be either a or d"),
-            }
-        } else {
-            return Err(not_enough_tokens);
-        };
-
-        let kind = if let Some(kind_unparsed) = token_iter.next() {
-            match kind_unparsed {
-                "k" => FigureKind::King,
-                "s" => FigureKind::Soldier,
-                _ => return Err("kind described by data should be either k or s"),
-            }
-        } else {
-            return Err(not_enough_tokens);
-        };
-
-        let x = if let Some(x_unparsed) = token_iter.next() {
-            let x_parsed = x_unparsed
-                .parse::<usize>()
-                .expect("position x should be a usize");
-            x_parsed
-        } else {
-            return Err(not_enough_tokens);
-        };
-
-        let y = if let Some(y_unparsed) = token_iter.next() {
-            let y_parsed = y_unparsed
-                .parse::<usize>()
-                .expect("position y should be a usize");
-            y_parsed
-        } else {
-            return Err(not_enough_tokens);
-        };
-
-        let position = Position { x, y };
-
-        if !board.is_on_board(position) {
-            return Err("the figure should be on the board");
-        }
-
-        result.push(Figure {
-            side,
-            kind,
-            position,
-        });
-    }
-
-    Ok(result)
-}
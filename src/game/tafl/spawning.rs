@@ -3,7 +3,9 @@ use bevy::{
     utils::HashMap,
 };
 
+use crate::game::settings::Settings;
 use crate::game::tafl::*;
+use crate::game::theme::ThemeAssets;
 
 #[derive(Event, Clone)]
 pub struct SpawnBoardEvent {
@@ -21,6 +23,12 @@ pub struct SpawnBoardEvent {
 #[derive(Component)]
 pub struct MainBoard;
 
+/// A field of the board, the fields are moved when the board is rotated.
+#[derive(Component)]
+pub struct BoardField {
+    pub position: Position,
+}
+
 pub fn spawn_board(
     mut event: EventReader<SpawnBoardEvent>,
     mut commands: Commands,
@@ -75,6 +83,7 @@ pub fn spawn_board(
 
                     let field = commands
                         .spawn((
+                            BoardField { position },
                             Name::new("Field"),
                             MaterialMesh2dBundle {
                                 mesh,
@@ -143,10 +152,27 @@ pub struct SpawnFiguresEvent {
 #[derive(Component)]
 pub struct MainFigures;
 
+/// A shape on a figure that tells its side and kind apart without colors, shown when it is turned
+/// on in the settings.
+#[derive(Component)]
+pub struct FigureMarker;
+
+// a square for the attackers, a circle for the defenders and a triangle for the king
+fn marker_mesh(figure_type: FigureType, size: f32) -> Mesh {
+    match (figure_type.side, figure_type.kind) {
+        (_, FigureKind::King) => RegularPolygon::new(1.3 * size, 3).into(),
+        (Side::Attacker, _) => Rectangle::new(2. * size, 2. * size).into(),
+        (Side::Defender, _) => Circle::new(size).into(),
+    }
+}
+
 pub fn spawn_figures(
     mut event: EventReader<SpawnFiguresEvent>,
     mut commands: Commands,
     mut q_board: Query<(&SimpleId, &mut Board)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    settings: Res<Settings>,
 ) {
     for ev in event.read() {
         let mut board = 'blk: {
@@ -170,13 +196,26 @@ pub fn spawn_figures(
             ))
             .id();
 
+        // the markers have a dark outline to stand out on any field
+        let marker_size = 0.1 * board.field_size;
+        let outline_size = marker_size + 1.5;
+        let marker_material = materials.add(Color::WHITE);
+        let outline_material = materials.add(Color::BLACK);
+        let mut marker_meshes = HashMap::<FigureType, (Mesh2dHandle, Mesh2dHandle)>::new();
+        let marker_visibility = match settings.figure_markers {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+
         for figure in &ev.figures {
+            let figure_type = FigureType {
+                side: figure.side,
+                kind: figure.kind,
+            };
+
             let texture = ev
                 .textures
-                .get(&FigureType {
-                    side: figure.side,
-                    kind: figure.kind,
-                })
+                .get(&figure_type)
                 .expect("all used figure types should have a texture")
                 .clone();
 
@@ -198,13 +237,100 @@ pub fn spawn_figures(
                 ))
                 .id();
 
+            let (marker_mesh, outline_mesh) = marker_meshes
+                .entry(figure_type)
+                .or_insert_with(|| {
+                    (
+                        meshes.add(marker_mesh(figure_type, marker_size)).into(),
+                        meshes.add(marker_mesh(figure_type, outline_size)).into(),
+                    )
+                })
+                .clone();
+
+            commands.entity(figure_entity).with_children(|parent| {
+                parent
+                    .spawn((
+                        FigureMarker,
+                        Name::new("Marker"),
+                        MaterialMesh2dBundle {
+                            mesh: outline_mesh,
+                            material: outline_material.clone(),
+                            transform: Transform::from_xyz(
+                                0.3 * board.field_size,
+                                -0.3 * board.field_size,
+                                0.1,
+                            ),
+                            visibility: marker_visibility,
+                            ..default()
+                        },
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(MaterialMesh2dBundle {
+                            mesh: marker_mesh,
+                            material: marker_material.clone(),
+                            transform: Transform::from_xyz(0., 0., 0.01),
+                            ..default()
+                        });
+                    });
+            });
+
             board.figures.insert(figure.position, figure_entity);
             commands.entity(parent).add_child(figure_entity);
         }
     }
 }
 
+pub fn show_figure_markers(
+    settings: Res<Settings>,
+    mut q_figure_marker: Query<&mut Visibility, With<FigureMarker>>,
+) {
+    for mut visibility in &mut q_figure_marker {
+        *visibility = match settings.figure_markers {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+    }
+}
+
 pub fn despawn_figures(q_figures: Query<Entity, With<MainFigures>>, mut commands: Commands) {
     let figures_entity = q_figures.single();
     commands.entity(figures_entity).despawn_recursive();
 }
+
+/// Replaces the figures on a board, e.g. to catch up with a game played elsewhere.
+#[derive(Event)]
+pub struct ResetFiguresEvent {
+    pub board_entity: Entity,
+    pub figures: Vec<Figure>,
+    pub side_to_move: Side,
+}
+
+pub fn reset_figures(
+    mut event: EventReader<ResetFiguresEvent>,
+    mut q_board: Query<(&SimpleId, &mut Board, &mut TurnTracker)>,
+    q_figures: Query<Entity, With<MainFigures>>,
+    mut commands: Commands,
+    theme_assets: Res<ThemeAssets>,
+    mut spawn_figures_event: EventWriter<SpawnFiguresEvent>,
+    mut indicate_turn_event: EventWriter<IndicateTurnEvent>,
+) {
+    for ev in event.read() {
+        let (board_id, mut board, mut turn_tracker) = q_board.get_mut(ev.board_entity).unwrap();
+
+        for figures_entity in &q_figures {
+            commands.entity(figures_entity).despawn_recursive();
+        }
+        board.figures.clear();
+
+        spawn_figures_event.send(SpawnFiguresEvent {
+            board_id: *board_id,
+            figures: ev.figures.clone(),
+            textures: theme_assets.figures.clone(),
+        });
+
+        turn_tracker.side = ev.side_to_move;
+        indicate_turn_event.send(IndicateTurnEvent {
+            side: Some(ev.side_to_move),
+        });
+    }
+}
@@ -0,0 +1,629 @@
+use bevy::utils::Duration;
+
+use crate::game::tafl::notation::parse_millis;
+use crate::game::tafl::*;
+
+/// How much time the players have for a game.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct TimeControl {
+    /// The main time on the clock of each side at the start of the game.
+    pub time: Duration,
+    pub bonus: TimeBonus,
+    /// The longest a single move may take, regardless of the time left on the clock.
+    pub move_limit: Option<Duration>,
+    /// Games without a clock can't be lost on time, the other fields are ignored.
+    pub unlimited: bool,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            time: Duration::from_secs(600),
+            bonus: TimeBonus::None,
+            move_limit: None,
+            unlimited: false,
+        }
+    }
+}
+
+/// The time controls that can be chosen for a game, with their names.
+pub const TIME_CONTROL_PRESETS: &[(&str, TimeControl)] = &[
+    (
+        "No clock",
+        TimeControl {
+            time: Duration::ZERO,
+            bonus: TimeBonus::None,
+            move_limit: None,
+            unlimited: true,
+        },
+    ),
+    ("3 min", minutes(3, TimeBonus::None)),
+    ("5 min", minutes(5, TimeBonus::None)),
+    ("10 min", minutes(10, TimeBonus::None)),
+    ("30 min", minutes(30, TimeBonus::None)),
+    (
+        "3 min +2",
+        minutes(3, TimeBonus::Increment(Duration::from_secs(2))),
+    ),
+    (
+        "10 min +5",
+        minutes(10, TimeBonus::Increment(Duration::from_secs(5))),
+    ),
+    (
+        "5 min delay 3",
+        minutes(5, TimeBonus::Delay(Duration::from_secs(3))),
+    ),
+    (
+        "10 min +3x30",
+        minutes(
+            10,
+            TimeBonus::ByoYomi {
+                periods: 3,
+                period: Duration::from_secs(30),
+            },
+        ),
+    ),
+    (
+        "30 min, 1 min/move",
+        TimeControl {
+            time: Duration::from_secs(30 * 60),
+            bonus: TimeBonus::None,
+            move_limit: Some(Duration::from_secs(60)),
+            unlimited: false,
+        },
+    ),
+];
+
+const fn minutes(minutes: u64, bonus: TimeBonus) -> TimeControl {
+    TimeControl {
+        time: Duration::from_secs(minutes * 60),
+        bonus,
+        move_limit: None,
+        unlimited: false,
+    }
+}
+
+/// Extra time the players get on top of the main time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeBonus {
+    #[default]
+    None,
+    /// Fischer increment, added to the main time after every move.
+    Increment(Duration),
+    /// Bronstein delay, the main time only starts running after the delay of each move.
+    Delay(Duration),
+    /// Once the main time runs out every move has to be made within a period, the side loses
+    /// when all periods ran out.
+    ByoYomi { periods: u32, period: Duration },
+}
+
+/// The time left on the clock of a side, e.g. to match the clock of another instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ClockState {
+    /// The main time left.
+    pub remaining: Duration,
+    /// The byo-yomi periods left.
+    pub periods: u32,
+}
+
+/// The clock of a side, counting down according to a time control.
+#[derive(Debug, Clone, Default)]
+pub struct Clock {
+    pub time_control: TimeControl,
+    // the main time left
+    remaining: Duration,
+    // the byo-yomi periods left and the time left in the current one
+    periods: u32,
+    period_remaining: Duration,
+    // the time spent on the current move
+    move_time: Duration,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let (periods, period_remaining) = match time_control.bonus {
+            TimeBonus::ByoYomi { periods, period } => (periods, period),
+            _ => (0, Duration::ZERO),
+        };
+
+        Self {
+            remaining: time_control.time,
+            time_control,
+            periods,
+            period_remaining,
+            move_time: Duration::ZERO,
+        }
+    }
+
+    /// Gets the main time left.
+    pub fn remaining(&self) -> Duration {
+        self.remaining
+    }
+
+    /// Sets the main time left, e.g. to match the clock of another instance.
+    pub fn set_remaining(&mut self, remaining: Duration) {
+        self.remaining = remaining;
+    }
+
+    /// Gets the byo-yomi periods left.
+    pub fn periods(&self) -> u32 {
+        self.periods
+    }
+
+    /// Sets the byo-yomi periods left, e.g. when a saved game is loaded.
+    pub fn set_periods(&mut self, periods: u32) {
+        self.periods = periods;
+    }
+
+    /// Gets the time left.
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            remaining: self.remaining,
+            periods: self.periods,
+        }
+    }
+
+    /// Sets the time left as it is after a move, with the current byo-yomi period starting over.
+    pub fn set_state(&mut self, state: ClockState) {
+        self.remaining = state.remaining;
+        self.periods = state.periods;
+
+        if let TimeBonus::ByoYomi { period, .. } = self.time_control.bonus {
+            self.period_remaining = period;
+        }
+    }
+
+    /// Determines whether the side lost on time.
+    pub fn is_flagged(&self) -> bool {
+        if self.time_control.unlimited {
+            return false;
+        }
+
+        let out_of_time = self.remaining.is_zero() && self.periods == 0;
+        let over_move_limit = self
+            .time_control
+            .move_limit
+            .is_some_and(|move_limit| move_limit <= self.move_time);
+
+        out_of_time || over_move_limit
+    }
+
+    /// Counts down `delta` of the current move.
+    pub fn tick(&mut self, delta: Duration) {
+        if self.time_control.unlimited {
+            return;
+        }
+
+        let previous_move_time = self.move_time;
+        self.move_time += delta;
+
+        let delta = match self.time_control.bonus {
+            // only the part of the move after the delay counts
+            TimeBonus::Delay(delay) => {
+                self.move_time.saturating_sub(delay) - previous_move_time.saturating_sub(delay)
+            }
+            _ => delta,
+        };
+
+        let used = delta.min(self.remaining);
+        self.remaining -= used;
+
+        let TimeBonus::ByoYomi { period, .. } = self.time_control.bonus else {
+            return;
+        };
+
+        // what is left is taken from the periods
+        let mut overflow = delta - used;
+        while !overflow.is_zero() && 0 < self.periods {
+            let used = overflow.min(self.period_remaining);
+            self.period_remaining -= used;
+            overflow -= used;
+
+            if self.period_remaining.is_zero() {
+                self.periods -= 1;
+                self.period_remaining = period;
+            }
+        }
+    }
+
+    /// Finishes the current move, granting the bonus of the time control.
+    pub fn end_move(&mut self) {
+        match self.time_control.bonus {
+            TimeBonus::Increment(increment) => self.remaining += increment,
+            // a move made within the period keeps it
+            TimeBonus::ByoYomi { period, .. } => self.period_remaining = period,
+            _ => (),
+        }
+
+        self.move_time = Duration::ZERO;
+    }
+
+    /// Describes the time left, `active` tells whether the side is to move.
+    pub fn text(&self, active: bool) -> String {
+        if self.time_control.unlimited {
+            return "--:--".to_string();
+        }
+
+        let mut text = match self.time_control.bonus {
+            TimeBonus::ByoYomi { .. } if self.remaining.is_zero() => {
+                format!("{}x{}", self.periods, format_clock(self.period_remaining))
+            }
+            _ => format_clock(self.remaining),
+        };
+
+        match self.time_control.bonus {
+            TimeBonus::None => (),
+            TimeBonus::Increment(increment) => {
+                text.push_str(&format!(" +{}", increment.as_secs()));
+            }
+            TimeBonus::Delay(delay) if active => {
+                let left = delay.saturating_sub(self.move_time);
+                text.push_str(&format!(" ({})", ceil_secs(left)));
+            }
+            TimeBonus::Delay(_) => (),
+            TimeBonus::ByoYomi { periods, period } => {
+                if !self.remaining.is_zero() {
+                    text.push_str(&format!(" +{}x{}", periods, period.as_secs()));
+                }
+            }
+        }
+
+        if let (Some(move_limit), true) = (self.time_control.move_limit, active) {
+            let left = move_limit.saturating_sub(self.move_time);
+            text.push_str(&format!("\nmove {}", format_clock(left)));
+        }
+
+        text
+    }
+}
+
+/// Formats a duration as minutes and seconds, e.g. `09:58`.
+pub fn format_clock(time: Duration) -> String {
+    let secs = time.as_secs();
+    format!("{:0>2}:{:0>2}", secs / 60, secs % 60)
+}
+
+fn ceil_secs(time: Duration) -> u64 {
+    time.as_millis().div_ceil(1000) as u64
+}
+
+// Time controls are written as `none` for games without a clock, otherwise as the main time in
+// milliseconds followed by the bonus and the move limit, e.g. `600000 byo-yomi 3 30000
+// move-limit 120000`. The bonus is one of `increment <ms>`, `delay <ms>` or
+// `byo-yomi <periods> <period ms>`. The clock of a side is written as the main time left in
+// milliseconds and the byo-yomi periods left, e.g. `59000:3`.
+
+/// Converts a time control to its notation, split into the main time, the bonus and the move
+/// limit, e.g. to write them on lines of their own.
+pub fn format_time_control(time_control: &TimeControl) -> Vec<String> {
+    if time_control.unlimited {
+        return vec!["none".to_string()];
+    }
+
+    let mut fields = vec![time_control.time.as_millis().to_string()];
+
+    match time_control.bonus {
+        TimeBonus::None => (),
+        TimeBonus::Increment(increment) => {
+            fields.push(format!("increment {}", increment.as_millis()))
+        }
+        TimeBonus::Delay(delay) => fields.push(format!("delay {}", delay.as_millis())),
+        TimeBonus::ByoYomi { periods, period } => {
+            fields.push(format!("byo-yomi {} {}", periods, period.as_millis()))
+        }
+    }
+
+    if let Some(move_limit) = time_control.move_limit {
+        fields.push(format!("move-limit {}", move_limit.as_millis()));
+    }
+
+    fields
+}
+
+/// Parses the notation of a time control split into tokens, the bonus and the move limit may come
+/// in any order.
+pub fn parse_time_control<'a>(
+    tokens: impl IntoIterator<Item = &'a str>,
+) -> Result<TimeControl, &'static str> {
+    let mut tokens = tokens.into_iter();
+
+    let mut time_control = match tokens.next() {
+        Some("none") if tokens.next().is_none() => {
+            return Ok(TimeControl {
+                unlimited: true,
+                ..TimeControl::default()
+            })
+        }
+        Some("none") => return Err("a time control without a clock should only be none"),
+        time => TimeControl {
+            time: parse_millis(time)?,
+            ..TimeControl::default()
+        },
+    };
+
+    while let Some(token) = tokens.next() {
+        let has_bonus = time_control.bonus != TimeBonus::None;
+
+        match token {
+            "increment" | "delay" | "byo-yomi" if has_bonus => {
+                return Err("a time control should have at most one bonus")
+            }
+            "increment" => time_control.bonus = TimeBonus::Increment(parse_millis(tokens.next())?),
+            "delay" => time_control.bonus = TimeBonus::Delay(parse_millis(tokens.next())?),
+            "byo-yomi" => {
+                time_control.bonus = TimeBonus::ByoYomi {
+                    periods: tokens
+                        .next()
+                        .and_then(|periods| periods.parse().ok())
+                        .ok_or("byo-yomi should be followed by the periods and the period")?,
+                    period: parse_millis(tokens.next())?,
+                }
+            }
+            "move-limit" if time_control.move_limit.is_some() => {
+                return Err("a time control should have at most one move limit")
+            }
+            "move-limit" => time_control.move_limit = Some(parse_millis(tokens.next())?),
+            _ => return Err("a time control should only have a bonus and a move limit"),
+        }
+    }
+
+    Ok(time_control)
+}
+
+/// Converts the clock of a side to its notation, e.g. `59000:3`.
+pub fn format_clock_state(clock: &ClockState) -> String {
+    format!("{}:{}", clock.remaining.as_millis(), clock.periods)
+}
+
+/// Parses the notation of the clock of a side.
+pub fn parse_clock_state(data: Option<&str>) -> Result<ClockState, &'static str> {
+    let invalid = "a clock should consist of the time in milliseconds and the periods, e.g. 1000:0";
+
+    let (remaining, periods) = data.and_then(|data| data.split_once(':')).ok_or(invalid)?;
+
+    Ok(ClockState {
+        remaining: parse_millis(Some(remaining)).map_err(|_| invalid)?,
+        periods: periods.parse().map_err(|_| invalid)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn clock(time: u64, bonus: TimeBonus) -> Clock {
+        Clock::new(TimeControl {
+            time: secs(time),
+            bonus,
+            move_limit: None,
+            unlimited: false,
+        })
+    }
+
+    #[test]
+    fn increment_is_added_after_the_move() {
+        let mut clock = clock(60, TimeBonus::Increment(secs(5)));
+
+        clock.tick(secs(10));
+        assert_eq!(clock.remaining(), secs(50));
+
+        clock.end_move();
+        assert_eq!(clock.remaining(), secs(55));
+    }
+
+    #[test]
+    fn delay_only_charges_the_time_after_it() {
+        let mut clock = clock(60, TimeBonus::Delay(secs(3)));
+
+        clock.tick(secs(2));
+        assert_eq!(clock.remaining(), secs(60));
+
+        // one second is within the delay, the other three are charged
+        clock.tick(secs(4));
+        assert_eq!(clock.remaining(), secs(57));
+
+        clock.tick(secs(1));
+        assert_eq!(clock.remaining(), secs(56));
+
+        // the next move gets the full delay again
+        clock.end_move();
+        clock.tick(secs(3));
+        assert_eq!(clock.remaining(), secs(56));
+    }
+
+    #[test]
+    fn move_limit_flags_the_clock() {
+        let mut clock = Clock::new(TimeControl {
+            time: secs(600),
+            bonus: TimeBonus::None,
+            move_limit: Some(secs(30)),
+            unlimited: false,
+        });
+
+        clock.tick(secs(29));
+        assert!(!clock.is_flagged());
+
+        clock.end_move();
+        clock.tick(secs(29));
+        assert!(!clock.is_flagged());
+
+        clock.tick(secs(2));
+        assert!(clock.is_flagged());
+    }
+
+    #[test]
+    fn main_time_running_out_flags_the_clock() {
+        let mut clock = clock(60, TimeBonus::None);
+
+        clock.tick(secs(59));
+        assert!(!clock.is_flagged());
+
+        clock.tick(secs(2));
+        assert_eq!(clock.remaining(), Duration::ZERO);
+        assert!(clock.is_flagged());
+    }
+
+    #[test]
+    fn byo_yomi_rolls_over_the_periods() {
+        let mut clock = clock(
+            10,
+            TimeBonus::ByoYomi {
+                periods: 3,
+                period: secs(30),
+            },
+        );
+
+        // the main time runs out and 5 seconds of the first period are used
+        clock.tick(secs(15));
+        assert_eq!(clock.remaining(), Duration::ZERO);
+        assert_eq!(clock.periods(), 3);
+        assert!(!clock.is_flagged());
+
+        // a move made within the period keeps it, the next move starts a full one
+        clock.end_move();
+        clock.tick(secs(29));
+        assert_eq!(clock.periods(), 3);
+
+        // the first period runs out and the second one starts
+        clock.tick(secs(2));
+        assert_eq!(clock.periods(), 2);
+        assert!(!clock.is_flagged());
+
+        // the rest of the second period and the whole third one
+        clock.tick(secs(59));
+        assert_eq!(clock.periods(), 0);
+        assert!(clock.is_flagged());
+    }
+
+    #[test]
+    fn end_move_resets_the_period() {
+        let mut clock = clock(
+            0,
+            TimeBonus::ByoYomi {
+                periods: 1,
+                period: secs(30),
+            },
+        );
+
+        clock.tick(secs(20));
+        clock.end_move();
+        clock.tick(secs(20));
+        clock.end_move();
+        clock.tick(secs(20));
+
+        assert_eq!(clock.periods(), 1);
+        assert!(!clock.is_flagged());
+    }
+
+    #[test]
+    fn unlimited_clock_never_runs_out() {
+        let mut clock = Clock::new(TimeControl {
+            time: Duration::ZERO,
+            bonus: TimeBonus::None,
+            move_limit: Some(secs(1)),
+            unlimited: true,
+        });
+
+        clock.tick(secs(3600));
+
+        assert_eq!(clock.remaining(), Duration::ZERO);
+        assert!(!clock.is_flagged());
+    }
+
+    #[test]
+    fn time_controls_round_trip() {
+        let time_controls = [
+            TimeControl::default(),
+            TimeControl {
+                unlimited: true,
+                ..TimeControl::default()
+            },
+            TimeControl {
+                time: secs(180),
+                bonus: TimeBonus::Increment(secs(2)),
+                move_limit: None,
+                unlimited: false,
+            },
+            TimeControl {
+                time: secs(300),
+                bonus: TimeBonus::Delay(secs(3)),
+                move_limit: Some(secs(60)),
+                unlimited: false,
+            },
+            TimeControl {
+                time: secs(600),
+                bonus: TimeBonus::ByoYomi {
+                    periods: 3,
+                    period: secs(30),
+                },
+                move_limit: Some(secs(120)),
+                unlimited: false,
+            },
+        ];
+
+        for time_control in time_controls {
+            let text = format_time_control(&time_control).join(" ");
+            assert_eq!(
+                parse_time_control(text.split_whitespace()),
+                Ok(time_control)
+            );
+        }
+
+        assert_eq!(
+            format_time_control(&TimeControl {
+                time: secs(600),
+                bonus: TimeBonus::ByoYomi {
+                    periods: 3,
+                    period: secs(30),
+                },
+                move_limit: Some(secs(120)),
+                unlimited: false,
+            }),
+            ["600000", "byo-yomi 3 30000", "move-limit 120000"]
+        );
+    }
+
+    #[test]
+    fn the_options_of_time_controls_come_in_any_order() {
+        assert_eq!(
+            parse_time_control("60000 move-limit 5000 delay 2000".split_whitespace()),
+            Ok(TimeControl {
+                time: secs(60),
+                bonus: TimeBonus::Delay(secs(2)),
+                move_limit: Some(secs(5)),
+                unlimited: false,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_time_controls_are_rejected() {
+        let parse = |text: &str| parse_time_control(text.split_whitespace());
+
+        assert!(parse("").is_err());
+        assert!(parse("none 60000").is_err());
+        assert!(parse("soon").is_err());
+        assert!(parse("60000 increment").is_err());
+        assert!(parse("60000 increment 1000 delay 1000").is_err());
+        assert!(parse("60000 byo-yomi 3").is_err());
+        assert!(parse("60000 move-limit 1000 move-limit 2000").is_err());
+        assert!(parse("60000 overtime 1000").is_err());
+    }
+
+    #[test]
+    fn clock_states_round_trip() {
+        let state = ClockState {
+            remaining: Duration::from_millis(59_000),
+            periods: 3,
+        };
+
+        assert_eq!(format_clock_state(&state), "59000:3");
+        assert_eq!(parse_clock_state(Some("59000:3")), Ok(state));
+        assert!(parse_clock_state(Some("59000")).is_err());
+        assert!(parse_clock_state(None).is_err());
+    }
+}
@@ -1,6 +1,7 @@
+use crate::game::settings::Settings;
+use crate::game::tafl::chat::*;
 use crate::game::tafl::*;
 use crate::game::GameState;
-use bevy::utils::Duration;
 
 pub struct UiPlugin;
 
@@ -30,6 +31,9 @@ impl Plugin for UiPlugin {
                         update_game_timer.run_if(in_state(TaflState::Playing)),
                     )
                         .in_set(UiDynamicSet),
+                    show_side_labels
+                        .run_if(resource_changed::<Settings>)
+                        .in_set(UiSet),
                 ),
             )
             .insert_resource(TurnIndicators::default());
@@ -56,7 +60,7 @@ pub struct LoadingCircle {
 pub struct GameTimer {
     pub side: Side,
     pub active: bool,
-    pub timer: Timer,
+    pub clock: Clock,
 }
 
 #[derive(Event)]
@@ -83,6 +87,10 @@ pub fn indicate_turn(
                 if game_timer.side == side {
                     game_timer.active = true;
                 } else {
+                    // the move of the side is over
+                    if game_timer.active {
+                        game_timer.clock.end_move();
+                    }
                     game_timer.active = false;
                 }
             }
@@ -101,7 +109,7 @@ pub fn indicate_turn(
 #[derive(Event)]
 pub struct SetupGameUiEvent {
     pub side_with_initial_turn: Side,
-    pub timer_duration: Duration,
+    pub time_control: TimeControl,
 }
 
 // Sets up the game ui and turns it visible
@@ -113,8 +121,8 @@ pub fn setup_game_ui(
 ) {
     for ev in event.read() {
         for (mut game_timer, mut text) in &mut q_game_timer {
-            game_timer.timer = Timer::new(ev.timer_duration, TimerMode::Once);
-            set_timer_text(&game_timer.timer, &mut text);
+            game_timer.clock = Clock::new(ev.time_control.clone());
+            set_timer_text(&game_timer, &mut text);
         }
 
         for (loading_circle, mut loading_circle_visibility) in &mut q_loading_circle {
@@ -148,10 +156,10 @@ pub fn update_game_timer(
             continue;
         }
 
-        game_timer.timer.tick(time.delta());
-        set_timer_text(&game_timer.timer, &mut text);
+        game_timer.clock.tick(time.delta());
+        set_timer_text(&game_timer, &mut text);
 
-        if game_timer.timer.finished() {
+        if game_timer.clock.is_flagged() {
             if finished_sides.contains(&game_timer.side) {
                 continue;
             }
@@ -165,12 +173,12 @@ pub fn update_game_timer(
     }
 }
 
-fn set_timer_text(timer: &Timer, text: &mut Text) {
-    let remaining = timer.remaining().as_secs();
-    let mins = remaining / 60;
-    let secs = remaining % 60;
+pub fn set_timer_text(game_timer: &GameTimer, text: &mut Text) {
+    let value = game_timer.clock.text(game_timer.active);
 
-    text.sections[0].value = format!("{:0>2}:{:0>2}", mins, secs);
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
 }
 
 pub struct TurnIndicator {
@@ -208,8 +216,21 @@ impl Default for TurnIndicators {
 #[derive(Component)]
 pub struct GameUi;
 
+/// Names the side of a turn indicator, shown in the colorblind mode.
+#[derive(Component)]
+pub struct SideLabel;
+
+fn side_label_display(settings: &Settings) -> Display {
+    match settings.colorblind {
+        true => Display::Flex,
+        false => Display::None,
+    }
+}
+
 pub fn spawn_game_ui(
     turn_indicators: Res<TurnIndicators>,
+    players: Res<Players>,
+    settings: Res<Settings>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ) {
@@ -321,6 +342,28 @@ pub fn spawn_game_ui(
                                 ));
                             });
 
+                        // Side
+                        let side = match turn_indicator.side {
+                            Side::Attacker => "Attackers",
+                            Side::Defender => "Defenders",
+                        };
+
+                        parent.spawn((
+                            SideLabel,
+                            TextBundle::from_section(
+                                side,
+                                TextStyle {
+                                    font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                    font_size: 30.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            )
+                            .with_style(Style {
+                                display: side_label_display(&settings),
+                                ..default()
+                            }),
+                        ));
+
                         // Timer
                         parent.spawn((
                             TextBundle::from_section(
@@ -330,18 +373,33 @@ pub fn spawn_game_ui(
                                     font_size: 40.0,
                                     color: Color::rgb(0.9, 0.9, 0.9),
                                 },
-                            ),
+                            )
+                            .with_text_justify(JustifyText::Center),
                             GameTimer {
                                 side: turn_indicator.side,
                                 active: false,
-                                timer: Timer::default(),
+                                clock: Clock::default(),
                             },
                         ));
+
+                        // Chat
+                        if i == 1 && has_chat(&players) {
+                            spawn_chat(parent, &asset_server);
+                        }
                     });
             }
         });
 }
 
+pub fn show_side_labels(
+    settings: Res<Settings>,
+    mut q_side_label: Query<&mut Style, With<SideLabel>>,
+) {
+    for mut style in &mut q_side_label {
+        style.display = side_label_display(&settings);
+    }
+}
+
 pub fn despawn_game_ui(q_game_ui: Query<Entity, With<GameUi>>, mut commands: Commands) {
     let game_ui_entity = q_game_ui.single();
     commands.entity(game_ui_entity).despawn_recursive();
@@ -19,6 +19,7 @@ impl Plugin for VictoryUiPlugin {
 #[derive(Resource)]
 struct VictoryText {
     pub side_text_map: HashMap<Side, String>,
+    pub draw_text: String,
 }
 
 impl Default for VictoryText {
@@ -27,7 +28,10 @@ impl Default for VictoryText {
         map.insert(Side::Attacker, "Attacker wins!!!".to_string());
         map.insert(Side::Defender, "Defender wins!!!".to_string());
 
-        Self { side_text_map: map }
+        Self {
+            side_text_map: map,
+            draw_text: "Draw!".to_string(),
+        }
     }
 }
 
@@ -38,7 +42,7 @@ struct VictoryUi {
 
 #[derive(Event)]
 pub struct SpawnVictoryUiEvent {
-    pub winner: Side,
+    pub outcome: GameOutcome,
 }
 
 fn spawn_victory_ui(
@@ -79,7 +83,12 @@ fn spawn_victory_ui(
                         ..default()
                     })
                     .with_children(|parent| {
-                        let text = victory_text.side_text_map.get(&ev.winner).unwrap();
+                        let text = match ev.outcome {
+                            GameOutcome::Win(winner) => {
+                                victory_text.side_text_map.get(&winner).unwrap()
+                            }
+                            GameOutcome::Draw => &victory_text.draw_text,
+                        };
 
                         parent.spawn(TextBundle::from_section(
                             text,
@@ -14,28 +14,18 @@ pub struct KingOnCornerCheckEvent {
 
 pub fn king_on_corner_check(
     mut event: EventReader<KingOnCornerCheckEvent>,
-    q_board: Query<&Board>,
+    q_board: Query<(&Board, &TurnTracker)>,
     q_figure: Query<&Figure>,
     mut end_game_event: EventWriter<EndGameEvent>,
 ) {
     for ev in event.read() {
-        let board = q_board.get(ev.board_entity).unwrap();
+        let (board, turn_tracker) = q_board.get(ev.board_entity).unwrap();
+        let game = TaflGame::from_board(board, &q_figure, turn_tracker.side);
 
         // all defender kings are on an end position
-        let mut win = true;
-        for figure_entity in board.figures.values() {
-            let figure = q_figure.get(*figure_entity).unwrap();
-            if figure.side == Side::Defender && figure.kind == FigureKind::King {
-                if !board.end_positions.contains(&figure.position) {
-                    win = false;
-                    break;
-                }
-            }
-        }
-
-        if win {
+        if game.all_kings_on_end_positions() {
             end_game_event.send(EndGameEvent {
-                winner: Side::Defender,
+                outcome: GameOutcome::Win(Side::Defender),
             });
         }
     }
@@ -48,50 +38,18 @@ pub struct KingSurroundedCheckEvent {
 
 pub fn king_surrounded_check(
     mut event: EventReader<KingSurroundedCheckEvent>,
-    q_board: Query<&Board>,
+    q_board: Query<(&Board, &TurnTracker)>,
     q_figure: Query<&Figure>,
     mut end_game_event: EventWriter<EndGameEvent>,
 ) {
     for ev in event.read() {
-        let board = q_board.get(ev.board_entity).unwrap();
+        let (board, turn_tracker) = q_board.get(ev.board_entity).unwrap();
+        let game = TaflGame::from_board(board, &q_figure, turn_tracker.side);
 
         // all defender kings are surrounded
-        // being next to a wall, end_position or throne also counts as being surrounded.
-        let mut win = true;
-        for figure_entity in board.figures.values() {
-            let figure = q_figure.get(*figure_entity).unwrap();
-
-            if figure.side == Side::Defender && figure.kind == FigureKind::King {
-                let neighbors = board.get_neighbors2(figure.position);
-
-                for neighbor in neighbors.to_vec() {
-                    if let Neighbor::Empty { position } = neighbor {
-                        if !board.end_positions.contains(&position)
-                            && board.throne_position != position
-                        {
-                            win = false;
-                            break;
-                        }
-                    }
-
-                    if let Neighbor::Some {
-                        entity: neighbor_entity,
-                        ..
-                    } = neighbor
-                    {
-                        let neighbor_figure = q_figure.get(neighbor_entity).unwrap();
-                        if neighbor_figure.side != Side::Attacker {
-                            win = false;
-                            break;
-                        }
-                    }
-                }
-            }
-        }
-
-        if win {
+        if game.all_kings_surrounded() {
             end_game_event.send(EndGameEvent {
-                winner: Side::Attacker,
+                outcome: GameOutcome::Win(Side::Attacker),
             });
         }
     }
@@ -102,21 +60,37 @@ pub fn game_timer_check(
     mut end_game_event: EventWriter<EndGameEvent>,
 ) {
     for ev in event.read() {
-        if ev.side == Side::Attacker {
-            end_game_event.send(EndGameEvent {
-                winner: Side::Defender,
-            });
-        } else {
-            end_game_event.send(EndGameEvent {
-                winner: Side::Attacker,
-            });
+        end_game_event.send(EndGameEvent {
+            outcome: GameOutcome::Win(ev.side.opponent()),
+        });
+    }
+}
+
+#[derive(Event)]
+pub struct NoLegalMovesCheckEvent {
+    pub board_entity: Entity,
+}
+
+/// Ends the game if the side to move can't move any of its figures.
+pub fn no_legal_moves_check(
+    mut event: EventReader<NoLegalMovesCheckEvent>,
+    q_board: Query<(&Board, &TurnTracker)>,
+    q_figure: Query<&Figure>,
+    mut end_game_event: EventWriter<EndGameEvent>,
+) {
+    for ev in event.read() {
+        let (board, turn_tracker) = q_board.get(ev.board_entity).unwrap();
+        let game = TaflGame::from_board(board, &q_figure, turn_tracker.side);
+
+        if let Some(outcome) = game.no_legal_moves_outcome() {
+            end_game_event.send(EndGameEvent { outcome });
         }
     }
 }
 
 #[derive(Event)]
 pub struct EndGameEvent {
-    winner: Side,
+    pub outcome: GameOutcome,
 }
 
 pub fn on_game_end(
@@ -125,9 +99,15 @@ pub fn on_game_end(
     mut indicate_turn_event: EventWriter<IndicateTurnEvent>,
     mut spawn_victory_ui_event: EventWriter<SpawnVictoryUiEvent>,
 ) {
-    for ev in event.read() {
+    // a single move may satisfy multiple end conditions (e.g. the king escapes and the
+    // attackers are left without moves), only the first one that was detected counts
+    if let Some(ev) = event.read().next() {
         indicate_turn_event.send(IndicateTurnEvent { side: None });
-        spawn_victory_ui_event.send(SpawnVictoryUiEvent { winner: ev.winner });
+        spawn_victory_ui_event.send(SpawnVictoryUiEvent {
+            outcome: ev.outcome,
+        });
         next_tafl_state.set(TaflState::Ended);
     }
+
+    event.clear();
 }
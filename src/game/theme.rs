@@ -0,0 +1,406 @@
+use std::fs;
+use std::path::Path;
+
+use bevy::utils::HashMap;
+
+use crate::game::settings::*;
+use crate::game::tafl::figure::*;
+use crate::game::tafl::notation::parse_side;
+use crate::game::*;
+
+// the font the menus are spawned with, replaced by the font of the theme
+const DEFAULT_FONT: &str = "fonts/FiraSans-Bold.ttf";
+
+// the themes shipped with the game, the first one is the default
+const BUILT_IN_THEMES: &[(&str, &str)] = &[
+    ("classic", include_str!("../../assets/themes/classic.txt")),
+    ("dusk", include_str!("../../assets/themes/dusk.txt")),
+];
+
+// the starting areas in the colorblind palette, blue and vermilion of the Okabe-Ito palette are
+// told apart with every common kind of color blindness
+const COLORBLIND_DEFENDERS: Color = Color::rgb(0., 0.447, 0.698);
+const COLORBLIND_ATTACKERS: Color = Color::rgb(0.835, 0.369, 0.);
+
+// the highlights of the high contrast mode
+const HIGH_CONTRAST_HIGHLIGHT: Color = Color::rgb(1., 0.85, 0.);
+const HIGH_CONTRAST_SELECTION: Color = Color::rgb(1., 1., 1.);
+
+/// Loads the themes and applies the one chosen in the settings, also to a game in progress.
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(load_themes())
+            .add_systems(Startup, spawn_theme_assets)
+            .add_systems(
+                Update,
+                apply_theme
+                    .run_if(resource_exists::<ThemeAssets>.and_then(resource_changed::<Settings>)),
+            )
+            .add_systems(
+                PostUpdate,
+                apply_theme_font.run_if(resource_exists::<ThemeAssets>),
+            );
+    }
+}
+
+/// How the board, the figures and the text look.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    /// The colors of the fields, by the digits of `parse_board`.
+    pub fields: HashMap<u8, Color>,
+    pub border: Color,
+    /// The color of the fields the selected figure can move to.
+    pub highlight: Color,
+    pub selection: Color,
+    /// The asset paths of the textures of the figures.
+    pub figures: HashMap<FigureType, String>,
+    /// The asset path of the font of the text.
+    pub font: String,
+}
+
+impl Theme {
+    /// Parses a theme file, a line per color, texture or font, e.g. `field 0 #82a29d`.
+    pub fn parse(name: &str, data: &str) -> Result<Self, &'static str> {
+        let mut fields = HashMap::new();
+        let mut border = None;
+        let mut highlight = None;
+        let mut selection = None;
+        let mut figures = HashMap::new();
+        let mut font = None;
+
+        for line in data.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut tokens = line.split_whitespace();
+
+            match tokens.next() {
+                Some("field") => {
+                    let digit = tokens
+                        .next()
+                        .and_then(|digit| digit.parse::<u8>().ok())
+                        .filter(|digit| *digit <= 9)
+                        .ok_or("a field should be a digit of the board structure")?;
+
+                    fields.insert(digit, parse_color(tokens.next())?);
+                }
+                Some("border") => border = Some(parse_color(tokens.next())?),
+                Some("highlight") => highlight = Some(parse_color(tokens.next())?),
+                Some("selection") => selection = Some(parse_color(tokens.next())?),
+                Some("figure") => {
+                    let side = parse_side(tokens.next().unwrap_or_default())?;
+                    let kind = match tokens.next() {
+                        Some("king") => FigureKind::King,
+                        Some("soldier") => FigureKind::Soldier,
+                        _ => return Err("the kind of a figure should be king or soldier"),
+                    };
+                    let texture = tokens.next().ok_or("a figure should have a texture")?;
+
+                    figures.insert(FigureType { side, kind }, texture.to_string());
+                }
+                Some("font") => {
+                    font = Some(
+                        tokens
+                            .next()
+                            .ok_or("the font should have a path")?
+                            .to_string(),
+                    );
+                }
+                _ => return Err("unknown line in the theme"),
+            }
+
+            if tokens.next().is_some() {
+                return Err("too many values on a line of the theme");
+            }
+        }
+
+        Ok(Theme {
+            name: name.to_string(),
+            fields,
+            border: border.ok_or("the theme should have a border color")?,
+            highlight: highlight.ok_or("the theme should have a highlight color")?,
+            selection: selection.ok_or("the theme should have a selection color")?,
+            figures,
+            font: font.unwrap_or(DEFAULT_FONT.to_string()),
+        })
+    }
+}
+
+/// Adjusts the colors of a theme to the accessibility settings.
+pub fn with_accessibility(theme: &Theme, settings: &Settings) -> Theme {
+    let mut theme = theme.clone();
+
+    if settings.colorblind {
+        theme.fields.insert(2, COLORBLIND_DEFENDERS);
+        theme.fields.insert(3, COLORBLIND_ATTACKERS);
+    }
+
+    if settings.high_contrast {
+        theme.highlight = HIGH_CONTRAST_HIGHLIGHT;
+        theme.selection = HIGH_CONTRAST_SELECTION;
+    }
+
+    theme
+}
+
+fn parse_color(data: Option<&str>) -> Result<Color, &'static str> {
+    data.and_then(|data| Color::hex(data).ok())
+        .ok_or("colors should look like #82a29d or #00000099")
+}
+
+/// All the themes that can be chosen, the built-in ones first.
+#[derive(Resource, Debug, Clone)]
+pub struct Themes(pub Vec<Theme>);
+
+impl Themes {
+    /// Gets the theme called `name`, the default one if there is none.
+    pub fn get(&self, name: &str) -> &Theme {
+        self.0
+            .iter()
+            .find(|theme| theme.name == name)
+            .unwrap_or(&self.0[0])
+    }
+
+    /// Gets the name of the theme after `name`, starting over after the last one.
+    pub fn next_name(&self, name: &str) -> &str {
+        let next = self
+            .0
+            .iter()
+            .position(|theme| theme.name == name)
+            .map_or(0, |current| (current + 1) % self.0.len());
+
+        &self.0[next].name
+    }
+}
+
+/// Loads the built-in themes and the ones in the `themes` directory of the config directory,
+/// a theme file there replaces the built-in theme of the same name.
+pub fn load_themes() -> Themes {
+    let mut themes: Vec<Theme> = BUILT_IN_THEMES
+        .iter()
+        .map(|(name, data)| Theme::parse(name, data).expect("built-in themes should be valid"))
+        .collect();
+
+    for mut theme in read_theme_dir(&config_dir().join("themes")) {
+        fill_missing_figures(&mut theme, &themes[0]);
+
+        match themes.iter_mut().find(|other| other.name == theme.name) {
+            Some(other) => *other = theme,
+            None => themes.push(theme),
+        }
+    }
+
+    Themes(themes)
+}
+
+// the figures a theme has no texture for are spawned with the textures of the default theme
+fn fill_missing_figures(theme: &mut Theme, default: &Theme) {
+    for (figure_type, texture) in &default.figures {
+        theme
+            .figures
+            .entry(*figure_type)
+            .or_insert_with(|| texture.clone());
+    }
+}
+
+fn read_theme_dir(dir: &Path) -> Vec<Theme> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "txt"))
+        .collect();
+    paths.sort();
+
+    let mut themes = vec![];
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let theme = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| Theme::parse(name, &data).map_err(str::to_string));
+
+        match theme {
+            Ok(theme) => themes.push(theme),
+            Err(err) => warn!("failed to load the theme {}: {}", path.display(), err),
+        }
+    }
+
+    themes
+}
+
+/// The assets of the theme in use, the board and the figures are spawned with these.
+///
+/// The materials are shared by everything spawned with them, so that changing the theme only has
+/// to change the colors of the materials.
+#[derive(Resource)]
+pub struct ThemeAssets {
+    pub name: String,
+    pub fields: HashMap<u8, Handle<ColorMaterial>>,
+    pub border: Handle<ColorMaterial>,
+    pub highlight: Handle<ColorMaterial>,
+    pub selection: Handle<ColorMaterial>,
+    pub figures: HashMap<FigureType, Handle<Image>>,
+    pub font: Handle<Font>,
+}
+
+fn figure_textures(
+    theme: &Theme,
+    asset_server: &AssetServer,
+) -> HashMap<FigureType, Handle<Image>> {
+    theme
+        .figures
+        .iter()
+        .map(|(figure_type, path)| (*figure_type, asset_server.load(path.clone())))
+        .collect()
+}
+
+fn spawn_theme_assets(
+    themes: Res<Themes>,
+    settings: Res<Settings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+) {
+    let theme = &with_accessibility(themes.get(&settings.theme), &settings);
+
+    // every digit gets a material, the ones the theme has no color for are left white
+    let fields = (0..=9)
+        .map(|digit| {
+            let color = theme.fields.get(&digit).copied().unwrap_or(Color::WHITE);
+            (digit, materials.add(color))
+        })
+        .collect();
+
+    commands.insert_resource(ThemeAssets {
+        name: theme.name.clone(),
+        fields,
+        border: materials.add(theme.border),
+        highlight: materials.add(theme.highlight),
+        selection: materials.add(theme.selection),
+        figures: figure_textures(theme, &asset_server),
+        font: asset_server.load(theme.font.clone()),
+    });
+}
+
+/// Switches to the theme and the accessibility settings chosen in the settings without
+/// respawning anything.
+fn apply_theme(
+    settings: Res<Settings>,
+    themes: Res<Themes>,
+    mut theme_assets: ResMut<ThemeAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut q_figure: Query<(&Figure, &mut Handle<Image>)>,
+    mut q_text: Query<&mut Text>,
+    asset_server: Res<AssetServer>,
+) {
+    let theme = &with_accessibility(themes.get(&settings.theme), &settings);
+
+    let mut set_color = |handle: &Handle<ColorMaterial>, color: Color| {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    };
+
+    for (digit, handle) in &theme_assets.fields {
+        set_color(
+            handle,
+            theme.fields.get(digit).copied().unwrap_or(Color::WHITE),
+        );
+    }
+    set_color(&theme_assets.border, theme.border);
+    set_color(&theme_assets.highlight, theme.highlight);
+    set_color(&theme_assets.selection, theme.selection);
+
+    // the colors are all that changes with the accessibility settings
+    if theme_assets.name == theme.name {
+        return;
+    }
+
+    theme_assets.figures = figure_textures(theme, &asset_server);
+
+    for (figure, mut texture) in &mut q_figure {
+        let figure_type = FigureType {
+            side: figure.side,
+            kind: figure.kind,
+        };
+
+        if let Some(new_texture) = theme_assets.figures.get(&figure_type) {
+            *texture = new_texture.clone();
+        }
+    }
+
+    let previous_font = theme_assets.font.clone();
+    theme_assets.font = asset_server.load(theme.font.clone());
+
+    for mut text in &mut q_text {
+        for section in &mut text.sections {
+            if section.style.font == previous_font {
+                section.style.font = theme_assets.font.clone();
+            }
+        }
+    }
+
+    theme_assets.name = theme.name.clone();
+}
+
+/// Replaces the default font of new text with the font of the theme.
+fn apply_theme_font(
+    theme_assets: Res<ThemeAssets>,
+    mut q_text: Query<&mut Text, Added<Text>>,
+    asset_server: Res<AssetServer>,
+) {
+    let default_font = asset_server.load(DEFAULT_FONT);
+
+    if theme_assets.font == default_font {
+        return;
+    }
+
+    for mut text in &mut q_text {
+        for section in &mut text.sections {
+            if section.style.font == default_font {
+                section.style.font = theme_assets.font.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn figures_missing_in_a_theme_use_the_default_textures() {
+        let default = Theme::parse("classic", BUILT_IN_THEMES[0].1).unwrap();
+        let mut theme = Theme::parse(
+            "custom",
+            "border #000000\n\
+             highlight #ffffff\n\
+             selection #ffffff\n\
+             figure defenders king custom/king.png",
+        )
+        .unwrap();
+
+        fill_missing_figures(&mut theme, &default);
+
+        let king = FigureType {
+            side: Side::Defender,
+            kind: FigureKind::King,
+        };
+        assert_eq!(theme.figures[&king], "custom/king.png");
+
+        for (figure_type, texture) in &default.figures {
+            if *figure_type != king {
+                assert_eq!(&theme.figures[figure_type], texture);
+            }
+        }
+    }
+}
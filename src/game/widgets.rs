@@ -0,0 +1,74 @@
+use bevy::prelude::*;
+
+// the colors of buttons, while the mouse is away and while it is over them or pressing them
+pub const BUTTON_COLOR: Color = Color::rgb(78. / 255., 112. / 255., 165. / 255.);
+const ACTIVE_BUTTON_COLOR: Color = Color::rgb(157. / 255., 79. / 255., 79. / 255.);
+
+/// The style of the labels and texts of the menus.
+pub fn text_style(asset_server: &AssetServer, font_size: f32) -> TextStyle {
+    TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    }
+}
+
+/// The size of a button and of its label.
+#[derive(Clone, Copy)]
+pub struct ButtonSize {
+    pub width: f32,
+    pub height: f32,
+    pub font_size: f32,
+}
+
+impl ButtonSize {
+    /// A button with the height and label of the buttons of the menus.
+    pub const fn with_width(width: f32) -> Self {
+        Self {
+            width,
+            height: 40.,
+            font_size: 24.,
+        }
+    }
+}
+
+/// Spawns a button labeled with `label` below `parent`, `button` tells which one was pressed.
+pub fn spawn_button(
+    parent: &mut ChildBuilder,
+    button: impl Component,
+    label: impl Into<String>,
+    size: ButtonSize,
+    asset_server: &AssetServer,
+) {
+    parent
+        .spawn((
+            button,
+            ButtonBundle {
+                style: Style {
+                    width: Val::Px(size.width),
+                    height: Val::Px(size.height),
+                    margin: UiRect::all(Val::Px(5.)),
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                text_style(asset_server, size.font_size),
+            ));
+        });
+}
+
+/// Gets the color of a button for the `interaction` with it.
+pub fn button_interaction_color(interaction: Interaction) -> BackgroundColor {
+    match interaction {
+        Interaction::Pressed | Interaction::Hovered => ACTIVE_BUTTON_COLOR,
+        Interaction::None => BUTTON_COLOR,
+    }
+    .into()
+}
@@ -0,0 +1,4 @@
+pub mod game;
+pub mod self_play;
+pub mod server;
+pub mod tournament;
@@ -1,18 +1,68 @@
-#![windows_subsystem = "windows"]
+#![cfg_attr(feature = "windowed", windows_subsystem = "windows")]
 
 use bevy::{prelude::*, window::PrimaryWindow, DefaultPlugins};
 // use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use game::GamePlugin;
+use hnefatafl::game::GamePlugin;
 
-mod game;
+mod cli;
+mod engine;
+mod tui;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins)
+    let options = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if options.help {
+        println!("{}", cli::USAGE);
+        return;
+    }
+
+    if options.engine {
+        if let Err(err) = engine::run() {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if options.tui {
+        if let Err(err) = tui::run(options.time_control.unwrap_or_default(), options.game_setup) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut app = App::new();
+
+    app.add_plugins(DefaultPlugins)
         // .add_plugins(WorldInspectorPlugin::new())
         .add_plugins(GamePlugin)
-        .add_systems(Startup, set_window_title)
-        .run();
+        .insert_resource(options.players)
+        .add_systems(Startup, set_window_title);
+
+    if let Some(network_mode) = options.network_mode {
+        app.insert_resource(network_mode);
+    }
+
+    if let Some(online_server) = options.online_server {
+        app.insert_resource(online_server);
+    }
+
+    if let Some(time_control) = options.time_control {
+        app.insert_resource(time_control);
+    }
+
+    if let Some(game_setup) = options.game_setup {
+        app.insert_resource(game_setup);
+    }
+
+    app.run();
 }
 
 fn set_window_title(mut q_window: Query<&mut Window, With<PrimaryWindow>>) {
@@ -0,0 +1,289 @@
+//! Generates training data for evaluation functions by letting the built-in AI play against itself.
+//!
+//! Every searched position of a game becomes a sample holding the score the search gave it and
+//! the result the game had in the end, both from the perspective of the side to move.
+
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::game::tafl::ai::*;
+use crate::game::tafl::board::Position;
+use crate::game::tafl::figure::*;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::*;
+
+// In the binary format the file starts with the bytes `HTSP`, the number of rows and the number
+// of columns. Every sample that follows takes rows * cols + 7 bytes:
+//
+// - a byte for each square, row by row from the top: 0 empty, 1 attacker soldier, 2 attacker
+//   king, 3 defender soldier, 4 defender king
+// - the side to move, 0 for the attackers and 1 for the defenders
+// - the score as a little-endian i32
+// - the depth of the search that found the score, 0 if no search completed and the score is the
+//   evaluation of the position, capped at 255
+// - the result as an i8
+
+const BINARY_MAGIC: &[u8; 4] = b"HTSP";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleFormat {
+    /// A JSON object per line, see `Sample`.
+    Jsonl,
+    /// Fixed size records, see the comment on the format.
+    Binary,
+}
+
+pub struct SelfPlayOptions {
+    /// The number of games to play.
+    pub games: usize,
+    pub search: SearchOptions,
+    /// The number of random moves played at the start of each game, so the games differ.
+    pub random_moves: usize,
+    /// Games without a winner after this many moves are draws.
+    pub max_moves: usize,
+    pub variant: String,
+    pub format: SampleFormat,
+    pub output: PathBuf,
+    /// The seed of the random moves, None to pick one.
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Sample {
+    /// The position string, see `notation`.
+    pub position: String,
+    /// `attackers` or `defenders`.
+    pub side: String,
+    /// The score of the search from the perspective of the side to move.
+    pub score: i32,
+    /// The depth of the search that found the score.
+    pub depth: u32,
+    /// 1 if the side to move won the game in the end, -1 if it lost and 0 for a draw.
+    pub result: i8,
+}
+
+/// Plays the games and writes their samples to the output file.
+pub fn run(options: &SelfPlayOptions) -> io::Result<()> {
+    let (rules, initial_position) = variant(&options.variant)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let figures = parse_position(initial_position)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+        .figures;
+
+    let seed = options.seed.unwrap_or_else(random_seed);
+    let mut rng = Rng::new(seed);
+    println!("seed {}", seed);
+
+    let mut output = BufWriter::new(File::create(&options.output)?);
+
+    if options.format == SampleFormat::Binary {
+        output.write_all(BINARY_MAGIC)?;
+        output.write_all(&[rules.rows as u8, rules.cols as u8])?;
+    }
+
+    let mut total = 0;
+
+    for i in 0..options.games {
+        let game = TaflGame::new(rules.clone(), &figures, Side::Attacker)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        let (samples, outcome, moves) = play_game(game, options, &mut rng);
+
+        for (game, sample) in &samples {
+            match options.format {
+                SampleFormat::Jsonl => {
+                    serde_json::to_writer(&mut output, sample)?;
+                    output.write_all(b"\n")?;
+                }
+                SampleFormat::Binary => write_binary_sample(&mut output, game, sample)?,
+            }
+        }
+
+        total += samples.len();
+
+        println!(
+            "game {}/{}: {} moves, {}, {} samples",
+            i + 1,
+            options.games,
+            moves,
+            match outcome {
+                GameOutcome::Win(side) => format!("{} won", format_side(side)),
+                GameOutcome::Draw => "draw".to_string(),
+            },
+            samples.len()
+        );
+    }
+
+    output.flush()?;
+    println!("wrote {} samples to {}", total, options.output.display());
+
+    Ok(())
+}
+
+/// Plays a game to the end, returning the searched positions with their samples, the outcome and
+/// the number of moves made.
+fn play_game(
+    mut game: TaflGame,
+    options: &SelfPlayOptions,
+    rng: &mut Rng,
+) -> (Vec<(TaflGame, Sample)>, GameOutcome, usize) {
+    let mut samples = vec![];
+    let mut moves = 0;
+
+    let outcome = loop {
+        if let Some(outcome) = game.outcome() {
+            break outcome;
+        }
+
+        if options.max_moves <= moves {
+            break GameOutcome::Draw;
+        }
+
+        let mv = if moves < options.random_moves {
+            let legal_moves = game.legal_moves();
+            legal_moves.get(rng.below(legal_moves.len())).copied()
+        } else {
+            let result = search(&game, &options.search);
+
+            if result.best_move.is_some() {
+                samples.push((
+                    game.clone(),
+                    Sample {
+                        position: format_position(
+                            &game.iter_figures().collect(),
+                            game.rules().rows,
+                            game.rules().cols,
+                        ),
+                        side: format_side(game.side_to_move()).to_string(),
+                        score: result.score,
+                        depth: result.depth,
+                        result: 0,
+                    },
+                ));
+            }
+
+            result.best_move
+        };
+
+        // the rules decide the outcome of positions without legal moves
+        let Some(mv) = mv else {
+            break GameOutcome::Win(game.side_to_move().opponent());
+        };
+
+        game.make_move(mv).unwrap();
+        moves += 1;
+    };
+
+    for (game, sample) in &mut samples {
+        sample.result = match outcome {
+            GameOutcome::Win(side) if side == game.side_to_move() => 1,
+            GameOutcome::Win(_) => -1,
+            GameOutcome::Draw => 0,
+        };
+    }
+
+    (samples, outcome, moves)
+}
+
+fn write_binary_sample(
+    output: &mut impl Write,
+    game: &TaflGame,
+    sample: &Sample,
+) -> io::Result<()> {
+    let rows = game.rules().rows;
+    let cols = game.rules().cols;
+
+    let mut bytes = Vec::with_capacity(rows * cols + 7);
+
+    for y in 0..rows {
+        for x in 0..cols {
+            bytes.push(match game.get(Position { x, y }) {
+                None => 0,
+                Some(figure_type) => match (figure_type.side, figure_type.kind) {
+                    (Side::Attacker, FigureKind::Soldier) => 1,
+                    (Side::Attacker, FigureKind::King) => 2,
+                    (Side::Defender, FigureKind::Soldier) => 3,
+                    (Side::Defender, FigureKind::King) => 4,
+                },
+            });
+        }
+    }
+
+    bytes.push(match game.side_to_move() {
+        Side::Attacker => 0,
+        Side::Defender => 1,
+    });
+    bytes.extend_from_slice(&sample.score.to_le_bytes());
+    bytes.push(sample.depth.min(u8::MAX as u32) as u8);
+    bytes.push(sample.result as u8);
+
+    output.write_all(&bytes)
+}
+
+fn random_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish()
+}
+
+/// A small xorshift generator, good enough for picking opening moves.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift never leaves 0
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Gets a number in `0..n`, 0 if `n` is 0.
+    fn below(&mut self, n: usize) -> usize {
+        match n {
+            0 => 0,
+            _ => (self.next() % n as u64) as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_samples_hold_the_depth() {
+        let (rules, position) = variant("hnefatafl").unwrap();
+        let position = parse_position(position).unwrap();
+        let game = TaflGame::new(rules, &position.figures, Side::Defender).unwrap();
+
+        let sample = Sample {
+            position: String::new(),
+            side: format_side(Side::Defender).to_string(),
+            score: -300,
+            depth: 4,
+            result: -1,
+        };
+
+        let mut bytes = vec![];
+        write_binary_sample(&mut bytes, &game, &sample).unwrap();
+
+        assert_eq!(bytes.len(), 11 * 11 + 7);
+        // the king on the throne
+        assert_eq!(bytes[5 * 11 + 5], 4);
+        assert_eq!(bytes[121..], [1, 0xd4, 0xfe, 0xff, 0xff, 4, 0xff]);
+    }
+}
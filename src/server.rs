@@ -0,0 +1,1080 @@
+//! Headless server hosting many games at once for clients connected over WebSockets, the messages
+//! are described in `server_protocol`.
+//!
+//! Besides the games themselves the server runs a lobby, in which registered players challenge
+//! each other or wait in a queue to be matched with someone wanting to play the same variant and
+//! time control.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tungstenite::{Message, WebSocket};
+
+use crate::game::tafl::chat::MAX_MESSAGE_LENGTH;
+use crate::game::tafl::figure::Side;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::rules::{self, *};
+use crate::game::tafl::server_protocol::*;
+use crate::game::tafl::time_control::{Clock, TimeBonus, TimeControl};
+
+// how often the connections check for messages to send and the clocks for timeouts
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Accepts clients on `port` until the process is stopped.
+pub fn run(port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", port))?;
+    let server = Arc::new(Mutex::new(Server::default()));
+
+    println!("listening on port {}", port);
+
+    {
+        let server = server.clone();
+        thread::spawn(move || loop {
+            thread::sleep(POLL_INTERVAL);
+            server.lock().unwrap().check_clocks(Instant::now());
+        });
+    }
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("failed to accept a connection: {}", err);
+                continue;
+            }
+        };
+
+        let server = server.clone();
+        thread::spawn(move || {
+            if let Err(err) = handle_connection(stream, server) {
+                eprintln!("connection closed: {}", err);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+fn handle_connection(stream: TcpStream, server: Arc<Mutex<Server>>) -> io::Result<()> {
+    let mut socket = tungstenite::accept(stream.try_clone()?)
+        .map_err(|err| io::Error::other(err.to_string()))?;
+
+    // reads time out so the messages of other clients can be sent in between
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (sender, outbox) = mpsc::channel();
+    let client_id = server.lock().unwrap().connect(sender);
+
+    let result = serve(&mut socket, &outbox, client_id, &server);
+    server.lock().unwrap().disconnect(client_id);
+
+    result
+}
+
+fn serve(
+    socket: &mut WebSocket<TcpStream>,
+    outbox: &Receiver<ServerMessage>,
+    client_id: ClientId,
+    server: &Mutex<Server>,
+) -> io::Result<()> {
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                server
+                    .lock()
+                    .unwrap()
+                    .handle_message(client_id, &text, Instant::now());
+            }
+            Ok(_) => (),
+            Err(tungstenite::Error::Io(err))
+                if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(tungstenite::Error::ConnectionClosed) => return Ok(()),
+            Err(err) => return Err(io::Error::other(err)),
+        }
+
+        for message in outbox.try_iter() {
+            let text = serde_json::to_string(&message).expect("messages should be serializable");
+            socket.send(Message::Text(text)).map_err(io::Error::other)?;
+        }
+    }
+}
+
+type ClientId = u64;
+
+/// The clocks of both sides.
+struct GameClocks {
+    attacker: Clock,
+    defender: Clock,
+    // when the side to move started thinking
+    turn_started: Instant,
+}
+
+impl GameClocks {
+    fn new(time_control: &TimeControl, now: Instant) -> Self {
+        Self {
+            attacker: Clock::new(time_control.clone()),
+            defender: Clock::new(time_control.clone()),
+            turn_started: now,
+        }
+    }
+
+    fn get_mut(&mut self, side: Side) -> &mut Clock {
+        match side {
+            Side::Attacker => &mut self.attacker,
+            Side::Defender => &mut self.defender,
+        }
+    }
+
+    /// Gets the clock of `side` as it is at `now`.
+    fn at(&self, side: Side, side_to_move: Side, now: Instant) -> Clock {
+        let mut clock = match side {
+            Side::Attacker => self.attacker.clone(),
+            Side::Defender => self.defender.clone(),
+        };
+
+        if side == side_to_move {
+            clock.tick(now - self.turn_started);
+        }
+
+        clock
+    }
+
+    /// Stops the clock of `side`, granting its bonus, and starts the one of its opponent.
+    fn switch(&mut self, side: Side, now: Instant) {
+        let elapsed = now - self.turn_started;
+
+        let clock = self.get_mut(side);
+        clock.tick(elapsed);
+        clock.end_move();
+
+        self.turn_started = now;
+    }
+}
+
+struct Client {
+    sender: Sender<ServerMessage>,
+    // None until the client registered
+    name: Option<String>,
+}
+
+/// A player waiting to be matched with an opponent.
+struct QueuedPlayer {
+    client_id: ClientId,
+    variant: String,
+    time_control: TimeControl,
+}
+
+struct ServerGame {
+    game: TaflGame,
+    variant: String,
+    players: HashMap<Side, ClientId>,
+    time_control: TimeControl,
+    // None until both players joined
+    clock: Option<GameClocks>,
+    draw_offer: Option<Side>,
+}
+
+impl ServerGame {
+    fn side_of(&self, client_id: ClientId) -> Option<Side> {
+        self.players
+            .iter()
+            .find(|(_, player)| **player == client_id)
+            .map(|(side, _)| *side)
+    }
+}
+
+#[derive(Default)]
+struct Server {
+    clients: HashMap<ClientId, Client>,
+    games: HashMap<u64, ServerGame>,
+    // in the order the players joined
+    queue: Vec<QueuedPlayer>,
+    next_client_id: ClientId,
+    next_game_id: u64,
+}
+
+impl Server {
+    fn connect(&mut self, sender: Sender<ServerMessage>) -> ClientId {
+        let client_id = self.next_client_id;
+        self.next_client_id += 1;
+        self.clients
+            .insert(client_id, Client { sender, name: None });
+
+        client_id
+    }
+
+    /// Forgets the client, the games it was playing are lost.
+    fn disconnect(&mut self, client_id: ClientId) {
+        self.clients.remove(&client_id);
+        self.queue.retain(|queued| queued.client_id != client_id);
+
+        let game_ids: Vec<u64> = self
+            .games
+            .iter()
+            .filter(|(_, game)| game.side_of(client_id).is_some())
+            .map(|(game_id, _)| *game_id)
+            .collect();
+
+        for game_id in game_ids {
+            let game = &self.games[&game_id];
+            let side = game.side_of(client_id).unwrap();
+
+            if game.clock.is_some() {
+                self.end_game(
+                    game_id,
+                    GameResult::from(GameOutcome::Win(side.opponent())),
+                    GameOverReason::Abandoned,
+                );
+            } else {
+                self.games.remove(&game_id);
+            }
+        }
+    }
+
+    fn send(&self, client_id: ClientId, message: ServerMessage) {
+        if let Some(client) = self.clients.get(&client_id) {
+            _ = client.sender.send(message);
+        }
+    }
+
+    /// Gets the name of the client, which is needed to play.
+    fn name(&self, client_id: ClientId) -> Result<&str, &'static str> {
+        self.clients[&client_id]
+            .name
+            .as_deref()
+            .ok_or("register a name first")
+    }
+
+    fn broadcast(&self, game_id: u64, message: ServerMessage) {
+        for client_id in self.games[&game_id].players.values() {
+            self.send(*client_id, message.clone());
+        }
+    }
+
+    fn handle_message(&mut self, client_id: ClientId, text: &str, now: Instant) {
+        let message: ClientMessage = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(err) => {
+                self.send(
+                    client_id,
+                    ServerMessage::Error {
+                        message: format!("invalid message: {}", err),
+                    },
+                );
+                return;
+            }
+        };
+
+        let result = match message {
+            ClientMessage::Register { name } => self.register(client_id, name),
+            ClientMessage::ListGames {
+                variant,
+                time_ms,
+                bonus,
+                move_limit_ms,
+            } => {
+                let time_control =
+                    time_ms.map(|time_ms| time_control(time_ms, bonus, move_limit_ms));
+                self.list_games(client_id, variant, time_control);
+                Ok(())
+            }
+            ClientMessage::CreateGame {
+                variant,
+                side,
+                time_ms,
+                bonus,
+                move_limit_ms,
+            } => self.create_game(
+                client_id,
+                variant,
+                side,
+                time_control(time_ms, bonus, move_limit_ms),
+            ),
+            ClientMessage::JoinGame { game_id } => self.join_game(client_id, game_id, now),
+            ClientMessage::JoinQueue {
+                variant,
+                time_ms,
+                bonus,
+                move_limit_ms,
+            } => self.join_queue(
+                client_id,
+                variant,
+                time_control(time_ms, bonus, move_limit_ms),
+                now,
+            ),
+            ClientMessage::LeaveQueue => self.leave_queue(client_id),
+            ClientMessage::Move { game_id, from, to } => {
+                self.make_move(client_id, game_id, &from, &to, now)
+            }
+            ClientMessage::Resign { game_id } => self.resign(client_id, game_id),
+            ClientMessage::OfferDraw { game_id } => self.offer_draw(client_id, game_id),
+            ClientMessage::DeclineDraw { game_id } => self.decline_draw(client_id, game_id),
+            ClientMessage::Chat { game_id, text } => self.chat(client_id, game_id, text),
+        };
+
+        if let Err(err) = result {
+            self.send(
+                client_id,
+                ServerMessage::Error {
+                    message: err.to_string(),
+                },
+            );
+        }
+    }
+
+    fn register(&mut self, client_id: ClientId, name: String) -> Result<(), &'static str> {
+        let is_valid = !name.is_empty()
+            && name.chars().count() <= MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+        if !is_valid {
+            return Err("names should consist of at most 20 letters, digits, `-` or `_`");
+        }
+
+        let is_taken = self
+            .clients
+            .iter()
+            .any(|(other_id, other)| *other_id != client_id && other.name.as_ref() == Some(&name));
+
+        if is_taken {
+            return Err("the name is taken");
+        }
+
+        self.clients.get_mut(&client_id).unwrap().name = Some(name.clone());
+        self.send(client_id, ServerMessage::Registered { name });
+
+        Ok(())
+    }
+
+    fn list_games(
+        &self,
+        client_id: ClientId,
+        variant: Option<String>,
+        time_control: Option<TimeControl>,
+    ) {
+        let mut games: Vec<OpenGame> = self
+            .games
+            .iter()
+            .filter(|(_, game)| game.clock.is_none())
+            .filter(|(_, game)| variant.as_ref().is_none_or(|variant| game.variant == *variant))
+            .filter(|(_, game)| {
+                time_control
+                    .as_ref()
+                    .is_none_or(|time_control| game.time_control == *time_control)
+            })
+            .map(|(game_id, game)| {
+                let (side, creator) = game.players.iter().next().unwrap();
+
+                OpenGame {
+                    game_id: *game_id,
+                    variant: game.variant.clone(),
+                    time_ms: game.time_control.time.as_millis() as u64,
+                    bonus: Bonus::from_time_bonus(game.time_control.bonus),
+                    move_limit_ms: move_limit_ms(&game.time_control),
+                    creator: self.clients[creator].name.clone().unwrap_or_default(),
+                    side: *side,
+                }
+            })
+            .collect();
+
+        games.sort_by_key(|game| game.game_id);
+
+        self.send(client_id, ServerMessage::GameList { games });
+    }
+
+    /// Adds a game waiting for an opponent.
+    fn add_game(
+        &mut self,
+        variant: String,
+        time_control: TimeControl,
+        players: HashMap<Side, ClientId>,
+    ) -> Result<u64, &'static str> {
+        check_time_control(&time_control)?;
+
+        let (rules, position) = rules::variant(&variant)?;
+        let position = parse_position(position)?;
+        let game = TaflGame::new(rules, &position.figures, Side::Attacker)?;
+
+        let game_id = self.next_game_id;
+        self.next_game_id += 1;
+
+        self.games.insert(
+            game_id,
+            ServerGame {
+                game,
+                variant,
+                players,
+                time_control,
+                clock: None,
+                draw_offer: None,
+            },
+        );
+
+        Ok(game_id)
+    }
+
+    fn create_game(
+        &mut self,
+        client_id: ClientId,
+        variant: String,
+        side: Side,
+        time_control: TimeControl,
+    ) -> Result<(), &'static str> {
+        self.name(client_id)?;
+
+        let game_id = self.add_game(
+            variant.clone(),
+            time_control.clone(),
+            HashMap::from([(side, client_id)]),
+        )?;
+
+        self.send(
+            client_id,
+            ServerMessage::GameCreated {
+                game_id,
+                variant,
+                side,
+                time_ms: time_control.time.as_millis() as u64,
+                bonus: Bonus::from_time_bonus(time_control.bonus),
+                move_limit_ms: move_limit_ms(&time_control),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn join_game(
+        &mut self,
+        client_id: ClientId,
+        game_id: u64,
+        now: Instant,
+    ) -> Result<(), &'static str> {
+        self.name(client_id)?;
+
+        let Some(game) = self.games.get_mut(&game_id) else {
+            return Err("the game doesn't exist");
+        };
+
+        if game.clock.is_some() {
+            return Err("the game already started");
+        }
+
+        let creator_side = *game.players.keys().next().unwrap();
+        if game.players[&creator_side] == client_id {
+            return Err("the game can't be joined by its creator");
+        }
+
+        game.players.insert(creator_side.opponent(), client_id);
+        self.start_game(game_id, now);
+
+        Ok(())
+    }
+
+    fn join_queue(
+        &mut self,
+        client_id: ClientId,
+        variant: String,
+        time_control: TimeControl,
+        now: Instant,
+    ) -> Result<(), &'static str> {
+        self.name(client_id)?;
+        rules::variant(&variant)?;
+        check_time_control(&time_control)?;
+
+        self.queue.retain(|queued| queued.client_id != client_id);
+
+        let opponent = self.queue.iter().position(|queued| {
+            queued.variant == variant && queued.time_control == time_control
+        });
+
+        let Some(opponent) = opponent else {
+            self.send(
+                client_id,
+                ServerMessage::Queued {
+                    variant: variant.clone(),
+                    time_ms: time_control.time.as_millis() as u64,
+                    bonus: Bonus::from_time_bonus(time_control.bonus),
+                    move_limit_ms: move_limit_ms(&time_control),
+                },
+            );
+            self.queue.push(QueuedPlayer {
+                client_id,
+                variant,
+                time_control,
+            });
+            return Ok(());
+        };
+
+        let opponent = self.queue.remove(opponent);
+        let game_id = self.add_game(
+            variant,
+            time_control,
+            HashMap::from([
+                (Side::Attacker, opponent.client_id),
+                (Side::Defender, client_id),
+            ]),
+        )?;
+        self.start_game(game_id, now);
+
+        Ok(())
+    }
+
+    fn leave_queue(&mut self, client_id: ClientId) -> Result<(), &'static str> {
+        let queued = self.queue.len();
+        self.queue.retain(|queued| queued.client_id != client_id);
+
+        if self.queue.len() == queued {
+            return Err("the client isn't in the queue");
+        }
+
+        self.send(client_id, ServerMessage::LeftQueue);
+
+        Ok(())
+    }
+
+    /// Starts the clocks of a game both players joined.
+    fn start_game(&mut self, game_id: u64, now: Instant) {
+        let game = self.games.get_mut(&game_id).unwrap();
+        game.clock = Some(GameClocks::new(&game.time_control, now));
+
+        // players don't wait for another game while playing
+        let players: Vec<ClientId> = game.players.values().copied().collect();
+        self.queue
+            .retain(|queued| !players.contains(&queued.client_id));
+
+        let game = &self.games[&game_id];
+        let rules = game.game.rules();
+        let figures = game.game.iter_figures().collect();
+        let position = format_position(&figures, rules.rows, rules.cols);
+
+        for (side, player) in &game.players {
+            let opponent = game.players[&side.opponent()];
+
+            self.send(
+                *player,
+                ServerMessage::GameStarted {
+                    game_id,
+                    variant: game.variant.clone(),
+                    side: *side,
+                    opponent: self.clients[&opponent].name.clone().unwrap_or_default(),
+                    position: position.clone(),
+                    side_to_move: game.game.side_to_move(),
+                    attackers_ms: game.time_control.time.as_millis() as u64,
+                    defenders_ms: game.time_control.time.as_millis() as u64,
+                    bonus: Bonus::from_time_bonus(game.time_control.bonus),
+                    move_limit_ms: move_limit_ms(&game.time_control),
+                },
+            );
+        }
+    }
+
+    /// Gets a game that was started and isn't over yet, in which the client plays.
+    fn running_game(
+        &mut self,
+        client_id: ClientId,
+        game_id: u64,
+    ) -> Result<(&mut ServerGame, Side), &'static str> {
+        let Some(game) = self.games.get_mut(&game_id) else {
+            return Err("the game doesn't exist");
+        };
+
+        let Some(side) = game.side_of(client_id) else {
+            return Err("the game isn't played by this client");
+        };
+
+        if game.clock.is_none() {
+            return Err("the game didn't start yet");
+        }
+
+        Ok((game, side))
+    }
+
+    fn make_move(
+        &mut self,
+        client_id: ClientId,
+        game_id: u64,
+        from: &str,
+        to: &str,
+        now: Instant,
+    ) -> Result<(), &'static str> {
+        let (game, side) = self.running_game(client_id, game_id)?;
+
+        if game.game.side_to_move() != side {
+            return Err("it is the other side's turn");
+        }
+
+        // the time may have run out since the clocks were checked last
+        let clock = game.clock.as_ref().unwrap();
+        if clock.at(side, side, now).is_flagged() {
+            self.end_game(
+                game_id,
+                GameOutcome::Win(side.opponent()).into(),
+                GameOverReason::Timeout,
+            );
+            return Err("the time ran out before the move");
+        }
+
+        let rows = game.game.rules().rows;
+        let cols = game.game.rules().cols;
+        let mv = Move {
+            from: parse_square(from, rows, cols)?,
+            to: parse_square(to, rows, cols)?,
+        };
+
+        // the move is legal if the rules accept it
+        let captured = game.game.make_move(mv)?;
+
+        let clock = game.clock.as_mut().unwrap();
+        clock.switch(side, now);
+
+        // a pending draw offer is declined by making a move
+        game.draw_offer = None;
+
+        let side_to_move = game.game.side_to_move();
+        let attacker = clock.at(Side::Attacker, side_to_move, now);
+        let defender = clock.at(Side::Defender, side_to_move, now);
+        let outcome = game.game.outcome();
+
+        self.broadcast(
+            game_id,
+            ServerMessage::MoveMade {
+                game_id,
+                side,
+                from: format_square(mv.from, rows),
+                to: format_square(mv.to, rows),
+                captured: captured
+                    .into_iter()
+                    .map(|position| format_square(position, rows))
+                    .collect(),
+                side_to_move,
+            },
+        );
+        self.broadcast(
+            game_id,
+            ServerMessage::Clock {
+                game_id,
+                attackers_ms: attacker.remaining().as_millis() as u64,
+                defenders_ms: defender.remaining().as_millis() as u64,
+                attackers_periods: attacker.periods(),
+                defenders_periods: defender.periods(),
+            },
+        );
+
+        if let Some(outcome) = outcome {
+            self.end_game(game_id, outcome.into(), GameOverReason::Rules);
+        }
+
+        Ok(())
+    }
+
+    fn resign(&mut self, client_id: ClientId, game_id: u64) -> Result<(), &'static str> {
+        let (_, side) = self.running_game(client_id, game_id)?;
+
+        self.end_game(
+            game_id,
+            GameOutcome::Win(side.opponent()).into(),
+            GameOverReason::Resignation,
+        );
+
+        Ok(())
+    }
+
+    fn offer_draw(&mut self, client_id: ClientId, game_id: u64) -> Result<(), &'static str> {
+        let (game, side) = self.running_game(client_id, game_id)?;
+
+        if game.draw_offer == Some(side.opponent()) {
+            self.end_game(game_id, GameResult::Draw, GameOverReason::Agreement);
+            return Ok(());
+        }
+
+        game.draw_offer = Some(side);
+        self.broadcast(game_id, ServerMessage::DrawOffered { game_id, side });
+
+        Ok(())
+    }
+
+    fn decline_draw(&mut self, client_id: ClientId, game_id: u64) -> Result<(), &'static str> {
+        let (game, side) = self.running_game(client_id, game_id)?;
+
+        if game.draw_offer != Some(side.opponent()) {
+            return Err("the opponent didn't offer a draw");
+        }
+
+        game.draw_offer = None;
+        self.broadcast(game_id, ServerMessage::DrawDeclined { game_id, side });
+
+        Ok(())
+    }
+
+    fn chat(&mut self, client_id: ClientId, game_id: u64, text: String) -> Result<(), &'static str> {
+        let (_, side) = self.running_game(client_id, game_id)?;
+
+        let text = text.trim();
+        if text.is_empty() || text.chars().count() > MAX_MESSAGE_LENGTH {
+            return Err("chat messages should consist of 1 to 200 characters");
+        }
+
+        self.broadcast(
+            game_id,
+            ServerMessage::Chat {
+                game_id,
+                side,
+                text: text.to_string(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// Ends the games in which the side to move ran out of time.
+    fn check_clocks(&mut self, now: Instant) {
+        let timed_out: Vec<(u64, Side)> = self
+            .games
+            .iter()
+            .filter_map(|(game_id, game)| {
+                let clock = game.clock.as_ref()?;
+                let side_to_move = game.game.side_to_move();
+
+                clock
+                    .at(side_to_move, side_to_move, now)
+                    .is_flagged()
+                    .then_some((*game_id, side_to_move))
+            })
+            .collect();
+
+        for (game_id, side) in timed_out {
+            self.end_game(
+                game_id,
+                GameOutcome::Win(side.opponent()).into(),
+                GameOverReason::Timeout,
+            );
+        }
+    }
+
+    fn end_game(&mut self, game_id: u64, result: GameResult, reason: GameOverReason) {
+        self.broadcast(
+            game_id,
+            ServerMessage::GameOver {
+                game_id,
+                result,
+                reason,
+            },
+        );
+
+        self.games.remove(&game_id);
+    }
+}
+
+/// Checks that games with `time_control` can be played.
+fn check_time_control(time_control: &TimeControl) -> Result<(), &'static str> {
+    let bonus_is_positive = match time_control.bonus {
+        TimeBonus::None => true,
+        TimeBonus::Increment(time) | TimeBonus::Delay(time) => !time.is_zero(),
+        TimeBonus::ByoYomi { periods, period } => 0 < periods && !period.is_zero(),
+    };
+
+    if !bonus_is_positive || time_control.move_limit.is_some_and(|limit| limit.is_zero()) {
+        return Err("the bonus and the move limit should be positive");
+    }
+
+    // with byo-yomi the game may start in the periods
+    if Clock::new(time_control.clone()).is_flagged() {
+        return Err("the time of the game should be positive");
+    }
+
+    Ok(())
+}
+
+fn move_limit_ms(time_control: &TimeControl) -> Option<u64> {
+    time_control
+        .move_limit
+        .map(|move_limit| move_limit.as_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A client connected to the server, with the messages the server sent to it.
+    struct TestClient {
+        id: ClientId,
+        inbox: Receiver<ServerMessage>,
+    }
+
+    impl TestClient {
+        fn connect(server: &mut Server) -> Self {
+            let (sender, inbox) = mpsc::channel();
+
+            Self {
+                id: server.connect(sender),
+                inbox,
+            }
+        }
+
+        fn send(&self, server: &mut Server, text: &str, now: Instant) {
+            server.handle_message(self.id, text, now);
+        }
+
+        fn received(&self) -> Vec<ServerMessage> {
+            self.inbox.try_iter().collect()
+        }
+    }
+
+    // the attackers move, the time of the game is a minute
+    fn start_game(server: &mut Server, now: Instant) -> (TestClient, TestClient, u64) {
+        start_game_with(server, r#""time_ms": 60000"#, now)
+    }
+
+    // `time_control` are the time control fields of the `create_game` message
+    fn start_game_with(
+        server: &mut Server,
+        time_control: &str,
+        now: Instant,
+    ) -> (TestClient, TestClient, u64) {
+        let attacker = TestClient::connect(server);
+        let defender = TestClient::connect(server);
+
+        attacker.send(server, r#"{"type": "register", "name": "ragnar"}"#, now);
+        defender.send(server, r#"{"type": "register", "name": "bjorn"}"#, now);
+        attacker.send(
+            server,
+            &format!(
+                r#"{{"type": "create_game", "side": "attackers", {}}}"#,
+                time_control
+            ),
+            now,
+        );
+
+        let game_id = attacker
+            .received()
+            .into_iter()
+            .find_map(|message| match message {
+                ServerMessage::GameCreated { game_id, .. } => Some(game_id),
+                _ => None,
+            })
+            .expect("the game should be created");
+
+        defender.send(
+            server,
+            &format!(r#"{{"type": "join_game", "game_id": {}}}"#, game_id),
+            now,
+        );
+
+        for client in [&attacker, &defender] {
+            assert!(client
+                .received()
+                .iter()
+                .any(|message| matches!(message, ServerMessage::GameStarted { .. })));
+        }
+
+        (attacker, defender, game_id)
+    }
+
+    fn move_message(game_id: u64, from: &str, to: &str) -> String {
+        format!(
+            r#"{{"type": "move", "game_id": {}, "from": "{}", "to": "{}"}}"#,
+            game_id, from, to
+        )
+    }
+
+    fn game_over(messages: &[ServerMessage]) -> Option<(GameResult, GameOverReason)> {
+        messages.iter().find_map(|message| match message {
+            ServerMessage::GameOver { result, reason, .. } => Some((*result, *reason)),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn moves_are_sent_to_both_players() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, defender, game_id) = start_game(&mut server, start);
+
+        let now = start + Duration::from_secs(5);
+        attacker.send(&mut server, &move_message(game_id, "d1", "d4"), now);
+
+        for client in [&attacker, &defender] {
+            let messages = client.received();
+
+            assert!(messages.iter().any(|message| matches!(
+                message,
+                ServerMessage::MoveMade { from, to, side_to_move: Side::Defender, .. }
+                    if from == "d1" && to == "d4"
+            )));
+            assert!(messages.iter().any(|message| matches!(
+                message,
+                ServerMessage::Clock {
+                    attackers_ms: 55_000,
+                    defenders_ms: 60_000,
+                    ..
+                }
+            )));
+        }
+
+        // it is the turn of the defenders now
+        attacker.send(&mut server, &move_message(game_id, "d4", "d3"), now);
+        assert!(matches!(
+            attacker.received()[..],
+            [ServerMessage::Error { .. }]
+        ));
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut server = Server::default();
+        let now = Instant::now();
+        let (attacker, defender, game_id) = start_game(&mut server, now);
+
+        // through another figure
+        attacker.send(&mut server, &move_message(game_id, "a4", "a6"), now);
+        assert!(matches!(
+            attacker.received()[..],
+            [ServerMessage::Error { .. }]
+        ));
+        assert!(defender.received().is_empty());
+    }
+
+    #[test]
+    fn the_flag_falls_when_the_clocks_are_checked() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, defender, game_id) = start_game(&mut server, start);
+
+        server.check_clocks(start + Duration::from_secs(59));
+        assert!(game_over(&attacker.received()).is_none());
+
+        server.check_clocks(start + Duration::from_secs(60));
+
+        for client in [&attacker, &defender] {
+            assert_eq!(
+                game_over(&client.received()),
+                Some((GameResult::Defenders, GameOverReason::Timeout))
+            );
+        }
+
+        assert!(!server.games.contains_key(&game_id));
+    }
+
+    #[test]
+    fn moves_after_the_flag_fell_are_rejected() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, defender, game_id) = start_game(&mut server, start);
+
+        // the clocks weren't checked in the meantime
+        let now = start + Duration::from_secs(61);
+        attacker.send(&mut server, &move_message(game_id, "d1", "d4"), now);
+
+        let messages = attacker.received();
+        assert!(!messages
+            .iter()
+            .any(|message| matches!(message, ServerMessage::MoveMade { .. })));
+        assert_eq!(
+            game_over(&messages),
+            Some((GameResult::Defenders, GameOverReason::Timeout))
+        );
+        assert_eq!(
+            game_over(&defender.received()),
+            Some((GameResult::Defenders, GameOverReason::Timeout))
+        );
+    }
+
+    #[test]
+    fn the_increment_is_added_after_a_move() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, _, game_id) = start_game_with(
+            &mut server,
+            r#""time_ms": 60000, "bonus": {"type": "increment", "ms": 5000}"#,
+            start,
+        );
+
+        let now = start + Duration::from_secs(3);
+        attacker.send(&mut server, &move_message(game_id, "d1", "d4"), now);
+
+        assert!(attacker.received().iter().any(|message| matches!(
+            message,
+            ServerMessage::Clock {
+                attackers_ms: 62_000,
+                defenders_ms: 60_000,
+                ..
+            }
+        )));
+    }
+
+    #[test]
+    fn the_flag_falls_once_the_byo_yomi_periods_ran_out() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, _, _) = start_game_with(
+            &mut server,
+            r#""time_ms": 10000, "bonus": {"type": "byo_yomi", "periods": 2, "period_ms": 5000}"#,
+            start,
+        );
+
+        server.check_clocks(start + Duration::from_secs(19));
+        assert!(game_over(&attacker.received()).is_none());
+
+        server.check_clocks(start + Duration::from_secs(20));
+        assert_eq!(
+            game_over(&attacker.received()),
+            Some((GameResult::Defenders, GameOverReason::Timeout))
+        );
+    }
+
+    #[test]
+    fn the_flag_falls_when_a_move_takes_too_long() {
+        let mut server = Server::default();
+        let start = Instant::now();
+        let (attacker, _, _) = start_game_with(
+            &mut server,
+            r#""time_ms": 60000, "move_limit_ms": 10000"#,
+            start,
+        );
+
+        server.check_clocks(start + Duration::from_secs(10));
+        assert_eq!(
+            game_over(&attacker.received()),
+            Some((GameResult::Defenders, GameOverReason::Timeout))
+        );
+    }
+
+    #[test]
+    fn the_queue_matches_the_whole_time_control() {
+        let mut server = Server::default();
+        let now = Instant::now();
+        let ragnar = TestClient::connect(&mut server);
+        let bjorn = TestClient::connect(&mut server);
+
+        ragnar.send(&mut server, r#"{"type": "register", "name": "ragnar"}"#, now);
+        bjorn.send(&mut server, r#"{"type": "register", "name": "bjorn"}"#, now);
+        ragnar.send(
+            &mut server,
+            r#"{"type": "join_queue", "time_ms": 60000}"#,
+            now,
+        );
+        bjorn.send(
+            &mut server,
+            r#"{"type": "join_queue", "time_ms": 60000, "bonus": {"type": "delay", "ms": 3000}}"#,
+            now,
+        );
+
+        for client in [&ragnar, &bjorn] {
+            let messages = client.received();
+
+            assert!(messages
+                .iter()
+                .any(|message| matches!(message, ServerMessage::Queued { .. })));
+            assert!(!messages
+                .iter()
+                .any(|message| matches!(message, ServerMessage::GameStarted { .. })));
+        }
+    }
+}
@@ -0,0 +1,611 @@
+//! Plays matches between engines speaking the engine protocol, without the ECS.
+//!
+//! Every pair of engines plays the same number of games, switching sides after each game. The
+//! games are written to record files and the results are summarized with Elo estimates.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use crate::game::tafl::engine_protocol::*;
+use crate::game::tafl::figure::Side;
+use crate::game::tafl::notation::*;
+use crate::game::tafl::record::*;
+use crate::game::tafl::rules::*;
+use crate::game::tafl::time_control::{ClockState, TimeControl};
+
+// how long an engine may take to answer `hello`
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct TournamentOptions {
+    /// The commands starting the engines.
+    pub engines: Vec<EngineCommand>,
+    /// The number of games each pair of engines plays.
+    pub games: usize,
+    /// The time on the clock of each side at the start of a game.
+    pub time: Duration,
+    pub variant: String,
+    /// Games without a winner after this many moves are draws.
+    pub max_moves: usize,
+    /// The directory the record files are written to.
+    pub records: PathBuf,
+}
+
+/// Plays all games of the tournament and prints the results.
+pub fn run(options: &TournamentOptions) -> io::Result<()> {
+    let (rules, initial_position) = variant(&options.variant)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+
+    fs::create_dir_all(&options.records)?;
+
+    let mut base_names: Vec<String> = vec![];
+    for command in &options.engines {
+        base_names.push(engine_name(command)?);
+    }
+    let names = unique_names(&base_names);
+
+    let mut results: Vec<PairResult> = vec![];
+    let mut game_number = 0;
+
+    for first in 0..options.engines.len() {
+        for second in first + 1..options.engines.len() {
+            let mut result = PairResult {
+                first,
+                second,
+                ..Default::default()
+            };
+
+            for i in 0..options.games {
+                // the engines take turns playing the attackers
+                let (attacker, defender) = match i % 2 {
+                    0 => (first, second),
+                    _ => (second, first),
+                };
+
+                game_number += 1;
+
+                let game = play_game(
+                    &options.engines[attacker],
+                    &options.engines[defender],
+                    &rules,
+                    initial_position,
+                    options,
+                );
+
+                let record = RecordFile {
+                    variant: options.variant.clone(),
+                    time_control: TimeControl {
+                        time: options.time,
+                        ..TimeControl::default()
+                    },
+                    initial_position: initial_position.to_string(),
+                    first_side: Side::Attacker,
+                    entries: game.entries,
+                    outcome: Some(game.outcome),
+                };
+
+                let path = options
+                    .records
+                    .join(format!("game-{:0>4}.txt", game_number));
+                fs::write(
+                    &path,
+                    format!(
+                        "# attackers: {}\n# defenders: {}\n# {}\n{}",
+                        names[attacker],
+                        names[defender],
+                        game.reason,
+                        record.to_text()
+                    ),
+                )?;
+
+                let winner = match game.outcome {
+                    GameOutcome::Win(Side::Attacker) => Some(attacker),
+                    GameOutcome::Win(Side::Defender) => Some(defender),
+                    GameOutcome::Draw => None,
+                };
+
+                match winner {
+                    Some(winner) if winner == first => result.score.wins += 1,
+                    Some(_) => result.score.losses += 1,
+                    None => result.score.draws += 1,
+                }
+
+                println!(
+                    "game {}: {} (attackers) vs {} (defenders), {}, {}",
+                    game_number,
+                    names[attacker],
+                    names[defender],
+                    match winner {
+                        Some(winner) => format!("{} won", names[winner]),
+                        None => "draw".to_string(),
+                    },
+                    game.reason
+                );
+            }
+
+            results.push(result);
+        }
+    }
+
+    print_results(&names, &results);
+
+    Ok(())
+}
+
+/// The wins, draws and losses of an engine.
+#[derive(Debug, Clone, Copy, Default)]
+struct Score {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl Score {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    fn reversed(&self) -> Score {
+        Score {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    fn add(&mut self, other: Score) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+
+    /// Estimates the Elo difference to the opponents with the margin of a 95% confidence interval.
+    fn elo(&self) -> (f64, f64) {
+        let games = self.games() as f64;
+        if games == 0. {
+            return (0., 0.);
+        }
+
+        let score = (self.wins as f64 + 0.5 * self.draws as f64) / games;
+
+        // the standard deviation of the mean score of a game
+        let variance = (self.wins as f64 * (1. - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let deviation = (variance / games).sqrt();
+
+        let low = elo_difference(score - 1.96 * deviation);
+        let high = elo_difference(score + 1.96 * deviation);
+
+        (elo_difference(score), (high - low) / 2.)
+    }
+}
+
+/// Converts an expected score to the Elo difference it corresponds to.
+fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(0., 1.);
+    -400. * (1. / score - 1.).log10()
+}
+
+fn format_elo(elo: f64) -> String {
+    match elo.is_finite() {
+        // avoids printing -0
+        true if elo.abs() < 0.5 => "0".to_string(),
+        true => format!("{:+.0}", elo),
+        false => "-".to_string(),
+    }
+}
+
+/// The games played between two engines, from the perspective of the first one.
+#[derive(Debug, Clone, Default)]
+struct PairResult {
+    first: usize,
+    second: usize,
+    score: Score,
+}
+
+fn print_results(names: &[String], results: &[PairResult]) {
+    let width = names
+        .iter()
+        .map(|name| name.len())
+        .max()
+        .unwrap_or(0)
+        .max(6);
+
+    println!();
+    println!(
+        "{:<width$}  {:>5}  {:>5}  {:>5}  {:>5}  {:>6}  {:>6}",
+        "engine",
+        "games",
+        "wins",
+        "draws",
+        "losses",
+        "elo",
+        "+/-",
+        width = width
+    );
+
+    for (i, name) in names.iter().enumerate() {
+        let mut score = Score::default();
+        for result in results {
+            if result.first == i {
+                score.add(result.score);
+            } else if result.second == i {
+                score.add(result.score.reversed());
+            }
+        }
+
+        let (elo, margin) = score.elo();
+
+        println!(
+            "{:<width$}  {:>5}  {:>5}  {:>5}  {:>6}  {:>6}  {:>6}",
+            name,
+            score.games(),
+            score.wins,
+            score.draws,
+            score.losses,
+            format_elo(elo),
+            format_elo(margin).trim_start_matches('+'),
+            width = width
+        );
+    }
+
+    // with more than two engines the results of each pair are interesting as well
+    if 1 < results.len() {
+        println!();
+
+        for result in results {
+            let (elo, margin) = result.score.elo();
+
+            println!(
+                "{} vs {}: +{} ={} -{}, elo {} +/- {}",
+                names[result.first],
+                names[result.second],
+                result.score.wins,
+                result.score.draws,
+                result.score.losses,
+                format_elo(elo),
+                format_elo(margin).trim_start_matches('+'),
+            );
+        }
+    }
+}
+
+/// A finished game.
+struct PlayedGame {
+    entries: Vec<RecordEntry>,
+    outcome: GameOutcome,
+    /// Why the game ended, e.g. `defenders ran out of time`.
+    reason: String,
+}
+
+/// Plays a game between the engines started with the commands, the engines are stopped afterwards.
+fn play_game(
+    attacker: &EngineCommand,
+    defender: &EngineCommand,
+    rules: &Rules,
+    initial_position: &str,
+    options: &TournamentOptions,
+) -> PlayedGame {
+    let started = Instant::now();
+    let mut entries = vec![];
+
+    let finish = |entries, outcome, reason: String| PlayedGame {
+        entries,
+        outcome,
+        reason,
+    };
+
+    let mut engines = vec![];
+    for (side, command) in [(Side::Attacker, attacker), (Side::Defender, defender)] {
+        match Engine::start(command, rules) {
+            Ok(engine) => engines.push(engine),
+            Err(err) => {
+                return finish(
+                    entries,
+                    GameOutcome::Win(side.opponent()),
+                    format!("the {} engine failed to start: {}", format_side(side), err),
+                );
+            }
+        }
+    }
+
+    let mut game = match parse_position(initial_position)
+        .and_then(|parsed| TaflGame::new(rules.clone(), &parsed.figures, Side::Attacker))
+    {
+        Ok(game) => game,
+        Err(err) => return finish(entries, GameOutcome::Draw, err.to_string()),
+    };
+
+    let mut clock = [options.time, options.time];
+    let mut moves = 0;
+
+    let (outcome, reason) = loop {
+        if let Some(outcome) = game.outcome() {
+            break (outcome, "the game ended by the rules".to_string());
+        }
+
+        if options.max_moves <= moves {
+            break (
+                GameOutcome::Draw,
+                format!("no winner after {} moves", moves),
+            );
+        }
+
+        let side = game.side_to_move();
+        let index = match side {
+            Side::Attacker => 0,
+            Side::Defender => 1,
+        };
+
+        let position = format_position(
+            &game.iter_figures().collect(),
+            game.rules().rows,
+            game.rules().cols,
+        );
+
+        let engine_clock = EngineClock {
+            attacker: ClockState {
+                remaining: clock[0],
+                periods: 0,
+            },
+            defender: ClockState {
+                remaining: clock[1],
+                periods: 0,
+            },
+            time_control: TimeControl {
+                time: options.time,
+                ..TimeControl::default()
+            },
+        };
+
+        let requested = Instant::now();
+        let reply = engines[index].play(position, engine_clock, side, clock[index]);
+        let elapsed = requested.elapsed();
+
+        if clock[index] <= elapsed {
+            break (
+                GameOutcome::Win(side.opponent()),
+                format!("the {} ran out of time", format_side(side)),
+            );
+        }
+        clock[index] -= elapsed;
+
+        let mv = match reply {
+            Ok(mv) => mv,
+            Err(err) => {
+                break (
+                    GameOutcome::Win(side.opponent()),
+                    format!("the {} engine failed: {}", format_side(side), err),
+                );
+            }
+        };
+
+        if let Err(err) = game.make_move(mv) {
+            break (
+                GameOutcome::Win(side.opponent()),
+                format!(
+                    "the {} made the illegal move {}: {}",
+                    format_side(side),
+                    format_move(mv.from, mv.to, rules.rows),
+                    err
+                ),
+            );
+        }
+
+        entries.push(RecordEntry::Move {
+            side,
+            mv,
+            time: started.elapsed(),
+        });
+        moves += 1;
+    };
+
+    for engine in engines {
+        engine.stop();
+    }
+
+    finish(entries, outcome, reason)
+}
+
+/// Numbers the engines introducing themselves with the same name, e.g. the same engine playing
+/// with different options.
+fn unique_names(base_names: &[String]) -> Vec<String> {
+    base_names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let count = base_names[..i]
+                .iter()
+                .filter(|other| *other == name)
+                .count();
+
+            match count {
+                0 => name.clone(),
+                _ => format!("{} #{}", name, count + 1),
+            }
+        })
+        .collect()
+}
+
+/// Starts the engine with `command` once to ask for its name.
+fn engine_name(command: &EngineCommand) -> io::Result<String> {
+    let mut process = EngineProcess::start(command)?;
+    process.send(&GuiMessage::Hello)?;
+
+    let name = loop {
+        match process.receive_timeout(HELLO_TIMEOUT) {
+            Ok(line) => match EngineMessage::parse(&line, 0, 0) {
+                Ok(EngineMessage::Hello { name }) if !name.is_empty() => break name,
+                _ => continue,
+            },
+            Err(_) => break command.to_string(),
+        }
+    };
+
+    process.stop();
+
+    Ok(name)
+}
+
+/// An engine playing a game on a board of the provided size.
+struct Engine {
+    process: EngineProcess,
+    rows: usize,
+    cols: usize,
+}
+
+impl Engine {
+    /// Starts the engine with `command` and sends it the rules.
+    fn start(command: &EngineCommand, rules: &Rules) -> io::Result<Self> {
+        let mut engine = Self {
+            process: EngineProcess::start(command)?,
+            rows: rules.rows,
+            cols: rules.cols,
+        };
+
+        engine.send(GuiMessage::Hello)?;
+        engine.send(GuiMessage::Rules {
+            rows: rules.rows,
+            cols: rules.cols,
+            throne_position: rules.throne_position,
+            end_positions: rules.end_positions.clone(),
+        })?;
+
+        Ok(engine)
+    }
+
+    fn send(&mut self, message: GuiMessage) -> io::Result<()> {
+        self.process.send(&message)
+    }
+
+    /// Asks the engine for a move of `side` in `position`, waiting at most `limit`.
+    fn play(
+        &mut self,
+        position: String,
+        clock: EngineClock,
+        side: Side,
+        limit: Duration,
+    ) -> Result<Move, String> {
+        let failed = |err: io::Error| err.to_string();
+
+        self.send(GuiMessage::Position { position })
+            .map_err(failed)?;
+        self.send(GuiMessage::Clock { clock }).map_err(failed)?;
+        self.send(GuiMessage::Play { side }).map_err(failed)?;
+
+        let deadline = Instant::now() + limit;
+
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+
+            let line = match self.process.receive_timeout(timeout) {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => return Err("no move in time".to_string()),
+                Err(RecvTimeoutError::Disconnected) => return Err("the engine exited".to_string()),
+            };
+
+            match EngineMessage::parse(&line, self.rows, self.cols) {
+                Ok(EngineMessage::Move { from, to }) => return Ok(Move { from, to }),
+                Ok(EngineMessage::Error { message }) => return Err(message),
+                // e.g. the answer to `hello`, unknown messages are ignored as well
+                Ok(EngineMessage::Hello { .. }) | Err(_) => continue,
+            }
+        }
+    }
+
+    fn stop(self) {
+        self.process.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(wins: u32, draws: u32, losses: u32) -> Score {
+        Score {
+            wins,
+            draws,
+            losses,
+        }
+    }
+
+    #[test]
+    fn engines_with_the_same_name_are_numbered() {
+        let names = |base_names: &[&str]| {
+            unique_names(
+                &base_names
+                    .iter()
+                    .map(|name| name.to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        assert_eq!(
+            names(&["foo", "foo", "bar", "foo"]),
+            ["foo", "foo #2", "bar", "foo #3"]
+        );
+        assert_eq!(names(&["foobar", "foo"]), ["foobar", "foo"]);
+    }
+
+    #[test]
+    fn elo_difference_of_known_scores() {
+        assert_eq!(elo_difference(0.5), 0.);
+        assert!((elo_difference(0.75) - 190.85).abs() < 0.01);
+        assert!((elo_difference(0.25) + 190.85).abs() < 0.01);
+        assert_eq!(elo_difference(1.), f64::INFINITY);
+        assert_eq!(elo_difference(0.), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn even_scores_have_no_elo_difference() {
+        let (elo, margin) = score(3, 4, 3).elo();
+
+        assert_eq!(elo, 0.);
+        assert!(0. < margin && margin.is_finite());
+        assert_eq!(format_elo(elo), "0");
+    }
+
+    #[test]
+    fn elo_of_won_and_lost_games() {
+        let (elo, margin) = score(30, 0, 10).elo();
+        assert!((elo - 190.85).abs() < 0.01);
+        assert!(0. < margin && margin.is_finite());
+        assert_eq!(format_elo(elo), "+191");
+
+        // the confidence interval of few games reaches a perfect score
+        let (elo, margin) = score(6, 0, 2).elo();
+        assert!((elo - 190.85).abs() < 0.01);
+        assert_eq!(margin, f64::INFINITY);
+
+        let (elo, _) = score(2, 0, 6).elo();
+        assert!((elo + 190.85).abs() < 0.01);
+        assert_eq!(format_elo(elo), "-191");
+
+        // draws count as half a win
+        let (elo, _) = score(2, 4, 0).elo();
+        assert!((elo - 120.41).abs() < 0.01);
+    }
+
+    #[test]
+    fn elo_of_perfect_scores_is_not_printed() {
+        let (elo, margin) = score(5, 0, 0).elo();
+        assert_eq!(elo, f64::INFINITY);
+        assert_eq!(format_elo(elo), "-");
+        assert_eq!(format_elo(margin), "-");
+
+        let (elo, margin) = score(0, 0, 5).elo();
+        assert_eq!(elo, f64::NEG_INFINITY);
+        assert_eq!(format_elo(elo), "-");
+        assert_eq!(format_elo(margin), "-");
+    }
+
+    #[test]
+    fn no_games_have_no_elo_difference() {
+        assert_eq!(score(0, 0, 0).elo(), (0., 0.));
+    }
+}
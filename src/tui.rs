@@ -0,0 +1,239 @@
+use std::io::{self, BufRead, Write};
+use std::time::Instant;
+
+use hnefatafl::game::tafl::board::Position;
+use hnefatafl::game::tafl::figure::*;
+use hnefatafl::game::tafl::game_setup::GameSetup;
+use hnefatafl::game::tafl::notation::*;
+use hnefatafl::game::tafl::record::RecordEntry;
+use hnefatafl::game::tafl::rules::*;
+use hnefatafl::game::tafl::time_control::{Clock, TimeControl};
+
+const HELP: &str = "\
+Commands:
+    <from>-<to>    move a figure, e.g. d1-d3
+    moves <from>   list the squares the figure on <from> can move to
+    resign         give up the game
+    quit           leave without finishing the game
+    help           print this message";
+
+/// Plays a hot-seat game in the terminal with `time_control`, reading the moves from stdin.
+///
+/// The game continues from `game_setup`, otherwise it is a new game of hnefatafl with the
+/// attackers to move.
+///
+/// Returns when the game ends, `quit` is entered or stdin is closed.
+pub fn run(time_control: TimeControl, game_setup: Option<GameSetup>) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    let game_setup = match game_setup {
+        Some(game_setup) => game_setup,
+        None => GameSetup::new("hnefatafl", None, Side::Attacker, vec![], None)
+            .map_err(to_io_error)?,
+    };
+
+    let (mut game, mut clock, mut captured) =
+        replay(&game_setup, time_control).map_err(to_io_error)?;
+
+    writeln!(stdout, "{}", HELP)?;
+
+    let mut lines = stdin.lock().lines();
+
+    let outcome = loop {
+        if let Some(outcome) = game.outcome().or(game_setup.outcome) {
+            writeln!(stdout, "{}", render(&game, &clock, &captured))?;
+            break outcome;
+        }
+
+        let side = game.side_to_move();
+
+        writeln!(stdout, "{}", render(&game, &clock, &captured))?;
+        write!(stdout, "{}> ", format_side(side))?;
+        stdout.flush()?;
+
+        let started = Instant::now();
+        let Some(line) = lines.next() else {
+            return Ok(());
+        };
+        let line = line?;
+
+        // the clock can only be checked once the player entered something
+        let side_clock = &mut clock[index(side)];
+        side_clock.tick(started.elapsed());
+        if side_clock.is_flagged() {
+            writeln!(stdout, "the {} ran out of time", format_side(side))?;
+            break GameOutcome::Win(side.opponent());
+        }
+
+        let rows = game.rules().rows;
+        let cols = game.rules().cols;
+
+        let line = line.trim();
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+
+        match command {
+            "" => {}
+            "help" => writeln!(stdout, "{}", HELP)?,
+            "quit" => return Ok(()),
+            "resign" => {
+                writeln!(stdout, "the {} resigned", format_side(side))?;
+                break GameOutcome::Win(side.opponent());
+            }
+            "moves" => match parse_square(argument.trim(), rows, cols) {
+                Ok(from) => {
+                    let squares: Vec<String> = game
+                        .possible_moves(from)
+                        .into_iter()
+                        .map(|to| format_square(to, rows))
+                        .collect();
+
+                    match squares.is_empty() {
+                        true => writeln!(stdout, "no moves from {}", argument.trim())?,
+                        false => writeln!(stdout, "{}", squares.join(" "))?,
+                    }
+                }
+                Err(err) => writeln!(stdout, "{}", err)?,
+            },
+            _ => match parse_move(line, rows, cols) {
+                Ok((from, to)) => {
+                    // the captured figures are gone after the move
+                    let before = game.clone();
+
+                    match game.make_move(Move { from, to }) {
+                        Ok(captures) => {
+                            captured[index(side)].extend(
+                                captures
+                                    .into_iter()
+                                    .filter_map(|position| before.get(position)),
+                            );
+                            clock[index(side)].end_move();
+                        }
+                        Err(err) => writeln!(stdout, "{}", err)?,
+                    }
+                }
+                Err(err) => writeln!(stdout, "{}, enter help for the commands", err)?,
+            },
+        }
+    };
+
+    match outcome {
+        GameOutcome::Win(side) => writeln!(stdout, "the {} won", format_side(side))?,
+        GameOutcome::Draw => writeln!(stdout, "the game is a draw")?,
+    }
+
+    Ok(())
+}
+
+/// The figures each side captured.
+type Captured = [Vec<FigureType>; 2];
+
+/// Replays the moves of `game_setup` from its initial position to get the clocks and the figures
+/// each side captured so far.
+fn replay(
+    game_setup: &GameSetup,
+    time_control: TimeControl,
+) -> Result<(TaflGame, [Clock; 2], Captured), &'static str> {
+    let (rules, _) = variant(&game_setup.variant)?;
+    let figures = parse_position(&game_setup.initial_position)?.figures;
+    let mut game = TaflGame::new(rules, &figures, game_setup.first_side)?;
+
+    let mut clock = [Clock::new(time_control.clone()), Clock::new(time_control)];
+    let mut captured: Captured = [vec![], vec![]];
+    // each side used the time between the previous move and its own
+    let mut previous = std::time::Duration::ZERO;
+
+    for entry in &game_setup.entries {
+        if let RecordEntry::Move { side, mv, time } = entry {
+            let before = game.clone();
+            let captures = game.make_move(*mv)?;
+
+            captured[index(*side)].extend(
+                captures
+                    .into_iter()
+                    .filter_map(|position| before.get(position)),
+            );
+
+            clock[index(*side)].tick(time.saturating_sub(previous));
+            clock[index(*side)].end_move();
+            previous = *time;
+        }
+    }
+
+    Ok((game, clock, captured))
+}
+
+fn index(side: Side) -> usize {
+    match side {
+        Side::Attacker => 0,
+        Side::Defender => 1,
+    }
+}
+
+fn to_io_error(err: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+/// Draws the board as a grid of characters with the clocks and the captured figures below.
+///
+/// Figures show their letter of the position strings, empty fields whether they are the throne or
+/// one of the fields the king escapes to.
+fn render(game: &TaflGame, clock: &[Clock; 2], captured: &Captured) -> String {
+    let rules = game.rules();
+    let rows = rules.rows;
+    let cols = rules.cols;
+    let label_width = rows.to_string().len();
+
+    let mut result = String::from("\n");
+
+    for y in 0..rows {
+        result.push_str(&format!("{:>width$} ", rows - y, width = label_width));
+
+        for x in 0..cols {
+            let position = Position { x, y };
+
+            let c = match game.get(position) {
+                Some(figure_type) => figure_type_to_char(figure_type),
+                None if position == rules.throne_position => '+',
+                None if rules.end_positions.contains(&position) => '#',
+                None => '.',
+            };
+
+            result.push(' ');
+            result.push(c);
+        }
+
+        result.push('\n');
+    }
+
+    result.push_str(&" ".repeat(label_width + 1));
+    for x in 0..cols {
+        result.push(' ');
+        result.push((b'a' + x as u8) as char);
+    }
+    result.push_str("\n\n");
+
+    result.push_str("t attacker, T defender, K king, . field, + throne, # escape field\n");
+
+    for side in [Side::Attacker, Side::Defender] {
+        let figures: String = captured[index(side)]
+            .iter()
+            .map(|figure_type| figure_type_to_char(*figure_type))
+            .collect();
+
+        result.push_str(&format!(
+            "{:<10} {}  captured: {}\n",
+            format_side(side),
+            // the move limit is shown on a line of its own in the window
+            clock[index(side)]
+                .text(side == game.side_to_move())
+                .replace('\n', ", "),
+            match figures.is_empty() {
+                true => "-",
+                false => &figures,
+            }
+        ));
+    }
+
+    result
+}
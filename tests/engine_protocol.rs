@@ -0,0 +1,165 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use bevy::utils::HashMap;
+use hnefatafl::game::tafl::engine_protocol::*;
+use hnefatafl::game::tafl::figure::{FigureType, Side};
+use hnefatafl::game::tafl::notation::*;
+use hnefatafl::game::tafl::rules::*;
+use hnefatafl::game::tafl::time_control::ClockState;
+
+/// An engine process the tests talk to like the GUI does.
+struct Engine {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Engine {
+    fn start(program: &str, args: &[&str]) -> Self {
+        let mut process = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("the engine should start");
+
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+
+        Self {
+            process,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn send(&mut self, message: GuiMessage) {
+        writeln!(self.stdin, "{}", message.to_line()).unwrap();
+    }
+
+    fn receive(&mut self, rows: usize, cols: usize) -> EngineMessage {
+        let mut line = String::new();
+        self.stdout.read_line(&mut line).unwrap();
+
+        EngineMessage::parse(&line, rows, cols).expect("the engine should send a known message")
+    }
+
+    /// Says goodbye and checks that the engine exits on its own.
+    fn stop(mut self) {
+        self.send(GuiMessage::Goodbye);
+        assert!(self.process.wait().unwrap().success());
+    }
+}
+
+/// Sends the rules and the initial position of hnefatafl and asks for a move of `side`.
+fn request_move(engine: &mut Engine, game: &TaflGame, side: Side) -> EngineMessage {
+    let rules = game.rules().clone();
+    let figures: HashMap<_, FigureType> = game.iter_figures().collect();
+
+    engine.send(GuiMessage::Rules {
+        rows: rules.rows,
+        cols: rules.cols,
+        throne_position: rules.throne_position,
+        end_positions: rules.end_positions.clone(),
+    });
+    engine.send(GuiMessage::Position {
+        position: format_position(&figures, rules.rows, rules.cols),
+    });
+    engine.send(GuiMessage::Play { side });
+
+    engine.receive(rules.rows, rules.cols)
+}
+
+fn hnefatafl(side: Side) -> TaflGame {
+    let (rules, position) = variant("hnefatafl").unwrap();
+    let position = parse_position(position).unwrap();
+
+    TaflGame::new(rules, &position.figures, side).unwrap()
+}
+
+fn assert_legal(game: &TaflGame, message: EngineMessage) {
+    let EngineMessage::Move { from, to } = message else {
+        panic!("the engine should answer with a move, not {:?}", message);
+    };
+
+    assert!(game.legal_moves().contains(&Move { from, to }));
+}
+
+#[test]
+fn dummy_engine_plays_legal_moves() {
+    let mut engine = Engine::start(env!("CARGO_BIN_EXE_hnefatafl-dummy-engine"), &[]);
+
+    engine.send(GuiMessage::Hello);
+    assert!(matches!(
+        engine.receive(11, 11),
+        EngineMessage::Hello { .. }
+    ));
+
+    for side in [Side::Attacker, Side::Defender] {
+        let game = hnefatafl(side);
+        let message = request_move(&mut engine, &game, side);
+        assert_legal(&game, message);
+    }
+
+    engine.stop();
+}
+
+#[test]
+fn dummy_engine_follows_its_script() {
+    let mut engine = Engine::start(
+        env!("CARGO_BIN_EXE_hnefatafl-dummy-engine"),
+        &["move a1-a2", "error thinking too hard"],
+    );
+
+    let game = hnefatafl(Side::Attacker);
+
+    assert_eq!(
+        request_move(&mut engine, &game, Side::Attacker),
+        EngineMessage::Move {
+            from: parse_square("a1", 11, 11).unwrap(),
+            to: parse_square("a2", 11, 11).unwrap(),
+        }
+    );
+    assert_eq!(
+        request_move(&mut engine, &game, Side::Attacker),
+        EngineMessage::Error {
+            message: "thinking too hard".to_string(),
+        }
+    );
+
+    // falls back to legal moves once the script is over
+    let message = request_move(&mut engine, &game, Side::Attacker);
+    assert_legal(&game, message);
+
+    engine.stop();
+}
+
+#[test]
+fn built_in_engine_plays_legal_moves() {
+    let mut engine = Engine::start(env!("CARGO_BIN_EXE_hnefatafl"), &["--engine"]);
+
+    engine.send(GuiMessage::Hello);
+    assert!(matches!(
+        engine.receive(11, 11),
+        EngineMessage::Hello { .. }
+    ));
+
+    let game = hnefatafl(Side::Attacker);
+    let side_clock = ClockState {
+        remaining: std::time::Duration::from_millis(3_000),
+        periods: 0,
+    };
+    engine.send(GuiMessage::Clock {
+        clock: EngineClock {
+            attacker: side_clock,
+            defender: side_clock,
+            ..EngineClock::default()
+        },
+    });
+
+    let message = request_move(&mut engine, &game, Side::Attacker);
+    assert_legal(&game, message);
+
+    engine.stop();
+}
@@ -0,0 +1,34 @@
+use std::fs;
+use std::process::Command;
+
+use hnefatafl::game::tafl::engine_protocol::EngineCommand;
+
+fn dummy_engine(replies: &[&str]) -> String {
+    EngineCommand {
+        program: env!("CARGO_BIN_EXE_hnefatafl-dummy-engine").to_string(),
+        args: replies.iter().map(|reply| reply.to_string()).collect(),
+    }
+    .to_string()
+}
+
+#[test]
+fn unknown_engine_messages_are_ignored() {
+    let records = std::env::temp_dir().join(format!("hnefatafl-match-{}", std::process::id()));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_hnefatafl-match"))
+        .args(["--engine", &dummy_engine(&["info thinking\nmove a4-c4"])])
+        .args(["--engine", &dummy_engine(&[])])
+        .args(["--games", "1", "--max-moves", "2", "--time", "10"])
+        .arg("--records")
+        .arg(&records)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let record = fs::read_to_string(records.join("game-0001.txt")).unwrap();
+    fs::remove_dir_all(&records).unwrap();
+
+    assert!(record.contains("# no winner after 2 moves"), "{}", record);
+    assert!(record.contains("move attackers a4-c4 "), "{}", record);
+    assert!(record.ends_with("result draw\n"), "{}", record);
+}